@@ -64,10 +64,10 @@ fn main() -> anyhow::Result<()> {
 
 fn print_help() {
     println!("decode_aec_payload (example)");
-    println!("");
+    println!();
     println!("Usage:");
     println!("  decode_aec_payload --payload <path> --samples <n>");
-    println!("");
+    println!();
     println!("Defaults:");
     println!("  --payload aec_payload.bin");
     println!("  --samples 1038240");
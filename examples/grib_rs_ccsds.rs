@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use grib::Grib2SubmessageDecoder;
+use rust_aec::grib_rs::{decode_submessage, is_ccsds};
+
+fn main() -> anyhow::Result<()> {
+    // Usage:
+    //   cargo run -p rust-aec --features grib --example grib_rs_ccsds -- data.grib2
+    //
+    // grib-rs parses the file; template 5.42 (CCSDS) fields are decoded by rust-aec, everything
+    // else by grib-rs itself, so no libaec is needed.
+    let path = std::env::args().nth(1).map(PathBuf::from).context("usage: grib_rs_ccsds <file.grib2>")?;
+    let data = std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let grib2 = grib::from_bytes(&data[..])?;
+
+    for (index, submessage) in grib2.iter() {
+        let (decoder, values) = if is_ccsds(&submessage) {
+            ("rust-aec", decode_submessage(&data, &submessage)?)
+        } else {
+            ("grib-rs", Grib2SubmessageDecoder::from(submessage)?.dispatch()?.collect())
+        };
+        let present: Vec<f32> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+        let min = present.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = present.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        println!(
+            "{index:?}: {} points, {} with values, min={min}, max={max} (decoded by {decoder})",
+            values.len(),
+            present.len()
+        );
+    }
+    Ok(())
+}
@@ -1,9 +1,12 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 use rust_aec::{flags_from_grib2_ccsds_flags, AecParams, DecodeStatus, Decoder, Flush};
 
 fn main() -> anyhow::Result<()> {
+    // This spells out the push/decode/flush loop; with the `io` feature,
+    // `rust_aec::io::copy_decode` runs the same loop from any `Read` into any `Write`.
+    //
     // Minimal argument parsing (no clap dependency).
     // Usage:
     //   cargo run -p rust-aec --example stream_decode_aec_payload -- --payload aec_payload.bin --samples 1038240
@@ -103,7 +106,7 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn print_summary(payload_path: &PathBuf, payload: &[u8], samples: usize, decoded: &[u8], dec: &Decoder) {
+fn print_summary(payload_path: &Path, payload: &[u8], samples: usize, decoded: &[u8], dec: &Decoder) {
     println!("payload: {} ({} bytes)", payload_path.display(), payload.len());
     println!("samples: {samples}");
     println!("decoded bytes: {}", decoded.len());
@@ -114,10 +117,10 @@ fn print_summary(payload_path: &PathBuf, payload: &[u8], samples: usize, decoded
 
 fn print_help() {
     println!("stream_decode_aec_payload (example)");
-    println!("");
+    println!();
     println!("Usage:");
     println!("  stream_decode_aec_payload --payload <path> --samples <n> [--in-chunk <n>] [--out-chunk <n>]");
-    println!("");
+    println!();
     println!("Defaults:");
     println!("  --payload aec_payload.bin");
     println!("  --samples 1038240");
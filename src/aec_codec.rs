@@ -0,0 +1,65 @@
+//! [`AecCodec`]: encoding and decoding whole payloads with one shared [`AecParams`].
+//!
+//! Archival code that handles several codecs through one interface can hold a
+//! [`crate::AecSession`] as an `AecCodec`: it decodes with its cached state and encodes with the
+//! one-shot [`crate::encode`]. Other implementors only need [`AecCodec::params`]; both directions
+//! default to [`crate::encode`] / [`crate::decode`].
+//!
+//! ```
+//! use rust_aec::{AecCodec, AecFlags, AecParams, AecSession};
+//!
+//! fn round_trip(codec: &mut impl AecCodec, samples: &[u8]) -> Result<Vec<u8>, rust_aec::AecError> {
+//!     let payload = codec.encode_payload(samples)?;
+//!     codec.decode_payload(&payload, samples.len())
+//! }
+//!
+//! let mut session = AecSession::new(AecParams::new(8, 16, 64, AecFlags::DATA_PREPROCESS))?;
+//! let samples: Vec<u8> = (0..200).map(|i| (i % 50) as u8).collect();
+//! assert_eq!(round_trip(&mut session, &samples)?, samples);
+//! # Ok::<(), rust_aec::AecError>(())
+//! ```
+//!
+//! For byte-stream pipelines rather than whole payloads, the `codec` module (feature
+//! `compression-codecs`) wraps the streaming [`crate::Encoder`] and [`crate::Decoder`] in the
+//! `compression_codecs` encoder and decoder traits.
+
+use crate::error::AecError;
+use crate::params::AecParams;
+
+/// Symmetric whole-payload AEC coding with the parameters of [`AecCodec::params`].
+pub trait AecCodec {
+    /// Parameters both directions code with.
+    fn params(&self) -> AecParams;
+
+    /// Encode packed sample bytes, laid out as [`crate::encode`] expects them, into a complete
+    /// payload.
+    fn encode_payload(&mut self, samples: &[u8]) -> Result<Vec<u8>, AecError> {
+        crate::encode(samples, self.params())
+    }
+
+    /// Decode a complete payload of `output_samples` samples into packed sample bytes.
+    fn decode_payload(&mut self, payload: &[u8], output_samples: usize) -> Result<Vec<u8>, AecError> {
+        crate::decode(payload, self.params(), output_samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AecFlags, AecSession};
+
+    #[test]
+    fn sessions_round_trip_through_the_trait() {
+        let params = AecParams::new(12, 16, 8, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+        let samples: Vec<u8> = (0..700u16).flat_map(|i| ((i * 37) % 4096).to_be_bytes()).collect();
+
+        let mut session = AecSession::new(params).unwrap();
+        let codec: &mut dyn AecCodec = &mut session;
+        let payload = codec.encode_payload(&samples).unwrap();
+        assert_eq!(payload, crate::encode(&samples, params).unwrap());
+        assert_eq!(codec.decode_payload(&payload, 700).unwrap(), samples);
+        // The session's cached state serves more than one payload.
+        let payload = codec.encode_payload(&samples[..100]).unwrap();
+        assert_eq!(codec.decode_payload(&payload, 50).unwrap(), &samples[..100]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Self-differential fuzzer: one-shot `decode` versus the streaming `Decoder`.
+//!
+//! Feeds identical random payload/parameter combinations through both decoders, pushing input
+//! and pulling output in random chunk sizes, and reports every case where the results are not
+//! byte-identical (or not the same kind of error), or where the decoder state hashes differ.
+//!
+//! Usage:
+//!   aec-difffuzz [--iterations N] [--seed S] [--max-len BYTES] [--keep-going]
+
+use rust_aec::{decode_with_report, AecError, AecFlags, AecParams, DecodeOptions, DecodeStatus, Decoder, Flush};
+
+/// Decoded bytes and the decoder state hash.
+type Outcome = Result<(Vec<u8>, Option<u64>), AecError>;
+
+/// Small deterministic xorshift generator, so failures are reproducible from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// One generated input, with the chunking seed the streaming side uses.
+#[derive(Debug)]
+struct Case {
+    params: AecParams,
+    output_samples: usize,
+    payload: Vec<u8>,
+    chunk_seed: u64,
+}
+
+fn generate(rng: &mut Rng, max_len: usize) -> Case {
+    let bits_per_sample = rng.pick(&[1u8, 2, 3, 4, 5, 7, 8, 9, 12, 15, 16, 17, 20, 24, 25, 31, 32]);
+    let block_size = rng.pick(&[8u32, 16, 32, 64]);
+    let rsi = rng.pick(&[1u32, 2, 3, 4, 16, 128]);
+    let flags = AecFlags::from_bits_truncate(rng.next() as u32);
+    let params = AecParams::new(bits_per_sample, block_size, rsi, flags);
+
+    // Long runs of one byte reach zero blocks, low-entropy and uncompressed options more often.
+    let len = rng.below(max_len as u64 + 1) as usize;
+    let fill = rng.pick(&[0x00u8, 0xff, 0x0f, 0x80]);
+    let fill_odds = rng.pick(&[2u64, 4, 16]);
+    let payload = (0..len).map(|_| if rng.below(fill_odds) == 0 { rng.next() as u8 } else { fill }).collect();
+
+    let output_samples = rng.below(600) as usize;
+    Case { params, output_samples, payload, chunk_seed: rng.next() | 1 }
+}
+
+/// Drive the streaming decoder with random input and output chunk sizes.
+fn decode_streaming(case: &Case, options: &DecodeOptions) -> Outcome {
+    let mut rng = Rng(case.chunk_seed);
+    let mut dec = Decoder::with_options(case.params, case.output_samples, options)?;
+    let mut out = Vec::new();
+    let mut fed = 0usize;
+    let mut idle_calls = 0u32;
+    loop {
+        let flush = if fed == case.payload.len() { Flush::Flush } else { Flush::NoFlush };
+        let mut buf = vec![0u8; 1 + rng.below(48) as usize];
+        let (written, status) = dec.decode(&mut buf, flush)?;
+        out.extend_from_slice(&buf[..written]);
+        idle_calls = if written == 0 { idle_calls + 1 } else { 0 };
+        match status {
+            DecodeStatus::Finished => return Ok((out, dec.report().state_hash)),
+            DecodeStatus::NeedOutput => {}
+            DecodeStatus::NeedInput if fed < case.payload.len() => {
+                let n = (1 + rng.below(16) as usize).min(case.payload.len() - fed);
+                dec.push_input(&case.payload[fed..fed + n]);
+                fed += n;
+                idle_calls = 0;
+            }
+            DecodeStatus::NeedInput => return Err(AecError::InvalidInput("NeedInput after Flush::Flush")),
+        }
+        if idle_calls > 64 {
+            return Err(AecError::InvalidInput("streaming decoder made no progress"));
+        }
+    }
+}
+
+/// Error identity compared across the two decoders; positions may legitimately differ.
+fn error_kind(e: &AecError) -> String {
+    match e.untagged() {
+        AecError::UnexpectedEof { .. } | AecError::UnexpectedEofDuringDecode { .. } => "unexpected EOF".to_string(),
+        AecError::LimitExceeded { what, .. } => format!("limit exceeded: {what}"),
+        other => other.to_string(),
+    }
+}
+
+/// Describe how the two results differ, if they do.
+fn divergence(one_shot: &Outcome, streaming: &Outcome) -> Option<String> {
+    match (one_shot, streaming) {
+        (Ok((a, ha)), Ok((b, hb))) if a == b && ha != hb => Some(format!("state hashes differ: {ha:x?} vs {hb:x?}")),
+        (Ok((a, _)), Ok((b, _))) if a == b => None,
+        (Ok((a, _)), Ok((b, _))) => {
+            let at = a.iter().zip(b).position(|(x, y)| x != y).unwrap_or(a.len().min(b.len()));
+            Some(format!("outputs differ at byte {at} (one-shot {} bytes, streaming {} bytes)", a.len(), b.len()))
+        }
+        (Err(a), Err(b)) if error_kind(a) == error_kind(b) => None,
+        (Err(a), Err(b)) => Some(format!("errors differ: one-shot `{a}`, streaming `{b}`")),
+        (Ok((a, _)), Err(b)) => Some(format!("one-shot produced {} bytes, streaming failed: {b}", a.len())),
+        (Err(a), Ok((b, _))) => Some(format!("one-shot failed: {a}, streaming produced {} bytes", b.len())),
+    }
+}
+
+fn print_help() {
+    println!("aec-difffuzz: compare one-shot and streaming decodes of random inputs");
+    println!();
+    println!("  --iterations N   cases to run (default 100000)");
+    println!("  --seed S         generator seed (default 1)");
+    println!("  --max-len BYTES  longest generated payload (default 256)");
+    println!("  --keep-going     report every divergence instead of stopping at the first");
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut iterations = 100_000u64;
+    let mut seed = 1u64;
+    let mut max_len = 256usize;
+    let mut keep_going = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "--iterations" => iterations = value()?.parse().map_err(|_| "--iterations must be an integer")?,
+            "--seed" => seed = value()?.parse().map_err(|_| "--seed must be an integer")?,
+            "--max-len" => max_len = value()?.parse().map_err(|_| "--max-len must be an integer")?,
+            "--keep-going" => keep_going = true,
+            "--help" | "-h" => {
+                print_help();
+                return Ok(());
+            }
+            other => return Err(format!("unknown argument: {other} (use --help)").into()),
+        }
+    }
+
+    // xorshift has a fixed point at zero.
+    let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1);
+    let options = DecodeOptions { state_hash: true, ..Default::default() };
+    let mut divergences = 0u64;
+    let mut decoded = 0u64;
+    for i in 0..iterations {
+        let case = generate(&mut rng, max_len);
+        let one_shot = decode_with_report(&case.payload, case.params, case.output_samples, &options)
+            .map(|(out, report)| (out, report.state_hash));
+        let streaming = decode_streaming(&case, &options);
+        decoded += u64::from(one_shot.is_ok());
+        if let Some(what) = divergence(&one_shot, &streaming) {
+            divergences += 1;
+            println!("case {i}: {what}");
+            println!("  params={:?} output_samples={}", case.params, case.output_samples);
+            println!("  chunk_seed={:#x} payload={:02x?}", case.chunk_seed, case.payload);
+            if !keep_going {
+                break;
+            }
+        }
+    }
+
+    println!("{iterations} cases (seed {seed}), {decoded} decoded cleanly, {divergences} divergences");
+    if divergences > 0 {
+        return Err(format!("{divergences} divergences between one-shot and streaming decoding").into());
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! GRIB2 helpers for working with AEC payloads outside of a GRIB2 library.
+//!
+//! `extract` writes the Section 7 payload of one CCSDS (template 5.42) field to a file and
+//! prints the `AecParams` and scaling derived from its Section 5, ready for `aec-guess`, the
+//! examples or a test.
+//!
+//! Usage:
+//!   aec-grib extract --grib data.grib2 --message N [--field M] [--out aec_payload.bin]
+
+use std::path::PathBuf;
+
+use rust_aec::grib2::iter_messages;
+use rust_aec::flags_to_grib2_ccsds_flags;
+
+fn print_help() {
+    println!("aec-grib: GRIB2 helpers for AEC payloads");
+    println!();
+    println!("aec-grib extract --grib FILE --message N [--field M] [--out FILE]");
+    println!("  --grib FILE      GRIB2 file (required)");
+    println!("  --message N      message number, from 0 (required)");
+    println!("  --field M        CCSDS field within the message, from 0 (default 0)");
+    println!("  --out FILE       where to write the Section 7 payload (default aec_payload.bin)");
+}
+
+fn extract(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut grib_path: Option<PathBuf> = None;
+    let mut message: Option<usize> = None;
+    let mut field = 0usize;
+    let mut out_path = PathBuf::from("aec_payload.bin");
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "--grib" => grib_path = Some(PathBuf::from(value()?)),
+            "--message" => message = Some(value()?.parse().map_err(|_| "--message must be an integer")?),
+            "--field" => field = value()?.parse().map_err(|_| "--field must be an integer")?,
+            "--out" => out_path = PathBuf::from(value()?),
+            "--help" | "-h" => {
+                print_help();
+                return Ok(());
+            }
+            other => return Err(format!("unknown argument: {other} (use --help)").into()),
+        }
+    }
+    let grib_path = grib_path.ok_or("--grib is required (use --help)")?;
+    let message = message.ok_or("--message is required (use --help)")?;
+
+    let file = std::fs::read(&grib_path)?;
+    let msg = iter_messages(&file)
+        .nth(message)
+        .ok_or_else(|| format!("{} has no message {message}", grib_path.display()))??;
+    let fields = msg.aec_fields()?;
+    let f = fields
+        .get(field)
+        .ok_or_else(|| format!("message {message} has {} CCSDS fields, no field {field}", fields.len()))?;
+    std::fs::write(&out_path, f.payload)?;
+
+    let p = f.params;
+    println!("message {message} at offset {}, field {field}", msg.offset);
+    println!("payload: {} bytes written to {}", f.payload.len(), out_path.display());
+    println!("values: {}", f.num_values);
+    println!(
+        "bits_per_sample {} block_size {} rsi {} ccsdsFlags {:#04x} ({:?})",
+        p.bits_per_sample,
+        p.block_size,
+        p.rsi,
+        flags_to_grib2_ccsds_flags(p.flags),
+        p.flags
+    );
+    println!(
+        "scaling: reference_value {} binary_scale_factor {} decimal_scale_factor {}",
+        f.scaling.reference_value, f.scaling.binary_scale_factor, f.scaling.decimal_scale_factor
+    );
+    println!(
+        "params: AecParams::new({}, {}, {}, flags_from_grib2_ccsds_flags({:#04x}))",
+        p.bits_per_sample,
+        p.block_size,
+        p.rsi,
+        flags_to_grib2_ccsds_flags(p.flags)
+    );
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("extract") => extract(args),
+        Some("--help" | "-h") | None => {
+            print_help();
+            Ok(())
+        }
+        Some(other) => Err(format!("unknown subcommand: {other} (use --help)").into()),
+    }
+}
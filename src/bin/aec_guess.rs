@@ -0,0 +1,80 @@
+//! Recover AEC parameters of a payload of unknown provenance.
+//!
+//! Brute-forces bits per sample, block size, RSI and flags, and prints the candidates that
+//! decode the first RSIs of the payload most cleanly and smoothly.
+//!
+//! Usage:
+//!   aec-guess --payload FILE [--samples N] [--bits 12,16] [--block 32] [--rsi 128] [--top 10]
+
+use std::path::PathBuf;
+
+use rust_aec::guess::{guess_flags, Candidate};
+
+fn parse_list<T: std::str::FromStr>(name: &str, v: &str) -> Result<Vec<T>, String> {
+    v.split(',').map(|x| x.trim().parse().map_err(|_| format!("{name}: invalid value `{x}`"))).collect()
+}
+
+fn print_help() {
+    println!("aec-guess: rank AEC parameter sets by how well they decode a payload");
+    println!();
+    println!("  --payload FILE   AEC payload (required)");
+    println!("  --samples N      sample count, if known");
+    println!("  --bits LIST      bits per sample to try (default 1..=32)");
+    println!("  --block LIST     block sizes to try (default 8,16,32,64)");
+    println!("  --rsi LIST       RSIs to try (default powers of two 1..=4096)");
+    println!("  --top N          candidates to print (default 10)");
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut payload_path: Option<PathBuf> = None;
+    let mut samples = usize::MAX;
+    let mut bits: Vec<u8> = (1..=32).collect();
+    let mut blocks: Vec<u32> = vec![8, 16, 32, 64];
+    let mut rsis: Vec<u32> = (0..=12).map(|i| 1 << i).collect();
+    let mut top = 10usize;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "--payload" => payload_path = Some(PathBuf::from(value()?)),
+            "--samples" => samples = value()?.parse().map_err(|_| "--samples must be an integer")?,
+            "--bits" => bits = parse_list("--bits", &value()?)?,
+            "--block" => blocks = parse_list("--block", &value()?)?,
+            "--rsi" => rsis = parse_list("--rsi", &value()?)?,
+            "--top" => top = value()?.parse().map_err(|_| "--top must be an integer")?,
+            "--help" | "-h" => {
+                print_help();
+                return Ok(());
+            }
+            other => return Err(format!("unknown argument: {other} (use --help)").into()),
+        }
+    }
+
+    let payload_path = payload_path.ok_or("--payload is required (use --help)")?;
+    let payload = std::fs::read(&payload_path)?;
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for &b in &bits {
+        for &block in &blocks {
+            for &rsi in &rsis {
+                candidates.extend(guess_flags(&payload, b, block, rsi, samples));
+            }
+        }
+    }
+    candidates.sort_by(Candidate::rank);
+
+    println!("payload: {} ({} bytes), {} candidates", payload_path.display(), payload.len(), candidates.len());
+    for c in candidates.iter().take(top) {
+        let p = c.params;
+        let outcome = match &c.error {
+            None => "clean".to_string(),
+            Some(e) => format!("stopped: {e}"),
+        };
+        println!(
+            "bits={:2} block={:2} rsi={:4} flags={:?} decoded={}/{} roughness={:.4} {outcome}",
+            p.bits_per_sample, p.block_size, p.rsi, p.flags, c.decoded_samples, c.prefix_samples, c.roughness
+        );
+    }
+    Ok(())
+}
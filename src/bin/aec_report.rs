@@ -0,0 +1,139 @@
+//! Compression analytics over a GRIB2 file.
+//!
+//! Walks every CCSDS (template 5.42) field and prints its parameters, compression ratio and
+//! block-mode mix, followed by totals per parameter set, to help producers tune their encoders.
+//!
+//! Usage:
+//!   aec-report --file data.grib2
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use rust_aec::bitreader::BitReader;
+use rust_aec::grib2::{aec_fields, Grib2Field};
+use rust_aec::{decode_block, BlockKind, PredictorState};
+
+/// Block counts per coding option.
+#[derive(Debug, Default, Clone, Copy)]
+struct ModeMix {
+    zero: u64,
+    second_extension: u64,
+    split: u64,
+    uncompressed: u64,
+}
+
+impl ModeMix {
+    fn add(&mut self, other: ModeMix) {
+        self.zero += other.zero;
+        self.second_extension += other.second_extension;
+        self.split += other.split;
+        self.uncompressed += other.uncompressed;
+    }
+
+    fn total(&self) -> u64 {
+        self.zero + self.second_extension + self.split + self.uncompressed
+    }
+
+    fn percentages(&self) -> String {
+        let pct = |n: u64| 100.0 * n as f64 / self.total().max(1) as f64;
+        format!(
+            "zero {:5.1}% se {:5.1}% split {:5.1}% raw {:5.1}%",
+            pct(self.zero),
+            pct(self.second_extension),
+            pct(self.split),
+            pct(self.uncompressed)
+        )
+    }
+}
+
+/// Totals over the fields sharing one parameter set.
+#[derive(Debug, Default)]
+struct Group {
+    fields: usize,
+    raw_bytes: u64,
+    coded_bytes: u64,
+    mix: ModeMix,
+}
+
+fn mode_mix(field: &Grib2Field<'_>) -> Result<ModeMix, rust_aec::AecError> {
+    let mut mix = ModeMix::default();
+    let mut r = BitReader::new(field.payload);
+    let mut state = PredictorState::default();
+    let mut samples = 0usize;
+    while samples < field.num_values {
+        let block = decode_block(&mut r, &mut state, field.params)?;
+        match block.kind {
+            BlockKind::ZeroRun { blocks } => mix.zero += blocks as u64,
+            BlockKind::SecondExtension => mix.second_extension += 1,
+            BlockKind::Split { .. } => mix.split += 1,
+            BlockKind::Uncompressed => mix.uncompressed += 1,
+        }
+        samples += block.samples.len();
+    }
+    Ok(mix)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut path: Option<PathBuf> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--file" => path = Some(PathBuf::from(args.next().ok_or("--file requires a value")?)),
+            "--help" | "-h" => {
+                println!("aec-report --file FILE: compression statistics of every CCSDS field in a GRIB2 file");
+                return Ok(());
+            }
+            other => return Err(format!("unknown argument: {other} (use --help)").into()),
+        }
+    }
+    let path = path.ok_or("--file is required (use --help)")?;
+    let file = std::fs::read(&path)?;
+    let fields = aec_fields(&file)?;
+    println!("{}: {} CCSDS fields", path.display(), fields.len());
+
+    // Keyed by (bits, block, rsi, flags).
+    let mut groups: BTreeMap<(u8, u32, u32, u32), Group> = BTreeMap::new();
+    for field in &fields {
+        let p = field.params;
+        let raw = (field.num_values as u64 * p.bits_per_sample as u64).div_ceil(8);
+        let coded = field.payload.len() as u64;
+        let ratio = raw as f64 / coded.max(1) as f64;
+        let mix = match mode_mix(field) {
+            Ok(mix) => mix,
+            Err(e) => {
+                println!("msg {:4} field {:2}: decode error: {e}", field.message, field.field);
+                continue;
+            }
+        };
+        println!(
+            "msg {:4} field {:2}: values {:9} bits {:2} block {:2} rsi {:4} flags {:#04x} ratio {:6.2} {}",
+            field.message,
+            field.field,
+            field.num_values,
+            p.bits_per_sample,
+            p.block_size,
+            p.rsi,
+            p.flags.bits(),
+            ratio,
+            mix.percentages()
+        );
+
+        let group = groups.entry((p.bits_per_sample, p.block_size, p.rsi, p.flags.bits())).or_default();
+        group.fields += 1;
+        group.raw_bytes += raw;
+        group.coded_bytes += coded;
+        group.mix.add(mix);
+    }
+
+    println!();
+    println!("per parameter set:");
+    for ((bits, block, rsi, flags), g) in &groups {
+        println!(
+            "bits {bits:2} block {block:2} rsi {rsi:4} flags {flags:#04x}: {:5} fields ratio {:6.2} {}",
+            g.fields,
+            g.raw_bytes as f64 / g.coded_bytes.max(1) as f64,
+            g.mix.percentages()
+        );
+    }
+    Ok(())
+}
@@ -6,7 +6,7 @@ fn bytes_per_sample(params: AecParams) -> usize {
     match params.bits_per_sample {
         1..=8 => 1,
         9..=16 => 2,
-        17..=24 => if params.flags.contains(AecFlags::DATA_3BYTE) { 3 } else { 4 },
+        17..=24 if params.flags.contains(AecFlags::DATA_3BYTE) => 3,
         _ => 4,
     }
 }
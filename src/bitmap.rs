@@ -0,0 +1,99 @@
+//! GRIB2 Section 6 bitmaps: which grid points carry a coded value (feature `grib2`).
+//!
+//! A GRIB2 field with missing points codes only the present ones, in grid order; the bitmap
+//! has one bit per grid point, MSB-first, set where a value is coded. [`Bitmap::expand`] places
+//! a decoded compact vector onto the full grid; [`crate::grib2::decode_grib2_grid_f32`] does the
+//! same while decoding.
+
+use crate::error::AecError;
+
+/// A presence mask over `points` grid points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bitmap<'a> {
+    bits: &'a [u8],
+    points: usize,
+}
+
+impl<'a> Bitmap<'a> {
+    /// The mask of `points` grid points in `bits` (a Section 6 without its first six octets);
+    /// bits past the last point are ignored.
+    pub fn new(bits: &'a [u8], points: usize) -> Result<Self, AecError> {
+        if bits.len() < points.div_ceil(8) {
+            return Err(AecError::InvalidInput("bitmap too short for grid"));
+        }
+        Ok(Self { bits, points })
+    }
+
+    /// Number of grid points.
+    pub fn points(&self) -> usize {
+        self.points
+    }
+
+    /// Whether grid point `i` (below [`Self::points`]) has a value.
+    #[inline]
+    pub fn is_present(&self, i: usize) -> bool {
+        self.bits[i / 8] & (0x80 >> (i % 8)) != 0
+    }
+
+    /// Number of grid points with a value: the number of values the field codes.
+    pub fn count_present(&self) -> usize {
+        let whole: usize = self.bits[..self.points / 8].iter().map(|b| b.count_ones() as usize).sum();
+        let rest = self.bits.get(self.points / 8).map_or(0, |&b| (b & !(0xffu8 >> (self.points % 8))).count_ones());
+        whole + rest as usize
+    }
+
+    /// Grid positions of the coded values, in order.
+    pub fn present_indices(self) -> impl Iterator<Item = usize> + 'a {
+        (0..self.points).filter(move |&i| self.is_present(i))
+    }
+
+    /// Place `values`, one per present point, onto a new grid with `missing` everywhere else.
+    ///
+    /// ```
+    /// use rust_aec::bitmap::Bitmap;
+    ///
+    /// let bitmap = Bitmap::new(&[0b1011_0000], 5)?;
+    /// assert_eq!(bitmap.count_present(), 3);
+    /// assert_eq!(bitmap.expand(&[1.0, 2.0, 3.0], f32::MAX)?, [1.0, f32::MAX, 2.0, 3.0, f32::MAX]);
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn expand<T: Copy>(&self, values: &[T], missing: T) -> Result<Vec<T>, AecError> {
+        let mut grid = vec![missing; self.points];
+        self.expand_into(values, missing, &mut grid)?;
+        Ok(grid)
+    }
+
+    /// Like [`Self::expand`], into `grid` of exactly [`Self::points`] elements.
+    pub fn expand_into<T: Copy>(&self, values: &[T], missing: T, grid: &mut [T]) -> Result<(), AecError> {
+        if grid.len() != self.points {
+            return Err(AecError::InvalidInput("grid size does not match the bitmap"));
+        }
+        if values.len() != self.count_present() {
+            return Err(AecError::InvalidInput("bitmap does not match the number of coded values"));
+        }
+        let mut values = values.iter();
+        for (i, point) in grid.iter_mut().enumerate() {
+            *point = if self.is_present(i) { *values.next().unwrap_or(&missing) } else { missing };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_expansion_ignore_bits_past_the_grid() {
+        let bitmap = Bitmap::new(&[0xff, 0b1010_1111], 12).unwrap();
+        assert_eq!(bitmap.count_present(), 10);
+        assert_eq!(bitmap.present_indices().collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5, 6, 7, 8, 10]);
+        let values: Vec<u16> = (1..=10).collect();
+        assert_eq!(bitmap.expand(&values, 0).unwrap(), [1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 10, 0]);
+
+        assert!(bitmap.expand(&values[..9], 0).is_err());
+        assert!(bitmap.expand_into(&values, 0, &mut [0; 11]).is_err());
+        assert!(Bitmap::new(&[0xff], 9).is_err());
+        assert_eq!(Bitmap::new(&[], 0).unwrap().count_present(), 0);
+    }
+}
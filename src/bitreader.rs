@@ -1,26 +1,82 @@
 use crate::error::AecError;
 
+/// Up to 64 bits loaded ahead of the read position, next bit first (MSB-aligned).
+///
+/// Readers refill it a byte at a time and take up to 32 bits from it with one shift, instead of
+/// assembling values bit by bit.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BitWindow {
+    bits: u64,
+    len: u32,
+}
+
+impl BitWindow {
+    /// Bits loaded and not yet taken.
+    #[inline]
+    pub(crate) fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Whether another byte fits.
+    #[inline]
+    pub(crate) fn has_room(&self) -> bool {
+        self.len <= 56
+    }
+
+    /// Append the 8 bits of `byte`, most significant first.
+    #[inline]
+    pub(crate) fn push_byte(&mut self, byte: u8) {
+        self.bits |= u64::from(byte) << (56 - self.len);
+        self.len += 8;
+    }
+
+    /// Take the next `n` (1..=32, at most [`Self::len`]) bits.
+    #[inline]
+    pub(crate) fn take(&mut self, n: u32) -> u32 {
+        debug_assert!((1..=32).contains(&n) && n <= self.len);
+        let value = (self.bits >> (64 - n)) as u32;
+        self.bits <<= n;
+        self.len -= n;
+        value
+    }
+
+    /// Zero bits before the next one bit among those loaded (all of them if there is none).
+    #[inline]
+    pub(crate) fn leading_zeros(&self) -> u32 {
+        // Bits past `len` are always zero.
+        self.bits.leading_zeros().min(self.len)
+    }
+
+    /// Drop the next `n` (at most [`Self::len`]) bits.
+    #[inline]
+    pub(crate) fn skip(&mut self, n: u32) {
+        debug_assert!(n <= self.len);
+        self.bits = self.bits.checked_shl(n).unwrap_or(0);
+        self.len -= n;
+    }
+}
+
 /// MSB-first bit reader over a byte slice.
 #[derive(Debug, Clone)]
 pub struct BitReader<'a> {
     data: &'a [u8],
-    bit_pos: usize,
+    /// Next byte of `data` to load into `window`.
+    next: usize,
+    window: BitWindow,
 }
 
 impl<'a> BitReader<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        Self { data, bit_pos: 0 }
+        Self { data, next: 0, window: BitWindow::default() }
     }
 
     pub fn bits_read(&self) -> usize {
-        self.bit_pos
+        self.next * 8 - self.window.len() as usize
     }
 
     pub fn align_to_byte(&mut self) {
-        let rem = self.bit_pos % 8;
-        if rem != 0 {
-            self.bit_pos += 8 - rem;
-        }
+        // Loaded bits always end on a byte boundary.
+        self.window.skip(self.window.len() % 8);
     }
 
     pub fn read_bit(&mut self) -> Result<bool, AecError> {
@@ -34,20 +90,50 @@ impl<'a> BitReader<'a> {
         if nbits > 32 {
             return Err(AecError::InvalidInput("read_bits_u32 supports up to 32 bits"));
         }
+        let n = nbits as u32;
+        if self.window.len() < n {
+            self.refill();
+            if self.window.len() < n {
+                // Like a bitwise read, stop at the end of the data.
+                self.window = BitWindow::default();
+                return Err(AecError::UnexpectedEof { bit_pos: self.data.len() * 8 });
+            }
+        }
+        Ok(self.window.take(n))
+    }
+
+    /// Read a fundamental sequence (zero bits up to the next one bit) a window at a time with
+    /// `leading_zeros`; runs longer than `limit` fail as corrupt.
+    pub(crate) fn read_unary(&mut self, limit: u32) -> Result<u32, AecError> {
+        let mut count: u32 = 0;
+        loop {
+            if self.window.len() == 0 {
+                self.refill();
+                if self.window.len() == 0 {
+                    return Err(AecError::UnexpectedEof { bit_pos: self.data.len() * 8 });
+                }
+            }
+            let zeros = self.window.leading_zeros();
+            count = count.saturating_add(zeros);
+            if count > limit {
+                return Err(AecError::InvalidInput("unary run too long"));
+            }
+            if zeros < self.window.len() {
+                self.window.skip(zeros + 1);
+                return Ok(count);
+            }
+            self.window.skip(zeros);
+        }
+    }
 
-        let mut out: u32 = 0;
-        for _ in 0..nbits {
-            let byte_idx = self.bit_pos / 8;
-            let bit_in_byte = self.bit_pos % 8;
-            let byte = *self
-                .data
-                .get(byte_idx)
-                .ok_or(AecError::UnexpectedEof { bit_pos: self.bit_pos })?;
-            let bit = (byte >> (7 - bit_in_byte)) & 1;
-            out = (out << 1) | (bit as u32);
-            self.bit_pos += 1;
+    fn refill(&mut self) {
+        while self.window.has_room() {
+            match self.data.get(self.next) {
+                Some(&byte) => self.window.push_byte(byte),
+                None => return,
+            }
+            self.next += 1;
         }
-        Ok(out)
     }
 }
 
@@ -58,23 +144,22 @@ impl<'a> BitReader<'a> {
 #[derive(Debug, Clone)]
 pub struct BitReaderLsb<'a> {
     data: &'a [u8],
-    bit_pos: usize,
+    /// Next byte of `data` to load (bit-reversed) into `window`.
+    next: usize,
+    window: BitWindow,
 }
 
 impl<'a> BitReaderLsb<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        Self { data, bit_pos: 0 }
+        Self { data, next: 0, window: BitWindow::default() }
     }
 
     pub fn bits_read(&self) -> usize {
-        self.bit_pos
+        self.next * 8 - self.window.len() as usize
     }
 
     pub fn align_to_byte(&mut self) {
-        let rem = self.bit_pos % 8;
-        if rem != 0 {
-            self.bit_pos += 8 - rem;
-        }
+        self.window.skip(self.window.len() % 8);
     }
 
     pub fn read_bit(&mut self) -> Result<bool, AecError> {
@@ -88,20 +173,21 @@ impl<'a> BitReaderLsb<'a> {
         if nbits > 32 {
             return Err(AecError::InvalidInput("read_bits_u32 supports up to 32 bits"));
         }
-
-        let mut out: u32 = 0;
-        for _ in 0..nbits {
-            let byte_idx = self.bit_pos / 8;
-            let bit_in_byte = self.bit_pos % 8;
-            let byte = *self
-                .data
-                .get(byte_idx)
-                .ok_or(AecError::UnexpectedEof { bit_pos: self.bit_pos })?;
-            let bit = (byte >> bit_in_byte) & 1;
-            out = (out << 1) | (bit as u32);
-            self.bit_pos += 1;
+        let n = nbits as u32;
+        if self.window.len() < n {
+            while self.window.has_room() {
+                match self.data.get(self.next) {
+                    Some(&byte) => self.window.push_byte(byte.reverse_bits()),
+                    None => break,
+                }
+                self.next += 1;
+            }
+            if self.window.len() < n {
+                self.window = BitWindow::default();
+                return Err(AecError::UnexpectedEof { bit_pos: self.data.len() * 8 });
+            }
         }
-        Ok(out)
+        Ok(self.window.take(n))
     }
 }
 
@@ -131,4 +217,46 @@ mod tests {
         assert_eq!(r.read_bits_u32(8)?, 0x12);
         Ok(())
     }
+
+    #[test]
+    fn wide_reads_match_bitwise_reads() {
+        let data: Vec<u8> = (0..64u32).map(|i| (i * 151 + 7) as u8).collect();
+        let widths = [1, 32, 7, 13, 31, 2, 24, 5, 17, 29, 3, 32, 11];
+        let (mut wide, mut bitwise) = (BitReader::new(&data), BitReader::new(&data));
+        let mut lsb = BitReaderLsb::new(&data);
+        for &n in widths.iter().cycle().take(25) {
+            let expected = (0..n).fold(0u32, |acc, _| (acc << 1) | bitwise.read_bit().unwrap() as u32);
+            assert_eq!(wide.read_bits_u32(n).unwrap(), expected);
+            assert_eq!(wide.bits_read(), bitwise.bits_read());
+            let pos = lsb.bits_read();
+            let bit = |i: usize| u32::from(data[(pos + i) / 8] >> ((pos + i) % 8) & 1);
+            let expected_lsb = (0..n).fold(0u32, |acc, i| (acc << 1) | bit(i));
+            assert_eq!(lsb.read_bits_u32(n).unwrap(), expected_lsb);
+        }
+        let mut r = BitReader::new(&data[..3]);
+        r.read_bits_u32(20).unwrap();
+        assert!(matches!(r.read_bits_u32(5), Err(AecError::UnexpectedEof { bit_pos: 24 })));
+        assert_eq!(r.bits_read(), 24);
+    }
+
+    #[test]
+    fn unary_runs_span_window_refills() {
+        // Runs of 0, 70 and 130 zero bits, then 3 zeros and the end of the data.
+        let mut bits = vec![true];
+        for run in [70, 130] {
+            bits.extend(std::iter::repeat_n(false, run));
+            bits.push(true);
+        }
+        bits.extend([false; 3]);
+        let byte = |c: &[bool]| c.iter().fold(0u8, |b, &bit| (b << 1) | bit as u8) << (8 - c.len());
+        let data: Vec<u8> = bits.chunks(8).map(byte).collect();
+
+        let mut r = BitReader::new(&data);
+        assert_eq!(r.read_unary(200).unwrap(), 0);
+        assert_eq!(r.read_unary(200).unwrap(), 70);
+        assert_eq!(r.bits_read(), 72);
+        assert!(matches!(r.clone().read_unary(129), Err(AecError::InvalidInput(_))));
+        assert_eq!(r.read_unary(130).unwrap(), 130);
+        assert!(matches!(r.read_unary(200), Err(AecError::UnexpectedEof { .. })));
+    }
 }
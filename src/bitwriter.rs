@@ -0,0 +1,112 @@
+/// MSB-first bit writer into a growable byte buffer; the counterpart of
+/// [`crate::bitreader::BitReader`].
+#[derive(Debug, Default, Clone)]
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    /// Whole bytes already handed out by [`Self::drain_into`].
+    drained: usize,
+    /// The low `nbits` bits are written but not yet a whole byte.
+    acc: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the low `nbits` (at most 32) bits of `value`.
+    pub(crate) fn put(&mut self, value: u32, nbits: u32) {
+        debug_assert!(nbits <= 32);
+        if nbits == 0 {
+            return;
+        }
+        let mask = if nbits == 32 { u32::MAX } else { (1u32 << nbits) - 1 };
+        self.acc = (self.acc << nbits) | u64::from(value & mask);
+        self.nbits += nbits;
+        while self.nbits >= 8 {
+            self.nbits -= 8;
+            self.bytes.push((self.acc >> self.nbits) as u8);
+        }
+        self.acc &= (1u64 << self.nbits) - 1;
+    }
+
+    /// Append a fundamental sequence: `count` zero bits, then a one.
+    pub(crate) fn put_unary(&mut self, count: u64) {
+        let mut left = count;
+        while left > 0 {
+            let n = left.min(32) as u32;
+            self.put(0, n);
+            left -= u64::from(n);
+        }
+        self.put(1, 1);
+    }
+
+    /// Pad with zero bits to the next byte boundary; returns the number of bits added.
+    pub(crate) fn align_to_byte(&mut self) -> u32 {
+        let pad = (8 - self.nbits % 8) % 8;
+        self.put(0, pad);
+        pad
+    }
+
+    /// Copy whole bytes not yet handed out into `out`; returns how many were copied.
+    pub(crate) fn drain_into(&mut self, out: &mut [u8]) -> usize {
+        let pending = &self.bytes[self.drained..];
+        let n = pending.len().min(out.len());
+        out[..n].copy_from_slice(&pending[..n]);
+        self.drained += n;
+        if self.drained == self.bytes.len() {
+            self.bytes.clear();
+            self.drained = 0;
+        }
+        n
+    }
+
+    /// Write all whole bytes not yet handed out to `writer`; returns how many were written.
+    #[cfg(feature = "io")]
+    pub(crate) fn write_to<W: std::io::Write + ?Sized>(&mut self, writer: &mut W) -> std::io::Result<usize> {
+        let n = self.bytes.len() - self.drained;
+        writer.write_all(&self.bytes[self.drained..])?;
+        self.bytes.clear();
+        self.drained = 0;
+        Ok(n)
+    }
+
+    /// Whether every whole byte has been handed out.
+    pub(crate) fn is_drained(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Pad to a byte boundary and return the bytes not yet handed out.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.align_to_byte();
+        self.bytes.drain(..self.drained);
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitreader::BitReader;
+
+    #[test]
+    fn round_trips_through_the_bit_reader() {
+        let mut w = BitWriter::new();
+        w.put(0b101, 3);
+        w.put(u32::MAX, 32);
+        w.put_unary(40);
+        w.put(0x5, 4);
+        let bytes = w.finish();
+        assert_eq!(bytes.len(), 80usize.div_ceil(8));
+
+        let mut r = BitReader::new(&bytes);
+        assert_eq!(r.read_bits_u32(3).unwrap(), 0b101);
+        assert_eq!(r.read_bits_u32(32).unwrap(), u32::MAX);
+        for _ in 0..40 {
+            assert!(!r.read_bit().unwrap());
+        }
+        assert!(r.read_bit().unwrap());
+        assert_eq!(r.read_bits_u32(4).unwrap(), 0x5);
+    }
+}
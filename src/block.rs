@@ -0,0 +1,255 @@
+//! Low-level, block-at-a-time decoding.
+//!
+//! [`decode_block`] reads exactly one coded block (or one zero-block run) from a [`BitReader`]
+//! and returns its reconstructed samples together with the coding option that was used. It is
+//! meant for format tooling and research code that wants to step through a stream with full
+//! control; regular users should prefer [`crate::decode`] or [`crate::Decoder`].
+
+use crate::bitreader::BitReader;
+use crate::decoder::{id_len, second_extension_pair, unary_limit, validate_params};
+use crate::error::AecError;
+use crate::geometry::blocks_to_segment_end;
+use crate::options::DecodeOptions;
+use crate::params::{AecFlags, AecParams};
+use crate::preprocess::{inverse_preprocess_step, sign_extend};
+use crate::rice::{self, read_unary, RiceLayout};
+
+/// Decoder state carried from one block to the next.
+///
+/// Start a stream with `PredictorState::default()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PredictorState {
+    /// Last reconstructed sample, used as the prediction for the next one.
+    ///
+    /// Only meaningful with [`AecFlags::DATA_PREPROCESS`]; reset at every RSI boundary.
+    pub predictor: Option<i64>,
+    /// Index of the next block within the current reference sample interval.
+    pub block_index_within_rsi: u32,
+}
+
+/// Coding option of a decoded block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// Run of all-zero blocks (low-entropy, selector 0); covers `blocks` blocks.
+    ZeroRun { blocks: u32 },
+    /// Second Extension option (low-entropy, selector 1).
+    SecondExtension,
+    /// Rice split option with `k` low bits per coded value.
+    Split { k: u32 },
+    /// Uncompressed samples.
+    Uncompressed,
+}
+
+/// One decoded block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// Coding option.
+    pub kind: BlockKind,
+    /// Option id as read from the stream.
+    pub id: u32,
+    /// RSI reference sample, when this block starts an interval with preprocessing enabled.
+    pub reference: Option<i64>,
+    /// Reconstructed sample values, including the reference sample (if any) first.
+    ///
+    /// Signed streams yield sign-extended values.
+    pub samples: Vec<i64>,
+    /// Bit offset of the block's option id within the stream.
+    pub bit_offset: usize,
+    /// Bits consumed by the block, including any `PAD_RSI` padding that followed it.
+    pub bits: usize,
+}
+
+/// Decode a single block (or zero-block run) and advance `state`.
+///
+/// The reader must be positioned at a block's option id. Unlike the one-shot decoder, the
+/// total sample count is not known here, so the block is always decoded in full.
+///
+/// Block sizes outside the CCSDS set are accepted; unary runs use the default limit.
+pub fn decode_block(r: &mut BitReader<'_>, state: &mut PredictorState, params: AecParams) -> Result<Block, AecError> {
+    let options = DecodeOptions { allow_nonstandard_block_size: true, ..Default::default() };
+    validate_params(params, &options)?;
+    if params.bits_per_sample == 0 {
+        return Err(AecError::InvalidInput("a constant field (bits_per_sample 0) has no blocks"));
+    }
+
+    let id_len = id_len(params)?;
+    let limit = unary_limit(params, &options);
+    let max_id = (1u32 << id_len) - 1;
+    let block_size = params.block_size as usize;
+    let preprocess = params.flags.contains(AecFlags::DATA_PREPROCESS);
+    let bit_offset = r.bits_read();
+
+    let ref_pending = preprocess && state.block_index_within_rsi == 0;
+    if ref_pending {
+        state.predictor = None;
+    }
+
+    let id = r.read_bits_u32(id_len)?;
+    let mut samples: Vec<i64> = Vec::with_capacity(block_size);
+    let mut reference = None;
+    let mut blocks = 1u32;
+
+    let kind = if id == 0 {
+        // For low-entropy blocks, the selector bit comes before the reference sample.
+        let selector = r.read_bit()?;
+        if ref_pending {
+            let x = read_reference(r, state, params)?;
+            reference = Some(x);
+            samples.push(x);
+        }
+
+        if !selector {
+            let fs = read_unary(r, limit)?;
+            let mut z_blocks = fs.saturating_add(1);
+            const ROS: u32 = 5;
+            if z_blocks == ROS {
+                z_blocks = blocks_to_segment_end(params, state.block_index_within_rsi);
+            } else if z_blocks > ROS {
+                z_blocks -= 1;
+            }
+            blocks = z_blocks;
+
+            let n = (z_blocks as usize)
+                .checked_mul(block_size)
+                .ok_or(AecError::InvalidInput("zero-run overflow"))?
+                .saturating_sub(samples.len());
+            for _ in 0..n {
+                let x = reconstruct(state, 0, params)?;
+                samples.push(x);
+            }
+            BlockKind::ZeroRun { blocks }
+        } else {
+            // Pairs are aligned to even sample indices; a reference sample takes slot 0.
+            let mut i = samples.len();
+            while i < block_size {
+                let m = read_unary(r, limit)?;
+                if m > 90 {
+                    return Err(AecError::InvalidInput("Second Extension unary symbol too large"));
+                }
+                let (a, b) = second_extension_pair(m);
+                if i % 2 == 0 {
+                    let x = reconstruct(state, a, params)?;
+                    samples.push(x);
+                    i += 1;
+                }
+                if i < block_size {
+                    let x = reconstruct(state, b, params)?;
+                    samples.push(x);
+                    i += 1;
+                }
+            }
+            BlockKind::SecondExtension
+        }
+    } else if id == max_id {
+        if ref_pending {
+            let x = read_reference(r, state, params)?;
+            reference = Some(x);
+            samples.push(x);
+        }
+        while samples.len() < block_size {
+            let v = r.read_bits_u32(params.bits_per_sample as usize)?;
+            let x = reconstruct(state, v, params)?;
+            samples.push(x);
+        }
+        BlockKind::Uncompressed
+    } else {
+        let k = id - 1;
+        if ref_pending {
+            let x = read_reference(r, state, params)?;
+            reference = Some(x);
+            samples.push(x);
+        }
+        // All fundamental sequences come first, then all k-bit remainders.
+        let mut coded = vec![0u32; block_size - samples.len()];
+        rice::read_values(r, k, RiceLayout::Split, limit, &mut coded)?;
+        for v in coded {
+            let x = reconstruct(state, v, params)?;
+            samples.push(x);
+        }
+        BlockKind::Split { k }
+    };
+
+    state.block_index_within_rsi = state.block_index_within_rsi.saturating_add(blocks);
+    if state.block_index_within_rsi >= params.rsi {
+        state.block_index_within_rsi %= params.rsi;
+        if params.flags.contains(AecFlags::PAD_RSI) {
+            r.align_to_byte();
+        }
+    }
+
+    Ok(Block { kind, id, reference, samples, bit_offset, bits: r.bits_read() - bit_offset })
+}
+
+fn read_reference(r: &mut BitReader<'_>, state: &mut PredictorState, params: AecParams) -> Result<i64, AecError> {
+    let raw = r.read_bits_u32(params.bits_per_sample as usize)?;
+    let x = if params.flags.contains(AecFlags::DATA_SIGNED) {
+        sign_extend(raw, params.bits_per_sample)
+    } else {
+        raw as i64
+    };
+    state.predictor = Some(x);
+    Ok(x)
+}
+
+fn reconstruct(state: &mut PredictorState, v: u32, params: AecParams) -> Result<i64, AecError> {
+    if params.flags.contains(AecFlags::DATA_PREPROCESS) {
+        let x_prev = state.predictor.ok_or(AecError::InvalidInput("missing reference sample"))?;
+        let x = inverse_preprocess_step(x_prev, v, params);
+        state.predictor = Some(x);
+        Ok(x)
+    } else if params.flags.contains(AecFlags::DATA_SIGNED) {
+        Ok(sign_extend(v, params.bits_per_sample))
+    } else {
+        Ok(v as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(s: &str) -> Vec<u8> {
+        let bits: Vec<u8> = s.bytes().filter(|b| *b != b' ').map(|b| b - b'0').collect();
+        bits.chunks(8)
+            .map(|c| c.iter().enumerate().fold(0u8, |acc, (i, b)| acc | (b << (7 - i))))
+            .collect()
+    }
+
+    #[test]
+    fn uncompressed_then_zero_run() -> anyhow::Result<()> {
+        let params = AecParams::new(4, 8, 2, AecFlags::empty());
+        // id_len = 3 for 4-bit samples; id 7 = uncompressed, id 0 + selector 0 = zero run.
+        let data = bits("111 0001 0010 0011 0100 0101 0110 0111 1000  000 0 1");
+        let mut r = BitReader::new(&data);
+        let mut state = PredictorState::default();
+
+        let b = decode_block(&mut r, &mut state, params)?;
+        assert_eq!(b.kind, BlockKind::Uncompressed);
+        assert_eq!(b.samples, (1..=8).collect::<Vec<i64>>());
+        assert_eq!((b.bit_offset, b.bits), (0, 35));
+
+        let b = decode_block(&mut r, &mut state, params)?;
+        assert_eq!(b.kind, BlockKind::ZeroRun { blocks: 1 });
+        assert_eq!(b.samples, vec![0; 8]);
+        assert_eq!(state.block_index_within_rsi, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn reference_sample_and_second_extension() -> anyhow::Result<()> {
+        let params = AecParams::new(8, 8, 4, AecFlags::DATA_PREPROCESS);
+        // id 0, selector 1, reference 100, then 4 symbols: the first only fills the odd slot.
+        // m=1 -> (1,0)/0; m=0 -> (0,0) for the rest.
+        let data = bits("000 1 01100100 01 1 1 1");
+        let mut r = BitReader::new(&data);
+        let mut state = PredictorState::default();
+
+        let b = decode_block(&mut r, &mut state, params)?;
+        assert_eq!(b.kind, BlockKind::SecondExtension);
+        assert_eq!(b.reference, Some(100));
+        assert_eq!(b.samples, vec![100; 8]);
+        assert_eq!(state.predictor, Some(100));
+        assert_eq!(state.block_index_within_rsi, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! CRC-32C (Castagnoli) of decoded output, as used by [`crate::DecodeOptions::crc32c`].
+
+const POLY: u32 = 0x82f6_3b78;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { (c >> 1) ^ POLY } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// Incremental CRC-32C.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32c(u32);
+
+impl Default for Crc32c {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32c {
+    pub fn new() -> Self {
+        Self(!0)
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        let mut c = self.0;
+        for &b in bytes {
+            c = TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+        }
+        self.0 = c;
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// Running 64-bit FNV-1a hash of decoder state at RSI boundaries, for
+/// [`crate::DecodeOptions::state_hash`]. Fields are hashed as little-endian integers, so the
+/// value is the same on every platform.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StateHash(u64);
+
+impl StateHash {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Fold in the state after RSI number `rsi` (counted from the start of decoding).
+    pub(crate) fn rsi_boundary(&mut self, rsi: u64, bit_pos: usize, predictor: Option<i64>) {
+        let words = [rsi, bit_pos as u64, u64::from(predictor.is_some()), predictor.unwrap_or(0) as u64];
+        for byte in words.iter().flat_map(|w| w.to_le_bytes()) {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// CRC-32C of `bytes`, for comparing a stored digest with [`crate::DecodeReport::crc32c`].
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    let mut c = Crc32c::new();
+    c.update(bytes);
+    c.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_standard_check_value() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+
+        let mut c = Crc32c::new();
+        c.update(b"1234");
+        c.update(b"56789");
+        assert_eq!(c.finish(), 0xe306_9283);
+    }
+}
@@ -0,0 +1,206 @@
+//! Adapter for the `compression-codecs` traits used by `async-compression` (feature
+//! `compression-codecs`).
+//!
+//! [`AecEncodeCodec`] implements [`compression_codecs::EncodeV2`] and [`AecDecodeCodec`]
+//! implements [`compression_codecs::DecodeV2`], so AEC payloads can be encoded and decoded inside
+//! generic byte-stream pipelines next to gzip or zstd codecs.
+
+use std::io;
+
+use compression_codecs::core::util::{PartialBuffer, WriteBuffer};
+use compression_codecs::{DecodeV2, EncodeV2};
+
+use crate::decoder::{DecodeStatus, Decoder, Flush};
+use crate::encoder::{EncodeStatus, Encoder};
+use crate::error::AecError;
+use crate::params::AecParams;
+
+/// Streaming AEC decoder behind the `compression-codecs` decoder interface.
+///
+/// Input is the AEC payload; output is packed sample bytes, as from [`crate::decode`]. The end
+/// of the stream is reached after `output_samples` samples.
+pub struct AecDecodeCodec {
+    params: AecParams,
+    output_samples: usize,
+    decoder: Decoder,
+}
+
+impl std::fmt::Debug for AecDecodeCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AecDecodeCodec")
+            .field("params", &self.params)
+            .field("output_samples", &self.output_samples)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AecDecodeCodec {
+    pub fn new(params: AecParams, output_samples: usize) -> Result<Self, AecError> {
+        Ok(Self { params, output_samples, decoder: Decoder::new(params, output_samples)? })
+    }
+
+    fn run(&mut self, output: &mut WriteBuffer<'_>, flush: Flush) -> io::Result<DecodeStatus> {
+        let (written, status) = self.decoder.decode(output.initialize_unwritten(), flush).map_err(io_error)?;
+        output.advance(written);
+        Ok(status)
+    }
+}
+
+impl DecodeV2 for AecDecodeCodec {
+    fn reinit(&mut self) -> io::Result<()> {
+        self.decoder = Decoder::new(self.params, self.output_samples).map_err(io_error)?;
+        Ok(())
+    }
+
+    fn decode(&mut self, input: &mut PartialBuffer<&[u8]>, output: &mut WriteBuffer<'_>) -> io::Result<bool> {
+        let data = input.unwritten();
+        self.decoder.push_input(data);
+        input.advance(data.len());
+        Ok(self.run(output, Flush::NoFlush)? == DecodeStatus::Finished)
+    }
+
+    fn flush(&mut self, output: &mut WriteBuffer<'_>) -> io::Result<bool> {
+        Ok(self.run(output, Flush::NoFlush)? != DecodeStatus::NeedOutput)
+    }
+
+    fn finish(&mut self, output: &mut WriteBuffer<'_>) -> io::Result<bool> {
+        Ok(self.run(output, Flush::Flush)? == DecodeStatus::Finished)
+    }
+}
+
+/// Streaming AEC encoder behind the `compression-codecs` encoder interface.
+///
+/// Input is packed sample bytes, laid out as [`crate::encode`] expects them; output is the AEC
+/// payload. Samples are encoded one RSI at a time, so `flush` hands out every complete RSI and
+/// the final partial RSI is only written by `finish`.
+#[derive(Debug)]
+pub struct AecEncodeCodec {
+    encoder: Encoder,
+}
+
+impl AecEncodeCodec {
+    pub fn new(params: AecParams) -> Result<Self, AecError> {
+        Ok(Self { encoder: Encoder::new(params)? })
+    }
+
+    fn run(&mut self, output: &mut WriteBuffer<'_>, flush: Flush) -> io::Result<EncodeStatus> {
+        let (written, status) = self.encoder.encode(output.initialize_unwritten(), flush).map_err(io_error)?;
+        output.advance(written);
+        Ok(status)
+    }
+}
+
+impl EncodeV2 for AecEncodeCodec {
+    fn encode(&mut self, input: &mut PartialBuffer<&[u8]>, output: &mut WriteBuffer<'_>) -> io::Result<()> {
+        let data = input.unwritten();
+        self.encoder.push_samples(data);
+        input.advance(data.len());
+        self.run(output, Flush::NoFlush)?;
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut WriteBuffer<'_>) -> io::Result<bool> {
+        Ok(self.run(output, Flush::NoFlush)? != EncodeStatus::NeedOutput)
+    }
+
+    fn finish(&mut self, output: &mut WriteBuffer<'_>) -> io::Result<bool> {
+        if self.encoder.is_finished() {
+            return Ok(true);
+        }
+        Ok(self.run(output, Flush::Flush)? == EncodeStatus::Finished)
+    }
+}
+
+fn io_error(e: AecError) -> io::Error {
+    let kind = match e.untagged() {
+        AecError::UnexpectedEof { .. } | AecError::UnexpectedEofDuringDecode { .. } => io::ErrorKind::UnexpectedEof,
+        AecError::Io(inner) => inner.kind(),
+        _ => io::ErrorKind::InvalidData,
+    };
+    io::Error::new(kind, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::AecFlags;
+
+    #[test]
+    fn decodes_through_small_buffers() {
+        // Uncompressed 8-bit blocks (id 7) of samples 1..=8 and 9..=16.
+        let payload = [
+            0xe0, 0x20, 0x40, 0x60, 0x80, 0xa0, 0xc0, 0xe1, 0x1c, 0x04, 0x84, 0x8c, 0x50, 0x5c, 0x18, 0x1a, 0x0e, 0x04,
+            0x00,
+        ];
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+        let expected = crate::decode(&payload, params, 16).unwrap();
+
+        let mut codec = AecDecodeCodec::new(params, 16).unwrap();
+        let mut out = Vec::new();
+        let mut buf = [0u8; 3];
+        let mut done = false;
+        for chunk in payload.chunks(2) {
+            let mut input = PartialBuffer::new(chunk);
+            while !input.unwritten().is_empty() || !done {
+                let mut output = WriteBuffer::new_initialized(&mut buf);
+                done = DecodeV2::decode(&mut codec, &mut input, &mut output).unwrap();
+                out.extend_from_slice(output.written());
+                if output.written_len() < buf.len() {
+                    break;
+                }
+            }
+        }
+        loop {
+            let mut output = WriteBuffer::new_initialized(&mut buf);
+            let finished = DecodeV2::finish(&mut codec, &mut output).unwrap();
+            out.extend_from_slice(output.written());
+            if finished {
+                break;
+            }
+        }
+        assert_eq!(out, expected);
+
+        DecodeV2::reinit(&mut codec).unwrap();
+        let mut output = WriteBuffer::new_initialized(&mut buf);
+        let err = DecodeV2::finish(&mut codec, &mut output).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn encodes_through_small_buffers() {
+        let params = AecParams::new(12, 16, 4, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+        let samples: Vec<u8> = (0..300u16).flat_map(|i| ((i * 37) % 4096).to_be_bytes()).collect();
+
+        let mut codec = AecEncodeCodec::new(params).unwrap();
+        let mut out = Vec::new();
+        let mut buf = [0u8; 5];
+        for chunk in samples.chunks(33) {
+            let mut input = PartialBuffer::new(chunk);
+            loop {
+                let mut output = WriteBuffer::new_initialized(&mut buf);
+                EncodeV2::encode(&mut codec, &mut input, &mut output).unwrap();
+                out.extend_from_slice(output.written());
+                if output.written_len() < buf.len() {
+                    break;
+                }
+            }
+            loop {
+                let mut output = WriteBuffer::new_initialized(&mut buf);
+                let flushed = EncodeV2::flush(&mut codec, &mut output).unwrap();
+                out.extend_from_slice(output.written());
+                if flushed {
+                    break;
+                }
+            }
+        }
+        loop {
+            let mut output = WriteBuffer::new_initialized(&mut buf);
+            let finished = EncodeV2::finish(&mut codec, &mut output).unwrap();
+            out.extend_from_slice(output.written());
+            if finished {
+                break;
+            }
+        }
+        assert_eq!(out, crate::encode(&samples, params).unwrap());
+    }
+}
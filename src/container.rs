@@ -0,0 +1,185 @@
+//! A lightweight framed container for many AEC fields (feature `io`).
+//!
+//! For archives of AEC-coded fields kept outside GRIB2. All integers are little-endian:
+//!
+//! ```text
+//! header   "RAEC" version:u8 reserved:[u8; 3]
+//! record*  bits_per_sample:u8 flags:u32 block_size:u32 rsi:u32 samples:u64 payload_len:u64 payload
+//! index    "RAEI" count:u64 record_offset:u64 * count
+//! footer   index_offset:u64 "RAEC"
+//! ```
+//!
+//! The trailing index lets [`ContainerReader`] load any record without scanning the file.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::error::AecError;
+use crate::params::{AecFlags, AecParams};
+
+const MAGIC: &[u8; 4] = b"RAEC";
+const INDEX_MAGIC: &[u8; 4] = b"RAEI";
+const VERSION: u8 = 1;
+const HEADER_LEN: u64 = 8;
+const FOOTER_LEN: u64 = 12;
+
+/// One field stored in a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Parameters the payload was coded with.
+    pub params: AecParams,
+    /// Number of samples the payload decodes to.
+    pub output_samples: u64,
+    /// The AEC payload.
+    pub payload: Vec<u8>,
+}
+
+impl Record {
+    /// Decode the record's payload.
+    pub fn decode(&self) -> Result<Vec<u8>, AecError> {
+        let samples = usize::try_from(self.output_samples).map_err(|_| AecError::InvalidInput("output too large"))?;
+        crate::decode(&self.payload, self.params, samples)
+    }
+}
+
+/// Writes records to a container; call [`ContainerWriter::finish`] to write the index.
+pub struct ContainerWriter<W: Write> {
+    writer: W,
+    offset: u64,
+    offsets: Vec<u64>,
+}
+
+impl<W: Write> ContainerWriter<W> {
+    /// Start a container by writing its header.
+    pub fn new(mut writer: W) -> Result<Self, AecError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, 0, 0, 0])?;
+        Ok(Self { writer, offset: HEADER_LEN, offsets: Vec::new() })
+    }
+
+    /// Append one field; returns its record number.
+    pub fn add(&mut self, params: AecParams, output_samples: u64, payload: &[u8]) -> Result<usize, AecError> {
+        let mut head = [0u8; 29];
+        head[0] = params.bits_per_sample;
+        head[1..5].copy_from_slice(&params.flags.bits().to_le_bytes());
+        head[5..9].copy_from_slice(&params.block_size.to_le_bytes());
+        head[9..13].copy_from_slice(&params.rsi.to_le_bytes());
+        head[13..21].copy_from_slice(&output_samples.to_le_bytes());
+        head[21..29].copy_from_slice(&(payload.len() as u64).to_le_bytes());
+        self.writer.write_all(&head)?;
+        self.writer.write_all(payload)?;
+
+        self.offsets.push(self.offset);
+        self.offset += head.len() as u64 + payload.len() as u64;
+        Ok(self.offsets.len() - 1)
+    }
+
+    /// Write the index and footer; returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, AecError> {
+        self.writer.write_all(INDEX_MAGIC)?;
+        self.writer.write_all(&(self.offsets.len() as u64).to_le_bytes())?;
+        for offset in &self.offsets {
+            self.writer.write_all(&offset.to_le_bytes())?;
+        }
+        self.writer.write_all(&self.offset.to_le_bytes())?;
+        self.writer.write_all(MAGIC)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Random access to the records of a container.
+pub struct ContainerReader<R: Read + Seek> {
+    reader: R,
+    offsets: Vec<u64>,
+}
+
+impl<R: Read + Seek> ContainerReader<R> {
+    /// Check the header and load the trailing index.
+    pub fn new(mut reader: R) -> Result<Self, AecError> {
+        let mut header = [0u8; HEADER_LEN as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        read_exact(&mut reader, &mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(AecError::InvalidInput("not a rust-aec container"));
+        }
+        if header[4] != VERSION {
+            return Err(AecError::Unsupported("container version"));
+        }
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        if end < HEADER_LEN + FOOTER_LEN {
+            return Err(AecError::InvalidInput("container is truncated"));
+        }
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        reader.seek(SeekFrom::Start(end - FOOTER_LEN))?;
+        read_exact(&mut reader, &mut footer)?;
+        if &footer[8..] != MAGIC {
+            return Err(AecError::InvalidInput("container footer is missing"));
+        }
+        let index_offset = u64::from_le_bytes(footer[..8].try_into().unwrap());
+
+        let mut index_head = [0u8; 12];
+        reader.seek(SeekFrom::Start(index_offset))?;
+        read_exact(&mut reader, &mut index_head)?;
+        let count = u64::from_le_bytes(index_head[4..].try_into().unwrap());
+        if &index_head[..4] != INDEX_MAGIC || count > (end - index_offset) / 8 {
+            return Err(AecError::InvalidInput("container index is corrupt"));
+        }
+        let mut offsets = Vec::with_capacity(count as usize);
+        let mut buf = [0u8; 8];
+        for _ in 0..count {
+            read_exact(&mut reader, &mut buf)?;
+            let offset = u64::from_le_bytes(buf);
+            if !(HEADER_LEN..index_offset).contains(&offset) {
+                return Err(AecError::InvalidInput("container index is corrupt"));
+            }
+            offsets.push(offset);
+        }
+        Ok(Self { reader, offsets })
+    }
+
+    /// Number of records.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the container holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Load record `n`.
+    pub fn record(&mut self, n: usize) -> Result<Record, AecError> {
+        let offset = *self.offsets.get(n).ok_or(AecError::InvalidInput("record number out of range"))?;
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut head = [0u8; 29];
+        read_exact(&mut self.reader, &mut head)?;
+        let u32_at = |i: usize| u32::from_le_bytes(head[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(head[i..i + 8].try_into().unwrap());
+
+        let flags = AecFlags::from_bits(u32_at(1)).ok_or(AecError::InvalidInput("unknown flags in container record"))?;
+        let params = AecParams::new(head[0], u32_at(5), u32_at(9), flags);
+        let payload_len =
+            usize::try_from(u64_at(21)).map_err(|_| AecError::InvalidInput("container record too large"))?;
+
+        // Read through `take` so a corrupt length cannot force a huge allocation up front.
+        let mut payload = Vec::new();
+        (&mut self.reader).take(payload_len as u64).read_to_end(&mut payload)?;
+        if payload.len() != payload_len {
+            return Err(AecError::InvalidInput("container record is truncated"));
+        }
+        Ok(Record { params, output_samples: u64_at(13), payload })
+    }
+
+    /// Load and decode record `n`.
+    pub fn decode(&mut self, n: usize) -> Result<Vec<u8>, AecError> {
+        self.record(n)?.decode()
+    }
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), AecError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => AecError::InvalidInput("container is truncated"),
+        _ => e.into(),
+    })
+}
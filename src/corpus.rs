@@ -0,0 +1,186 @@
+//! Golden corpus runner (feature `io`).
+//!
+//! A corpus is a directory of case directories, each holding:
+//!
+//! ```text
+//! payload.bin    the AEC payload
+//! params.toml    coding parameters
+//! expected.bin   the decoded bytes (e.g. from libaec)
+//! ```
+//!
+//! `params.toml` uses a flat subset of TOML:
+//!
+//! ```toml
+//! bits_per_sample = 12
+//! block_size = 32
+//! rsi = 128
+//! flags = ["DATA_PREPROCESS", "MSB"]   # `AecFlags` names, optional
+//! output_samples = 1038240             # optional, derived from expected.bin
+//! ```
+//!
+//! [`run_dir`] decodes every case with both the one-shot and the streaming decoder, so
+//! regression cases can be contributed as files rather than Rust tests.
+
+use std::path::{Path, PathBuf};
+
+use crate::decode;
+use crate::decoder::{bytes_per_sample, DecodeStatus, Decoder, Flush};
+use crate::error::AecError;
+use crate::params::{AecFlags, AecParams};
+
+/// One corpus case, loaded from its directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusCase {
+    /// Directory name of the case.
+    pub name: String,
+    pub params: AecParams,
+    pub output_samples: usize,
+    pub payload: Vec<u8>,
+    pub expected: Vec<u8>,
+}
+
+/// A decode path that disagreed with `expected.bin`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusFailure {
+    /// Name of the failing case.
+    pub case: String,
+    /// `"one-shot"` or `"streaming"`.
+    pub path: &'static str,
+    pub message: String,
+}
+
+/// Load a case from a directory holding `payload.bin`, `params.toml` and `expected.bin`.
+pub fn load_case(dir: &Path) -> Result<CorpusCase, AecError> {
+    let manifest = std::fs::read_to_string(dir.join("params.toml"))?;
+    let payload = std::fs::read(dir.join("payload.bin"))?;
+    let expected = std::fs::read(dir.join("expected.bin"))?;
+
+    let mut bits_per_sample = None;
+    let mut block_size = None;
+    let mut rsi = None;
+    let mut flags = AecFlags::empty();
+    let mut output_samples = None;
+    for line in manifest.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or(AecError::InvalidInput("params.toml: expected `key = value`"))?;
+        let value = value.trim();
+        match key.trim() {
+            "bits_per_sample" => bits_per_sample = Some(parse_int(value)?),
+            "block_size" => block_size = Some(parse_int(value)?),
+            "rsi" => rsi = Some(parse_int(value)?),
+            "flags" => flags = parse_flags(value)?,
+            "output_samples" => output_samples = Some(parse_int(value)?),
+            _ => return Err(AecError::InvalidInput("params.toml: unknown key")),
+        }
+    }
+
+    let missing = AecError::InvalidInput("params.toml: bits_per_sample, block_size and rsi are required");
+    let params = AecParams::new(
+        u8::try_from(bits_per_sample.ok_or(missing.clone())?)
+            .map_err(|_| AecError::InvalidInput("params.toml: bits_per_sample out of range"))?,
+        u32::try_from(block_size.ok_or(missing.clone())?)
+            .map_err(|_| AecError::InvalidInput("params.toml: block_size out of range"))?,
+        u32::try_from(rsi.ok_or(missing)?).map_err(|_| AecError::InvalidInput("params.toml: rsi out of range"))?,
+        flags,
+    );
+    let output_samples = match output_samples {
+        Some(n) => usize::try_from(n).map_err(|_| AecError::InvalidInput("params.toml: output_samples out of range"))?,
+        None => expected.len() / bytes_per_sample(params)?,
+    };
+
+    let name = dir.file_name().map_or_else(|| dir.display().to_string(), |n| n.to_string_lossy().into_owned());
+    Ok(CorpusCase { name, params, output_samples, payload, expected })
+}
+
+/// Load every case under `root` (each subdirectory with a `params.toml`), sorted by name.
+pub fn load_dir(root: &Path) -> Result<Vec<CorpusCase>, AecError> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.join("params.toml").is_file() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    dirs.iter().map(|d| load_case(d)).collect()
+}
+
+/// Decode `case` with the one-shot and the streaming decoder and compare both to `expected`.
+pub fn run_case(case: &CorpusCase) -> Vec<CorpusFailure> {
+    let mut failures = Vec::new();
+    let mut check = |path: &'static str, result: Result<Vec<u8>, AecError>| {
+        let message = match result {
+            Ok(got) if got == case.expected => return,
+            Ok(got) => {
+                let at = got.iter().zip(&case.expected).position(|(a, b)| a != b);
+                let at = at.unwrap_or(got.len().min(case.expected.len()));
+                format!("differs at byte {at} (got {} bytes, expected {})", got.len(), case.expected.len())
+            }
+            Err(e) => format!("decode failed: {e}"),
+        };
+        failures.push(CorpusFailure { case: case.name.clone(), path, message });
+    };
+
+    check("one-shot", decode(&case.payload, case.params, case.output_samples));
+    check("streaming", decode_chunked(case));
+    failures
+}
+
+/// Run every case under `root`; returns the number of cases and all failures.
+pub fn run_dir(root: &Path) -> Result<(usize, Vec<CorpusFailure>), AecError> {
+    let cases = load_dir(root)?;
+    let failures = cases.iter().flat_map(run_case).collect();
+    Ok((cases.len(), failures))
+}
+
+/// Streaming decode with odd input and output chunk sizes, so samples straddle both.
+fn decode_chunked(case: &CorpusCase) -> Result<Vec<u8>, AecError> {
+    const IN_CHUNK: usize = 7;
+    const OUT_CHUNK: usize = 5;
+
+    let mut dec = Decoder::new(case.params, case.output_samples)?;
+    let mut out = Vec::with_capacity(case.expected.len());
+    let mut buf = [0u8; OUT_CHUNK];
+    let mut chunks = case.payload.chunks(IN_CHUNK);
+    loop {
+        let flush = if chunks.len() == 0 { Flush::Flush } else { Flush::NoFlush };
+        let (n, status) = dec.decode(&mut buf, flush)?;
+        out.extend_from_slice(&buf[..n]);
+        match status {
+            DecodeStatus::Finished => return Ok(out),
+            DecodeStatus::NeedOutput => {}
+            DecodeStatus::NeedInput => match chunks.next() {
+                Some(chunk) => dec.push_input(chunk),
+                None => return Err(AecError::InvalidInput("streaming decoder requested input after Flush")),
+            },
+        }
+    }
+}
+
+fn parse_int(value: &str) -> Result<u64, AecError> {
+    let value = value.replace('_', "");
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| AecError::InvalidInput("params.toml: invalid integer"))
+}
+
+fn parse_flags(value: &str) -> Result<AecFlags, AecError> {
+    let list = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .ok_or(AecError::InvalidInput("params.toml: flags must be a list of strings"))?;
+    let mut flags = AecFlags::empty();
+    for item in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let name = item
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .ok_or(AecError::InvalidInput("params.toml: flags must be a list of strings"))?;
+        flags |= AecFlags::from_name(name).ok_or(AecError::InvalidInput("params.toml: unknown flag name"))?;
+    }
+    Ok(flags)
+}
@@ -0,0 +1,173 @@
+//! Encoder cost model: the exact size of every coding option for a block, and the choice of the
+//! cheapest, like libaec's rate comparison.
+//!
+//! Sizes leave out the option id, which every option spends alike, and the RSI reference sample;
+//! the low-entropy options include their selector bit.
+
+use crate::rice::encoded_bits;
+
+/// Zero-block count coded as "remainder of segment".
+pub(crate) const ROS: u32 = 5;
+
+/// Coding option of a block that is not part of a zero-block run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockOption {
+    SecondExtension,
+    Split { k: u32 },
+    Uncompressed,
+}
+
+/// Fundamental sequence length coding a run of `z` zero blocks that starts `to_segment_end`
+/// blocks before the end of its segment (or RSI).
+pub(crate) fn zero_run_fs(z: u32, to_segment_end: u32) -> u32 {
+    if z == to_segment_end && z >= ROS {
+        ROS - 1
+    } else if z < ROS {
+        z - 1
+    } else {
+        z
+    }
+}
+
+/// Second Extension symbol of the pair `(a, b)`, or `None` if the pair sums past 12; the inverse
+/// of the decoder's pair mapping.
+pub(crate) fn second_extension_symbol(a: u32, b: u32) -> Option<u32> {
+    if a > 12 || b > 12 || a + b > 12 {
+        return None;
+    }
+    let s = a + b;
+    Some(s * (s + 1) / 2 + b)
+}
+
+/// Bits of the block `d` (an even number of values) coded with the Second Extension.
+pub(crate) fn second_extension_bits(d: &[u32]) -> Option<u64> {
+    let mut bits = 1u64;
+    for pair in d.chunks_exact(2) {
+        bits += u64::from(second_extension_symbol(pair[0], pair[1])?) + 1;
+    }
+    Some(bits)
+}
+
+/// The cheapest option for a block that is not all zeros, and its size in bits.
+///
+/// `d` is the whole block as the Second Extension sees it (a reference sample's slot holds 0);
+/// `coded` the values a split or uncompressed block carries. `max_k` is the largest split
+/// parameter the option id table offers, if any. Ties go to the option listed first in
+/// [`BlockOption`], then to the smaller `k`.
+pub(crate) fn cheapest(d: &[u32], coded: &[u32], bits_per_sample: u32, max_k: Option<u32>) -> (BlockOption, u64) {
+    let mut best = (BlockOption::Uncompressed, coded.len() as u64 * u64::from(bits_per_sample));
+    let mut consider = |option, bits| {
+        if bits <= best.1 {
+            best = (option, bits);
+        }
+    };
+
+    // Beyond the bit length of the largest value every fundamental sequence is a single bit, so
+    // larger `k` only add remainder bits.
+    if let Some(max_k) = max_k {
+        let longest = 32 - coded.iter().fold(0, |acc, &v| acc | v).leading_zeros();
+        for k in (0..=max_k.min(longest)).rev() {
+            consider(BlockOption::Split { k }, encoded_bits(coded, k));
+        }
+    }
+    if let Some(bits) = second_extension_bits(d) {
+        consider(BlockOption::SecondExtension, bits);
+    }
+    best
+}
+
+/// A quick choice for a block that is not all zeros, and its size in bits: the split with `k`
+/// estimated as the floor of the log2 of the mean coded value, the Second Extension only for
+/// blocks whose values average below one, or the uncompressed option if that is smaller.
+pub(crate) fn fast_choice(d: &[u32], coded: &[u32], bits_per_sample: u32, max_k: Option<u32>) -> (BlockOption, u64) {
+    let mut best = (BlockOption::Uncompressed, coded.len() as u64 * u64::from(bits_per_sample));
+    let sum: u64 = coded.iter().map(|&v| u64::from(v)).sum();
+    let mean = sum / coded.len() as u64;
+    if let Some(max_k) = max_k {
+        let k = (63 - mean.max(1).leading_zeros()).min(max_k);
+        let bits = encoded_bits(coded, k);
+        if bits < best.1 {
+            best = (BlockOption::Split { k }, bits);
+        }
+    }
+    if mean == 0 {
+        if let Some(bits) = second_extension_bits(d).filter(|&bits| bits <= best.1) {
+            best = (BlockOption::SecondExtension, bits);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small deterministic xorshift generator, so failures are reproducible.
+    fn xorshift(mut x: u64) -> impl FnMut() -> u64 {
+        move || {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        }
+    }
+
+    #[test]
+    fn cheapest_matches_an_exhaustive_search() {
+        let mut next = xorshift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let bits = [4u32, 8, 12, 16, 32][(next() % 5) as usize];
+            let max_k = [None, Some(1), Some(5), Some(13), Some(29)][(next() % 5) as usize];
+            let scale = (next() % u64::from(bits + 1)) as u32;
+            let d: Vec<u32> = (0..16).map(|_| (next() as u32).checked_shr(32 - scale).unwrap_or(0)).collect();
+            let coded = &d[(next() % 2) as usize..];
+
+            let mut all = vec![(BlockOption::Uncompressed, coded.len() as u64 * u64::from(bits))];
+            all.extend(max_k.into_iter().flat_map(|m| 0..=m).map(|k| (BlockOption::Split { k }, encoded_bits(coded, k))));
+            all.extend(second_extension_bits(&d).map(|b| (BlockOption::SecondExtension, b)));
+            let min = all.iter().map(|&(_, b)| b).min().unwrap();
+
+            let (option, cost) = cheapest(&d, coded, bits, max_k);
+            assert_eq!(cost, min, "{d:?}");
+            assert!(all.contains(&(option, cost)));
+        }
+    }
+
+    #[test]
+    fn fast_choices_stay_close_to_the_cheapest() {
+        let mut next = xorshift(0x853c_49e6_748f_ea9b);
+        let (mut best, mut fast) = (0, 0);
+        for i in 0..4000u64 {
+            // Residual-like blocks of a growing spread.
+            let spread = 1 + i / 100;
+            let d: Vec<u32> = (0..16).map(|_| (next() % spread) as u32).collect();
+            let (option, bits) = fast_choice(&d, &d, 16, Some(13));
+            if let BlockOption::Split { k } = option {
+                assert_eq!(bits, encoded_bits(&d, k));
+            }
+            assert!(bits >= cheapest(&d, &d, 16, Some(13)).1 && bits <= 16 * 16);
+            best += cheapest(&d, &d, 16, Some(13)).1;
+            fast += bits;
+        }
+        assert!(fast * 100 < best * 103, "{fast} vs {best}");
+    }
+
+    #[test]
+    fn second_extension_symbols_invert_the_decoder_pairs() {
+        for m in 0..=90 {
+            let (a, b) = crate::decoder::second_extension_pair(m);
+            assert_eq!(second_extension_symbol(a, b), Some(m));
+        }
+        assert_eq!(second_extension_symbol(6, 7), None);
+        assert_eq!(second_extension_bits(&[0, 0, 1, 0, 0, 2]), Some(1 + 1 + 2 + 6));
+    }
+
+    #[test]
+    fn zero_runs_use_the_remainder_of_segment_code() {
+        assert_eq!(zero_run_fs(1, 64), 0);
+        assert_eq!(zero_run_fs(4, 4), 3);
+        assert_eq!(zero_run_fs(5, 64), 5);
+        assert_eq!(zero_run_fs(5, 5), ROS - 1);
+        assert_eq!(zero_run_fs(64, 64), ROS - 1);
+    }
+}
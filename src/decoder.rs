@@ -1,6 +1,28 @@
-use crate::bitreader::BitReader;
+#[cfg(feature = "io")]
+use std::io::Write;
+
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+
+use crate::bitreader::{BitReader, BitWindow};
+use crate::checksum::{Crc32c, StateHash};
+use crate::block::BlockKind;
 use crate::error::AecError;
+use crate::geometry::{self, blocks_to_segment_end};
+use crate::index::RsiIndex;
+use crate::options::{DecodeOptions, Substitution};
 use crate::params::{AecFlags, AecParams};
+use crate::preprocess::{inverse_preprocess_step, sign_extend};
+use crate::report::{DecodeReport, DecodedField, RangeCheck};
+use crate::rice::read_unary;
+use crate::sample::{check_sample_type, Sample};
+#[cfg(feature = "io")]
+use crate::sink::WriterSink;
+use crate::sink::{
+    BatchSink, BitPackSink, FnSink, OneSampleSink, OutputSink, RequantizeSink, SampleFormat, SliceSink, StrideSink, TransposeSink,
+    TypedSliceSink,
+    VecSink,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Flush {
@@ -32,30 +54,93 @@ pub enum DecodeStatus {
 /// - You must know `output_samples` up front (same as one-shot API).
 pub struct Decoder {
     params: AecParams,
-    bytes_per_sample: usize,
+    format: SampleFormat,
     id_len: usize,
     preprocess: bool,
+    unary_limit: u32,
 
     output_samples: usize,
     samples_written: usize,
 
     // Predictor state (only used with preprocessing enabled).
     predictor_x: Option<i64>,
-    sample_index_within_rsi: u64,
     block_index_within_rsi: u32,
 
     // Input bitstream.
     reader: StreamBitReader,
 
-    // Pending output from a partially-flushed decoded block.
-    pending: Vec<u8>,
+    // Reconstructed samples of the last decoded block not yet handed to a sink.
+    pending: Vec<i64>,
     pending_pos: usize,
 
     // Pending repeated coded values (used for zero-run etc.).
     pending_repeat: Option<PendingRepeat>,
 
-    total_in: usize,
+    // Tail of a sample that did not fit into the previous output buffer.
+    carry: Carry,
+
+    // Scratch space for Rice split blocks.
+    scratch: Vec<u32>,
+
     total_out: usize,
+
+    // Bits skipped by PAD_RSI alignment.
+    padding_bits: usize,
+
+    // Block budget.
+    blocks_decoded: u64,
+    max_blocks: Option<u64>,
+    max_blocks_per_call: Option<u64>,
+
+    // Caller context attached to errors and reports.
+    context: Option<Arc<str>>,
+
+    // Checksum of the bytes handed out, if requested.
+    crc32c: Option<Crc32c>,
+    // Counts samples outside `DecodeOptions::expected_range`.
+    range_check: Option<RangeCheck>,
+    // `DecodeOptions::missing_values`.
+    substitution: Option<Substitution>,
+    // `DecodeOptions::state_hash`, and the number of RSI boundaries passed.
+    state_hash: Option<StateHash>,
+    rsis_completed: u64,
+
+    // Per-RSI statistics, only gathered while a callback is set.
+    rsi_stats: RsiStats,
+    completed_rsi: Option<RsiStats>,
+    rsi_callback: Option<RsiCallback>,
+}
+
+type RsiCallback = Box<dyn FnMut(&RsiStats) + Send>;
+
+/// Statistics of one reference sample interval, passed to [`Decoder::set_rsi_callback`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RsiStats {
+    /// Position of the RSI among those decoded by this decoder, starting at 0.
+    pub index: u64,
+    /// Payload bits spent on the RSI, including `PAD_RSI` padding.
+    pub bits: usize,
+    /// Blocks covered by zero-block runs.
+    pub zero_blocks: u32,
+    /// Second Extension blocks.
+    pub second_extension_blocks: u32,
+    /// Rice split blocks.
+    pub split_blocks: u32,
+    /// Uncompressed blocks.
+    pub uncompressed_blocks: u32,
+    /// Samples decoded from the RSI (fewer than `rsi * block_size` for the last one).
+    pub samples: usize,
+    /// Smallest decoded sample value, `None` if no sample was decoded.
+    pub min: Option<i64>,
+    /// Largest decoded sample value, `None` if no sample was decoded.
+    pub max: Option<i64>,
+}
+
+impl RsiStats {
+    fn observe(&mut self, value: i64) {
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -64,31 +149,109 @@ struct PendingRepeat {
     remaining: usize,
 }
 
+/// Bytes of one sample split across two output buffers.
+#[derive(Debug, Clone, Default)]
+struct Carry {
+    bytes: [u8; 4],
+    pos: usize,
+    len: usize,
+}
+
+impl Carry {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.len
+    }
+
+    fn fill(&mut self, format: SampleFormat, value: i64) {
+        format.write(value, &mut self.bytes[..format.bytes_per_sample]);
+        self.pos = 0;
+        self.len = format.bytes_per_sample;
+    }
+
+    fn rest(&self) -> &[u8] {
+        &self.bytes[self.pos..self.len]
+    }
+
+    fn drain_into(&mut self, out: &mut [u8]) -> usize {
+        let n = out.len().min(self.len - self.pos);
+        out[..n].copy_from_slice(&self.bytes[self.pos..self.pos + n]);
+        self.pos += n;
+        n
+    }
+}
+
 impl Decoder {
     pub fn new(params: AecParams, output_samples: usize) -> Result<Self, AecError> {
-        validate_params(params)?;
-        let bytes_per_sample = bytes_per_sample(params)?;
+        Self::with_options(params, output_samples, &DecodeOptions::default())
+    }
+
+    /// Create a decoder with non-default [`DecodeOptions`].
+    pub fn with_options(params: AecParams, output_samples: usize, options: &DecodeOptions) -> Result<Self, AecError> {
+        validate_params(params, options).map_err(|e| options.tag(e))?;
+        let format = SampleFormat::for_output(params, options)?;
         let id_len = id_len(params)?;
 
         Ok(Self {
             params,
-            bytes_per_sample,
+            format,
             id_len,
             preprocess: params.flags.contains(AecFlags::DATA_PREPROCESS),
+            unary_limit: unary_limit(params, options),
             output_samples,
             samples_written: 0,
             predictor_x: None,
-            sample_index_within_rsi: 0,
             block_index_within_rsi: 0,
             reader: StreamBitReader::new(),
             pending: Vec::new(),
             pending_pos: 0,
             pending_repeat: None,
-            total_in: 0,
+            carry: Carry::default(),
+            scratch: Vec::new(),
             total_out: 0,
+            padding_bits: 0,
+            blocks_decoded: 0,
+            max_blocks: options.max_blocks,
+            max_blocks_per_call: options.max_blocks_per_call,
+            context: options.context.clone(),
+            crc32c: options.crc32c.then(Crc32c::new),
+            range_check: RangeCheck::new(options.expected_range.as_ref()),
+            substitution: Substitution::new(options),
+            state_hash: options.state_hash.then(StateHash::new),
+            rsis_completed: 0,
+            rsi_stats: RsiStats::default(),
+            completed_rsi: None,
+            rsi_callback: None,
         })
     }
 
+    /// Create a decoder that starts at RSI `first_rsi` of a payload described by `index`.
+    ///
+    /// Push the payload from `index.byte_offset(first_rsi)` onwards; `output_samples` counts
+    /// from the first sample of that RSI (see [`RsiIndex::first_sample`]).
+    pub fn with_index(
+        params: AecParams,
+        output_samples: usize,
+        index: &RsiIndex,
+        first_rsi: usize,
+    ) -> Result<Self, AecError> {
+        index.check_params(params)?;
+        let bit_offset = index
+            .bit_offset(first_rsi)
+            .ok_or(AecError::InvalidInput("RSI is not covered by the index"))?;
+        let mut dec = Self::new(params, output_samples)?;
+        dec.reader.seek(bit_offset % 8);
+        Ok(dec)
+    }
+
+    /// Call `callback` with the [`RsiStats`] of every RSI as soon as it has been decoded.
+    ///
+    /// This allows monitoring a live downlink without a second pass over the payload. The last
+    /// RSI is reported once `output_samples` is reached, even if it is partial. Statistics are
+    /// gathered from the next decoded block onwards.
+    pub fn set_rsi_callback(&mut self, callback: impl FnMut(&RsiStats) + Send + 'static) {
+        self.rsi_callback = Some(Box::new(callback));
+    }
+
     /// Append more bytes to the input buffer.
     pub fn push_input(&mut self, input: &[u8]) {
         self.reader.push(input);
@@ -96,7 +259,7 @@ impl Decoder {
 
     /// Total number of input bytes consumed so far.
     pub fn total_in(&self) -> usize {
-        self.total_in
+        self.reader.bits_read_total() / 8
     }
 
     /// Total number of output bytes produced so far.
@@ -109,67 +272,235 @@ impl Decoder {
         self.reader.avail_bytes()
     }
 
+    /// Number of samples the decoder is expected to produce.
+    pub fn output_samples(&self) -> usize {
+        self.output_samples
+    }
+
+    /// Change the number of samples the decoder is expected to produce.
+    ///
+    /// This is for cases where the exact count only becomes known after decoding has started
+    /// (e.g. GRIB2 Section 5 arrives after streaming of Section 7 has begun).
+    ///
+    /// The count may be changed until the last expected sample has been decoded, but never
+    /// below the number of samples already decoded.
+    pub fn set_output_samples(&mut self, output_samples: usize) -> Result<(), AecError> {
+        if self.samples_written >= self.output_samples {
+            return Err(AecError::InvalidInput("cannot change output_samples after decoding finished"));
+        }
+        if output_samples < self.samples_written {
+            return Err(AecError::InvalidInput("output_samples is less than samples already decoded"));
+        }
+        self.output_samples = output_samples;
+        Ok(())
+    }
+
+    /// Bitstream facts so far; `trailing_bytes` counts buffered input beyond the decoded part.
+    pub fn report(&self) -> DecodeReport {
+        let buffered = self.reader.total_bytes_dropped + self.reader.buf.len();
+        let report = DecodeReport {
+            context: self.context.clone(),
+            crc32c: self.crc32c.map(|c| c.finish()),
+            state_hash: self.state_hash.map(|h| h.finish()),
+            ..DecodeReport::new(self.reader.bits_read_total(), self.padding_bits, buffered)
+        };
+        RangeCheck::report(self.range_check.as_ref(), report)
+    }
+
+    /// Whether all `output_samples` have been decoded and handed out.
+    pub fn is_finished(&self) -> bool {
+        self.values_exhausted() && self.carry.is_empty()
+    }
+
     /// Decode into `out` and return (written_bytes, status).
     pub fn decode(&mut self, out: &mut [u8], flush: Flush) -> Result<(usize, DecodeStatus), AecError> {
-        if self.samples_written >= self.output_samples {
+        if self.is_finished() {
             return Ok((0, DecodeStatus::Finished));
         }
 
-        let mut written: usize = 0;
-
-        // Fast-path: flush any pending bytes first.
-        written += self.flush_pending(out, written);
-        if written >= out.len() {
+        let blocks_at_call_start = self.blocks_decoded;
+        let mut written = self.carry.drain_into(out);
+        if !self.carry.is_empty() {
             self.total_out += written;
+            if let Some(crc) = self.crc32c.as_mut() {
+                crc.update(&out[..written]);
+            }
             return Ok((written, DecodeStatus::NeedOutput));
         }
 
-        // Then flush any pending repeat-run.
-        if let Some(status) = self.flush_repeat(out, &mut written)? {
-            self.total_out += written;
-            return Ok((written, status));
+        let format = self.format;
+        let mut sink = SliceSink::new(&mut out[written..], format);
+        let mut status = self.decode_to(&mut sink, flush, blocks_at_call_start);
+        written += sink.len();
+
+        // A sample straddling the end of `out` is split across this call and the next.
+        if matches!(status, Ok(DecodeStatus::NeedOutput)) && written < out.len() {
+            let mut one = OneSampleSink::default();
+            status = self.decode_to(&mut one, flush, blocks_at_call_start);
+            if let Some(value) = one.value {
+                self.carry.fill(format, value);
+                written += self.carry.drain_into(&mut out[written..]);
+                status = Ok(DecodeStatus::NeedOutput);
+            }
         }
 
-        // Decode blocks/runs until output is full or decoding completes.
-        while written < out.len() {
-            if self.samples_written >= self.output_samples {
-                self.total_out += written;
-                return Ok((written, DecodeStatus::Finished));
-            }
+        self.total_out += written;
+        if let Some(crc) = self.crc32c.as_mut() {
+            crc.update(&out[..written]);
+        }
+        Ok((written, status?))
+    }
+
+    /// Decode as much as the buffered input allows into `out`; returns the bytes written.
+    ///
+    /// Unlike [`Decoder::decode`], running out of input is never an error: the call simply
+    /// returns what could be produced (possibly 0), and the caller pushes more input when it
+    /// arrives. Use [`Decoder::is_finished`] to detect completion. A payload that is truncated
+    /// for good therefore shows up as no further progress rather than as an error.
+    pub fn decode_available(&mut self, out: &mut [u8]) -> Result<usize, AecError> {
+        let (n, _status) = self.decode(out, Flush::NoFlush)?;
+        Ok(n)
+    }
+
+    /// Decode all remaining output from the input pushed so far.
+    ///
+    /// This runs the streaming state machine to completion with [`Flush::Flush`], for callers
+    /// that already hold the whole payload. Fails with [`AecError::UnexpectedEofDuringDecode`]
+    /// if the buffered input ends early.
+    pub fn decode_all(&mut self) -> Result<Vec<u8>, AecError> {
+        let remaining_samples = self.output_samples.saturating_sub(self.samples_written);
+        let remaining_bytes = remaining_samples
+            .checked_add(self.pending.len() - self.pending_pos)
+            .and_then(|n| n.checked_mul(self.format.bytes_per_sample))
+            .ok_or_else(|| AecError::InvalidInput("output too large").tagged(self.context.as_ref()))?;
+
+        let blocks_at_call_start = self.blocks_decoded;
+        let mut out = Vec::with_capacity(remaining_bytes + self.carry.rest().len());
+        out.extend_from_slice(self.carry.rest());
+        self.carry = Carry::default();
+
+        let mut sink = VecSink::new(&mut out, self.format);
+        self.decode_to(&mut sink, Flush::Flush, blocks_at_call_start)?;
+        self.total_out += out.len();
+        if let Some(crc) = self.crc32c.as_mut() {
+            crc.update(&out);
+        }
+        Ok(out)
+    }
+
+    /// Decode all remaining samples from the input pushed so far, handing each value to `f`.
+    ///
+    /// Like [`Decoder::decode_all`], but without packing samples into bytes.
+    pub(crate) fn decode_values(&mut self, f: impl FnMut(i64)) -> Result<(), AecError> {
+        let blocks_at_call_start = self.blocks_decoded;
+        self.decode_to(&mut FnSink(f), Flush::Flush, blocks_at_call_start)?;
+        Ok(())
+    }
+
+    /// Iterate over the remaining samples, decoding them lazily from the input pushed so far.
+    ///
+    /// Samples are decoded a batch at a time, so huge fields can be processed without holding
+    /// the whole output. Values are `bits_per_sample` wide; signed samples come out in 32-bit
+    /// two's complement (cast with `as i32`). The input must be complete, as with
+    /// [`Flush::Flush`]: a truncated payload yields the samples before the truncation and then
+    /// an error. A sample partly handed out by an earlier [`Decoder::decode`] call is not
+    /// repeated.
+    ///
+    /// ```
+    /// use rust_aec::{encode, AecFlags, AecParams, Decoder};
+    ///
+    /// let params = AecParams::new(8, 16, 4, AecFlags::DATA_PREPROCESS);
+    /// let samples: Vec<u8> = (0..=255).collect();
+    /// let mut dec = Decoder::new(params, samples.len())?;
+    /// dec.push_input(&encode(&samples, params)?);
+    /// let sum = dec.samples().try_fold(0u32, |sum, x| x.map(|x| sum + x))?;
+    /// assert_eq!(sum, 255 * 256 / 2);
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn samples(&mut self) -> SampleIter<'_> {
+        self.carry = Carry::default();
+        let blocks_at_call_start = self.blocks_decoded;
+        SampleIter { dec: self, batch: Vec::new(), pos: 0, blocks_at_call_start, error: None, done: false }
+    }
 
-            // Ensure predictor state is reset at RSI boundary when preprocessing is enabled.
-            if self.preprocess && self.block_index_within_rsi == 0 {
-                self.predictor_x = None;
+    /// Decode into `writer` as far as the buffered input allows; returns (written_bytes, status).
+    ///
+    /// This is [`Decoder::decode`] without an output buffer limit, so the status is never
+    /// [`DecodeStatus::NeedOutput`]. With [`Flush::Flush`] it decodes to completion.
+    #[cfg(feature = "io")]
+    pub fn decode_to_writer<W: Write + ?Sized>(
+        &mut self,
+        writer: &mut W,
+        flush: Flush,
+    ) -> Result<(usize, DecodeStatus), AecError> {
+        let blocks_at_call_start = self.blocks_decoded;
+        let carried = self.carry.rest().len();
+        writer.write_all(self.carry.rest()).map_err(|e| AecError::from(e).tagged(self.context.as_ref()))?;
+        let mut crc = self.crc32c.take();
+        if let Some(crc) = crc.as_mut() {
+            crc.update(self.carry.rest());
+        }
+        self.carry = Carry::default();
+
+        let mut sink = WriterSink::new(writer, self.format, crc.as_mut());
+        let status = self.decode_to(&mut sink, flush, blocks_at_call_start);
+        let finished = sink.finish();
+        self.crc32c = crc;
+        let written = carried + finished.map_err(|e| e.tagged(self.context.as_ref()))?;
+        self.total_out += written;
+        Ok((written, status?))
+    }
+
+    /// Core decode loop shared by all output flavours: hand pending samples to `sink`, decoding
+    /// further units until the sink is full, input runs out, or all samples are produced.
+    fn decode_to<S: OutputSink>(
+        &mut self,
+        sink: &mut S,
+        flush: Flush,
+        blocks_at_call_start: u64,
+    ) -> Result<DecodeStatus, AecError> {
+        self.decode_to_untagged(sink, flush, blocks_at_call_start)
+            .map_err(|e| e.tagged(self.context.as_ref()))
+    }
+
+    fn decode_to_untagged<S: OutputSink>(
+        &mut self,
+        sink: &mut S,
+        flush: Flush,
+        blocks_at_call_start: u64,
+    ) -> Result<DecodeStatus, AecError> {
+        loop {
+            // Decoded samples go out in bulk; scheduled zero-block repeats one at a time.
+            let taken = sink.put_slice(&self.pending[self.pending_pos..])?;
+            self.pending_pos += taken;
+            while sink.remaining() > 0 {
+                let Some(value) = self.next_value()? else { break };
+                sink.put(value)?;
+            }
+            if self.values_exhausted() {
+                return Ok(DecodeStatus::Finished);
             }
+            if sink.remaining() == 0 {
+                return Ok(DecodeStatus::NeedOutput);
+            }
+
+            check_block_budget(self.blocks_decoded, self.max_blocks, "blocks")?;
+            check_block_budget(self.blocks_decoded - blocks_at_call_start, self.max_blocks_per_call, "blocks per call")?;
 
             // If we don't have enough input to decode the next unit, request more.
             let snapshot = self.snapshot();
             match self.decode_next_unit() {
                 Ok(()) => {
-                    // Compaction: count consumed whole bytes.
-                    let consumed = self.reader.compact_consumed_bytes();
-                    self.total_in += consumed;
-
-                    // Flush any newly produced pending output/repeat.
-                    written += self.flush_pending(out, written);
-                    if written >= out.len() {
-                        self.total_out += written;
-                        return Ok((written, DecodeStatus::NeedOutput));
+                    self.reader.compact();
+                    if let (Some(stats), Some(callback)) = (self.completed_rsi.take(), self.rsi_callback.as_mut()) {
+                        callback(&stats);
                     }
-
-                    if let Some(status) = self.flush_repeat(out, &mut written)? {
-                        self.total_out += written;
-                        return Ok((written, status));
-                    }
-
-                    // Otherwise, loop and decode more.
                 }
                 Err(AecError::UnexpectedEof { .. }) | Err(AecError::UnexpectedEofDuringDecode { .. }) => {
                     // Restore state and request more input unless flushing.
                     self.restore(snapshot);
-                    self.total_out += written;
                     return match flush {
-                        Flush::NoFlush => Ok((written, DecodeStatus::NeedInput)),
+                        Flush::NoFlush => Ok(DecodeStatus::NeedInput),
                         Flush::Flush => Err(AecError::UnexpectedEofDuringDecode {
                             bit_pos: self.reader.bits_read_total(),
                             samples_written: self.samples_written,
@@ -182,385 +513,369 @@ impl Decoder {
                 }
             }
         }
+    }
 
-        self.total_out += written;
-        Ok((written, DecodeStatus::NeedOutput))
+    /// All `output_samples` decoded and every decoded sample handed to a sink.
+    fn values_exhausted(&self) -> bool {
+        self.samples_written >= self.output_samples && self.pending_pos >= self.pending.len()
     }
 
-    fn flush_pending(&mut self, out: &mut [u8], written: usize) -> usize {
-        if self.pending_pos >= self.pending.len() {
-            self.pending.clear();
-            self.pending_pos = 0;
-            return 0;
+    /// Next sample to output, if one is decoded (or scheduled as a repeat) already.
+    fn next_value(&mut self) -> Result<Option<i64>, AecError> {
+        if let Some(&x) = self.pending.get(self.pending_pos) {
+            self.pending_pos += 1;
+            return Ok(Some(x));
         }
 
-        let available = out.len().saturating_sub(written);
-        let remaining = self.pending.len().saturating_sub(self.pending_pos);
-        let to_copy = available.min(remaining);
-
-        out[written..written + to_copy]
-            .copy_from_slice(&self.pending[self.pending_pos..self.pending_pos + to_copy]);
-        self.pending_pos += to_copy;
-        to_copy
-    }
-
-    fn flush_repeat(&mut self, out: &mut [u8], written: &mut usize) -> Result<Option<DecodeStatus>, AecError> {
         let Some(rep) = self.pending_repeat.as_mut() else {
             return Ok(None);
         };
-
-        while *written < out.len() && rep.remaining > 0 {
-            if self.samples_written >= self.output_samples {
-                self.pending_repeat = None;
-                return Ok(Some(DecodeStatus::Finished));
-            }
-
-            // Write exactly one sample (packed bytes).
-            let out_start = *written;
-            let out_end = out_start + self.bytes_per_sample;
-            if out_end > out.len() {
-                return Ok(Some(DecodeStatus::NeedOutput));
-            }
-
-            // Use the same semantics as emit_coded_value(): preprocessing applies here.
-            let mut tmp = OutBuf::new(&mut out[out_start..out_end], self.bytes_per_sample);
-            tmp.pos = 0;
-            emit_coded_value(
-                &mut tmp,
-                &mut self.predictor_x,
-                self.params,
-                self.bytes_per_sample,
-                rep.coded_value,
-                &mut self.sample_index_within_rsi,
-                usize::MAX,
-            )?;
-            *written += self.bytes_per_sample;
-            self.samples_written += 1;
-            rep.remaining -= 1;
-        }
-
-        if rep.remaining == 0 {
+        if rep.remaining == 0 || self.samples_written >= self.output_samples {
             self.pending_repeat = None;
+            return Ok(None);
         }
-
-        if *written >= out.len() {
-            return Ok(Some(DecodeStatus::NeedOutput));
+        rep.remaining -= 1;
+        let coded_value = rep.coded_value;
+        let x = self.reconstruct(coded_value)?;
+        if let Some(check) = self.range_check.as_mut() {
+            check.observe(self.samples_written, x);
         }
-        Ok(None)
+        self.samples_written += 1;
+        Ok(Some(self.substitution.as_ref().map_or(x, |s| s.apply(x))))
     }
 
     fn snapshot(&self) -> Snapshot {
         Snapshot {
             predictor_x: self.predictor_x,
-            sample_index_within_rsi: self.sample_index_within_rsi,
             block_index_within_rsi: self.block_index_within_rsi,
             samples_written: self.samples_written,
-            reader: self.reader.clone(),
-            pending: self.pending.clone(),
-            pending_pos: self.pending_pos,
-            pending_repeat: self.pending_repeat.clone(),
+            blocks_decoded: self.blocks_decoded,
+            padding_bits: self.padding_bits,
+            reader_bit_pos: self.reader.bit_pos(),
         }
     }
 
     fn restore(&mut self, s: Snapshot) {
         self.predictor_x = s.predictor_x;
-        self.sample_index_within_rsi = s.sample_index_within_rsi;
         self.block_index_within_rsi = s.block_index_within_rsi;
         self.samples_written = s.samples_written;
-        self.reader = s.reader;
-        self.pending = s.pending;
-        self.pending_pos = s.pending_pos;
-        self.pending_repeat = s.pending_repeat;
+        self.blocks_decoded = s.blocks_decoded;
+        self.padding_bits = s.padding_bits;
+        self.reader.seek(s.reader_bit_pos);
+        // Units are only decoded once everything pending has been handed out.
+        self.pending.clear();
+        self.pending_pos = 0;
+        self.pending_repeat = None;
     }
 
     fn decode_next_unit(&mut self) -> Result<(), AecError> {
-        // Ensure no pending bytes: we decode one unit into pending.
-        if self.pending_pos < self.pending.len() {
+        let mut values = std::mem::take(&mut self.pending);
+        values.clear();
+        self.pending_pos = 0;
+        let result = self.decode_unit_into(&mut values);
+        self.pending = values;
+        result
+    }
+
+    /// Decode one block (or zero-block run) into `values`, mirroring the one-shot decoder: reading
+    /// stops as soon as `output_samples` is reached, except inside a Rice split block.
+    fn decode_unit_into(&mut self, values: &mut Vec<i64>) -> Result<(), AecError> {
+        let block_size = self.params.block_size as usize;
+        let wanted = self.output_samples.saturating_sub(self.samples_written);
+        let start_bits = self.reader.bits_read_total();
+        if self.params.bits_per_sample == 0 {
+            self.constant_unit(values, wanted.min(block_size));
             return Ok(());
         }
 
-        // Build a small output buffer for a single block.
-        let mut block_out: Vec<u8> = vec![0u8; self.bytes_per_sample * (self.params.block_size as usize)];
-        let mut out = OutBuf::new(&mut block_out, self.bytes_per_sample);
-
         // Start-of-RSI predictor reset.
-        if self.preprocess && self.block_index_within_rsi == 0 {
+        let ref_pending = self.preprocess && self.block_index_within_rsi == 0;
+        if ref_pending {
             self.predictor_x = None;
         }
 
-        let at_rsi_start = self.preprocess && self.block_index_within_rsi == 0;
-        let ref_pending = at_rsi_start;
-        let mut reference_sample_consumed = false;
-
         // Read block option id.
         let id = self.reader.read_bits_u32(self.id_len)?;
         let max_id = (1u32 << self.id_len) - 1;
-
-        // Helper to consume the RSI reference sample.
-        let mut consume_reference = |this: &mut Self, out: &mut OutBuf<'_>| -> Result<(), AecError> {
-            let ref_raw = this.reader.read_bits_u32(this.params.bits_per_sample as usize)?;
-            let ref_val = if this.params.flags.contains(AecFlags::DATA_SIGNED) {
-                sign_extend(ref_raw, this.params.bits_per_sample)
-            } else {
-                ref_raw as i64
-            };
-            write_sample(out, ref_val, this.params)?;
-            this.predictor_x = Some(ref_val);
-            reference_sample_consumed = true;
-            this.sample_index_within_rsi += 1;
-            Ok(())
-        };
-
-        let remaining_total_samples = self.output_samples.saturating_sub(self.samples_written);
-        let max_samples_this_block = (self.params.block_size as usize).min(remaining_total_samples);
+        let mut blocks = 1u32;
+        let kind;
 
         if id == 0 {
-            // Low-entropy family.
+            // Low-entropy family; the selector comes before the optional RSI reference.
             let selector = self.reader.read_bit()?;
-
-            // For low-entropy blocks, selector comes before optional RSI reference.
             if ref_pending {
-                consume_reference(self, &mut out)?;
-                self.samples_written += 1;
-            }
-
-            // Remaining capacity after the optional reference sample.
-            let remaining_total_samples = self.output_samples.saturating_sub(self.samples_written);
-
-            let mut remaining_in_block = self.params.block_size as usize;
-            if reference_sample_consumed {
-                remaining_in_block = remaining_in_block.saturating_sub(1);
+                values.push(self.read_reference()?);
             }
 
-            if !selector {
+            if !selector && values.len() >= wanted {
+                // The reference sample completed the output; like the one-shot decoder (and
+                // libaec), don't read the run length.
+                kind = BlockKind::ZeroRun { blocks };
+            } else if !selector {
                 // Zero-block run: do not materialize huge output; schedule repeats.
-                let fs = read_unary_stream(&mut self.reader)?;
-                let mut z_blocks = fs + 1;
+                let fs = self.reader.read_unary(self.unary_limit)?;
+                let mut z_blocks = fs.saturating_add(1);
                 const ROS: u32 = 5;
                 if z_blocks == ROS {
-                    let b = self.block_index_within_rsi;
-                    let fill1 = self.params.rsi.saturating_sub(b);
-                    let fill2 = 64u32.saturating_sub(b % 64);
-                    z_blocks = fill1.min(fill2);
+                    z_blocks = blocks_to_segment_end(self.params, self.block_index_within_rsi);
                 } else if z_blocks > ROS {
-                    z_blocks = z_blocks.saturating_sub(1);
+                    z_blocks -= 1;
                 }
+                blocks = z_blocks;
+                kind = BlockKind::ZeroRun { blocks };
 
-                let mut zeros_samples = (z_blocks as usize)
-                    .checked_mul(self.params.block_size as usize)
-                    .ok_or(AecError::InvalidInput("zero-run overflow"))?;
-                if reference_sample_consumed {
-                    zeros_samples = zeros_samples.saturating_sub(1);
-                }
-
-                // Limit to remaining total samples (reference already counted in `samples_written`).
-                zeros_samples = zeros_samples.min(remaining_total_samples);
-
-                // Emit any already-written reference sample into pending bytes.
-                let produced_len = out.len();
-                drop(out);
-                self.pending = block_out[..produced_len].to_vec();
-                self.pending_pos = 0;
+                let zeros_samples = (z_blocks as usize)
+                    .checked_mul(block_size)
+                    .ok_or(AecError::InvalidInput("zero-run overflow"))?
+                    .saturating_sub(values.len());
 
-                // Schedule coded-value repeats (coded_value = 0).
+                // Not clipped to the remaining total: `next_value` stops at `output_samples`, and
+                // keeping the full run lets `set_output_samples` grow the target mid-run.
                 if zeros_samples > 0 {
                     self.pending_repeat = Some(PendingRepeat { coded_value: 0, remaining: zeros_samples });
                 }
-
-                // Advance block counter by z_blocks.
-                self.block_index_within_rsi = self.block_index_within_rsi.saturating_add(z_blocks);
-                if self.block_index_within_rsi >= self.params.rsi {
-                    self.block_index_within_rsi %= self.params.rsi;
-                    if self.params.flags.contains(AecFlags::PAD_RSI) {
-                        self.reader.align_to_byte();
+            } else {
+                kind = BlockKind::SecondExtension;
+                // Second Extension: pairs are aligned to even sample indices, and a reference
+                // sample takes slot 0, so the first symbol then only yields its odd element.
+                let mut i = values.len();
+                while i < block_size && values.len() < wanted {
+                    let m = self.reader.read_unary(self.unary_limit)?;
+                    if m > 90 {
+                        return Err(AecError::InvalidInput("Second Extension unary symbol too large"));
+                    }
+                    let (a, b) = second_extension_pair(m);
+                    if i % 2 == 0 {
+                        values.push(self.reconstruct(a)?);
+                        i += 1;
+                    }
+                    if i < block_size && values.len() < wanted {
+                        values.push(self.reconstruct(b)?);
+                        i += 1;
                     }
-                    self.sample_index_within_rsi = 0;
-                }
-
-                // We do not increment samples_written here; repeats are accounted for in flush.
-                return Ok(());
-            }
-
-            // Second Extension option.
-            let mut produced_samples = 0usize;
-            while remaining_in_block > 0 && produced_samples < max_samples_this_block.saturating_sub(reference_sample_consumed as usize) {
-                let m = read_unary_stream(&mut self.reader)?;
-                if m > 90 {
-                    return Err(AecError::InvalidInput("Second Extension unary symbol too large"));
-                }
-                let (a, b) = second_extension_pair(m);
-
-                // Emit up to two values.
-                if produced_samples < max_samples_this_block.saturating_sub(reference_sample_consumed as usize) {
-                    emit_coded_value(
-                        &mut out,
-                        &mut self.predictor_x,
-                        self.params,
-                        self.bytes_per_sample,
-                        a,
-                        &mut self.sample_index_within_rsi,
-                        usize::MAX,
-                    )?;
-                    produced_samples += 1;
-                    self.samples_written += 1;
-                }
-
-                if remaining_in_block > 0 {
-                    remaining_in_block = remaining_in_block.saturating_sub(1);
-                }
-                if produced_samples < max_samples_this_block.saturating_sub(reference_sample_consumed as usize) {
-                    emit_coded_value(
-                        &mut out,
-                        &mut self.predictor_x,
-                        self.params,
-                        self.bytes_per_sample,
-                        b,
-                        &mut self.sample_index_within_rsi,
-                        usize::MAX,
-                    )?;
-                    produced_samples += 1;
-                    self.samples_written += 1;
-                }
-                if remaining_in_block > 0 {
-                    remaining_in_block = remaining_in_block.saturating_sub(1);
                 }
             }
         } else if id == max_id {
-            // Uncompressed block.
+            // Uncompressed block; the reference sample is the first raw sample.
+            kind = BlockKind::Uncompressed;
             if ref_pending {
-                consume_reference(self, &mut out)?;
-                self.samples_written += 1;
+                values.push(self.read_reference()?);
             }
-
-            let mut remaining_in_block = self.params.block_size as usize;
-            if reference_sample_consumed {
-                remaining_in_block = remaining_in_block.saturating_sub(1);
-            }
-
-            for _ in 0..remaining_in_block {
-                if self.samples_written >= self.output_samples {
-                    break;
-                }
+            while values.len() < block_size && values.len() < wanted {
                 let v = self.reader.read_bits_u32(self.params.bits_per_sample as usize)?;
-                emit_coded_value(
-                    &mut out,
-                    &mut self.predictor_x,
-                    self.params,
-                    self.bytes_per_sample,
-                    v,
-                    &mut self.sample_index_within_rsi,
-                    usize::MAX,
-                )?;
-                self.samples_written += 1;
+                values.push(self.reconstruct(v)?);
             }
         } else {
-            // Rice split.
-            let k = (id - 1) as usize;
+            // Rice split: all fundamental sequences first, then all k-bit remainders.
+            let k = id - 1;
+            kind = BlockKind::Split { k };
             if ref_pending {
-                consume_reference(self, &mut out)?;
-                self.samples_written += 1;
+                values.push(self.read_reference()?);
             }
-
-            let mut remaining_in_block = self.params.block_size as usize;
-            if reference_sample_consumed {
-                remaining_in_block = remaining_in_block.saturating_sub(1);
-            }
-            let n = remaining_in_block.min(self.output_samples.saturating_sub(self.samples_written));
-            let mut tmp: Vec<u32> = vec![0u32; n];
-
-            for i in 0..n {
-                let q = read_unary_stream(&mut self.reader)?;
-                tmp[i] = (q as u32)
-                    .checked_shl(k as u32)
-                    .ok_or(AecError::InvalidInput("rice shift overflow"))?;
-            }
-            if k > 0 {
-                for i in 0..n {
-                    let rem = self.reader.read_bits_u32(k)?;
-                    tmp[i] |= rem;
+            if values.len() < wanted {
+                let mut coded = std::mem::take(&mut self.scratch);
+                coded.clear();
+                let result = self.read_split(&mut coded, block_size - values.len(), k);
+                if result.is_ok() {
+                    for &v in coded.iter() {
+                        values.push(self.reconstruct(v)?);
+                    }
                 }
-            }
-            for v in tmp {
-                if self.samples_written >= self.output_samples {
-                    break;
-                }
-                emit_coded_value(
-                    &mut out,
-                    &mut self.predictor_x,
-                    self.params,
-                    self.bytes_per_sample,
-                    v,
-                    &mut self.sample_index_within_rsi,
-                    usize::MAX,
-                )?;
-                self.samples_written += 1;
+                self.scratch = coded;
+                result?;
             }
         }
 
-        // Commit block output.
-        let produced_len = out.len();
-        drop(out);
-        self.pending = block_out[..produced_len].to_vec();
-        self.pending_pos = 0;
+        values.truncate(wanted);
+        if let Some(check) = self.range_check.as_mut() {
+            for (i, &v) in values.iter().enumerate() {
+                check.observe(self.samples_written + i, v);
+            }
+        }
+        self.samples_written += values.len();
 
         // Advance block counter.
-        self.block_index_within_rsi = self.block_index_within_rsi.saturating_add(1);
-        if self.preprocess && self.block_index_within_rsi >= self.params.rsi {
-            self.block_index_within_rsi = 0;
-            self.sample_index_within_rsi = 0;
+        self.blocks_decoded += blocks as u64;
+        self.block_index_within_rsi = self.block_index_within_rsi.saturating_add(blocks);
+        if self.block_index_within_rsi >= self.params.rsi {
+            self.block_index_within_rsi %= self.params.rsi;
             if self.params.flags.contains(AecFlags::PAD_RSI) {
-                self.reader.align_to_byte();
+                self.padding_bits += self.reader.align_to_byte();
             }
+            // Zero-run repeats are still pending, but count as emitted; the predictor is the one
+            // they will leave behind (a zero step pulls an out-of-range predictor back in range).
+            let repeated = self.pending_repeat.as_ref().map_or(0, |rep| rep.remaining);
+            let repeated = repeated.min(self.output_samples - self.samples_written);
+            let predictor = match &self.pending_repeat {
+                Some(rep) if repeated > 0 && self.preprocess => {
+                    self.predictor_x.map(|x| inverse_preprocess_step(x, rep.coded_value, self.params))
+                }
+                _ => self.predictor_x,
+            };
+            hash_rsi_end(
+                self.state_hash.as_mut(),
+                &mut self.rsis_completed,
+                self.params,
+                self.samples_written + repeated,
+                self.reader.bits_read_total(),
+                predictor,
+            );
+        }
+
+        if self.rsi_callback.is_some() {
+            self.record_rsi_stats(kind, values, start_bits);
+        }
+        if let Some(substitution) = &self.substitution {
+            values.iter_mut().for_each(|v| *v = substitution.apply(*v));
+        }
+        Ok(())
+    }
+
+    /// `n` samples of a constant field (no bits per sample): zeros, without reading input.
+    fn constant_unit(&mut self, values: &mut Vec<i64>, n: usize) {
+        values.resize(n, 0);
+        if let Some(check) = self.range_check.as_mut() {
+            (0..n).for_each(|i| check.observe(self.samples_written + i, 0));
+        }
+        self.samples_written += n;
+        if let Some(substitution) = &self.substitution {
+            values.iter_mut().for_each(|v| *v = substitution.apply(*v));
+        }
+    }
+
+    /// Fold a successfully decoded unit into the running [`RsiStats`].
+    fn record_rsi_stats(&mut self, kind: BlockKind, values: &[i64], start_bits: usize) {
+        let stats = &mut self.rsi_stats;
+        stats.bits += self.reader.bits_read_total() - start_bits;
+        match kind {
+            BlockKind::ZeroRun { blocks } => stats.zero_blocks += blocks,
+            BlockKind::SecondExtension => stats.second_extension_blocks += 1,
+            BlockKind::Split { .. } => stats.split_blocks += 1,
+            BlockKind::Uncompressed => stats.uncompressed_blocks += 1,
+        }
+        for &v in values {
+            stats.observe(v);
+        }
+        stats.samples += values.len();
+
+        // Zero runs are expanded lazily; every repeated sample equals the predictor (or 0).
+        let repeated = self.pending_repeat.as_ref().map_or(0, |rep| rep.remaining);
+        let repeated = repeated.min(self.output_samples - self.samples_written);
+        if repeated > 0 {
+            stats.samples += repeated;
+            let value = if self.preprocess { self.predictor_x.unwrap_or(0) } else { 0 };
+            stats.observe(value);
         }
 
+        if self.block_index_within_rsi == 0 || self.samples_written + repeated >= self.output_samples {
+            let next = RsiStats { index: stats.index + 1, ..RsiStats::default() };
+            self.completed_rsi = Some(std::mem::replace(stats, next));
+        }
+    }
+
+    fn read_split(&mut self, coded: &mut Vec<u32>, n: usize, k: u32) -> Result<(), AecError> {
+        for _ in 0..n {
+            let q = self.reader.read_unary(self.unary_limit)?;
+            coded.push(q.checked_shl(k).ok_or(AecError::InvalidInput("rice shift overflow"))?);
+        }
+        if k > 0 {
+            for v in coded.iter_mut() {
+                *v |= self.reader.read_bits_u32(k as usize)?;
+            }
+        }
         Ok(())
     }
+
+    fn read_reference(&mut self) -> Result<i64, AecError> {
+        let raw = self.reader.read_bits_u32(self.params.bits_per_sample as usize)?;
+        let x = if self.params.flags.contains(AecFlags::DATA_SIGNED) {
+            sign_extend(raw, self.params.bits_per_sample)
+        } else {
+            raw as i64
+        };
+        self.predictor_x = Some(x);
+        Ok(x)
+    }
+
+    fn reconstruct(&mut self, v: u32) -> Result<i64, AecError> {
+        if self.preprocess {
+            let x_prev = self.predictor_x.ok_or(AecError::InvalidInput("missing reference sample"))?;
+            let x = inverse_preprocess_step(x_prev, v, self.params);
+            self.predictor_x = Some(x);
+            Ok(x)
+        } else if self.params.flags.contains(AecFlags::DATA_SIGNED) {
+            Ok(sign_extend(v, self.params.bits_per_sample))
+        } else {
+            Ok(v as i64)
+        }
+    }
 }
 
 #[derive(Clone)]
 struct Snapshot {
     predictor_x: Option<i64>,
-    sample_index_within_rsi: u64,
     block_index_within_rsi: u32,
     samples_written: usize,
-    reader: StreamBitReader,
-    pending: Vec<u8>,
-    pending_pos: usize,
-    pending_repeat: Option<PendingRepeat>,
+    blocks_decoded: u64,
+    padding_bits: usize,
+    reader_bit_pos: usize,
 }
 
+
 /// Streaming-capable bit reader backed by an internal buffer.
 ///
 /// It allows appending input incrementally and compacting consumed bytes.
 #[derive(Debug, Clone)]
 struct StreamBitReader {
     buf: Vec<u8>,
-    bit_pos: usize,
+    /// Next byte of `buf` to load into `window`.
+    next: usize,
+    window: BitWindow,
+    /// Bits of byte `next` still to skip once it is loaded (after [`Self::seek`]).
+    skip: u32,
     total_bytes_dropped: usize,
 }
 
 impl StreamBitReader {
     fn new() -> Self {
-        Self { buf: Vec::new(), bit_pos: 0, total_bytes_dropped: 0 }
+        Self { buf: Vec::new(), next: 0, window: BitWindow::default(), skip: 0, total_bytes_dropped: 0 }
     }
 
     fn push(&mut self, data: &[u8]) {
         self.buf.extend_from_slice(data);
     }
 
+    /// Read position in bits from the start of `buf`.
+    fn bit_pos(&self) -> usize {
+        self.next * 8 - self.window.len() as usize + self.skip as usize
+    }
+
+    /// Move the read position to `bit_pos` bits from the start of `buf`.
+    fn seek(&mut self, bit_pos: usize) {
+        self.next = bit_pos / 8;
+        self.window = BitWindow::default();
+        self.skip = (bit_pos % 8) as u32;
+    }
+
     fn avail_bytes(&self) -> usize {
-        self.buf.len().saturating_sub(self.bit_pos / 8)
+        self.buf.len().saturating_sub(self.bit_pos() / 8)
     }
 
     fn bits_read_total(&self) -> usize {
-        self.total_bytes_dropped * 8 + self.bit_pos
+        self.total_bytes_dropped * 8 + self.bit_pos()
     }
 
-    fn align_to_byte(&mut self) {
-        let rem = self.bit_pos % 8;
-        if rem != 0 {
-            self.bit_pos += 8 - rem;
+    /// Skip to the next byte boundary; returns the number of bits skipped.
+    fn align_to_byte(&mut self) -> usize {
+        let skip = ((8 - self.bit_pos() % 8) % 8) as u32;
+        if self.skip > 0 {
+            // Nothing loaded yet: move past the partly skipped byte.
+            self.next += 1;
+            self.skip = 0;
+        } else {
+            self.window.skip(skip);
         }
+        skip as usize
     }
 
     fn read_bit(&mut self) -> Result<bool, AecError> {
@@ -574,57 +889,89 @@ impl StreamBitReader {
         if nbits > 32 {
             return Err(AecError::InvalidInput("read_bits_u32 supports up to 32 bits"));
         }
-
-        let mut out: u32 = 0;
-        for _ in 0..nbits {
-            let byte_idx = self.bit_pos / 8;
-            let bit_in_byte = self.bit_pos % 8;
-            let byte = *self
-                .buf
-                .get(byte_idx)
-                .ok_or(AecError::UnexpectedEof { bit_pos: self.bits_read_total() })?;
-            let bit = (byte >> (7 - bit_in_byte)) & 1;
-            out = (out << 1) | (bit as u32);
-            self.bit_pos += 1;
+        let n = nbits as u32;
+        if self.window.len() < n {
+            self.refill();
+            if self.window.len() < n {
+                self.seek(self.buf.len() * 8);
+                return Err(AecError::UnexpectedEof { bit_pos: self.bits_read_total() });
+            }
         }
-        Ok(out)
+        Ok(self.window.take(n))
     }
 
-    fn compact_consumed_bytes(&mut self) -> usize {
-        let bytes = self.bit_pos / 8;
-        if bytes == 0 {
-            return 0;
+    /// Read a fundamental sequence a window at a time, like [`BitReader`]'s.
+    fn read_unary(&mut self, limit: u32) -> Result<u32, AecError> {
+        let mut count: u32 = 0;
+        loop {
+            if self.window.len() == 0 {
+                self.refill();
+                if self.window.len() == 0 {
+                    self.seek(self.buf.len() * 8);
+                    return Err(AecError::UnexpectedEof { bit_pos: self.bits_read_total() });
+                }
+            }
+            let zeros = self.window.leading_zeros();
+            count = count.saturating_add(zeros);
+            if count > limit {
+                return Err(AecError::InvalidInput("unary run too long"));
+            }
+            if zeros < self.window.len() {
+                self.window.skip(zeros + 1);
+                return Ok(count);
+            }
+            self.window.skip(zeros);
         }
-        self.buf.drain(0..bytes);
-        self.bit_pos -= bytes * 8;
-        self.total_bytes_dropped += bytes;
-        bytes
     }
-}
 
-fn read_unary_stream(r: &mut StreamBitReader) -> Result<u32, AecError> {
-    let mut count: u32 = 0;
-    loop {
-        let bit = r.read_bit()?;
-        if bit {
-            return Ok(count);
+    fn refill(&mut self) {
+        while self.window.has_room() {
+            match self.buf.get(self.next) {
+                Some(&byte) => self.window.push_byte(byte),
+                None => break,
+            }
+            self.next += 1;
         }
-        count = count.saturating_add(1);
-        if count > 1_000_000 {
-            return Err(AecError::InvalidInput("unary run too long"));
+        if self.skip > 0 && self.window.len() >= self.skip {
+            self.window.skip(self.skip);
+            self.skip = 0;
         }
     }
+
+    /// Drop consumed bytes once they make up at least half of the buffer, which keeps the
+    /// amortized cost linear even when the whole payload is pushed up front.
+    fn compact(&mut self) {
+        let bytes = self.bit_pos() / 8;
+        if bytes == 0 || bytes * 2 < self.buf.len() {
+            return;
+        }
+        self.buf.drain(0..bytes);
+        self.next -= bytes;
+        self.total_bytes_dropped += bytes;
+    }
 }
 
-struct OutBuf<'a> {
+/// One-shot output buffer; `BPS` fixes the sample width at compile time (0 = from `format`).
+struct OutBuf<'a, const BPS: usize> {
     buf: &'a mut [u8],
     pos: usize,
-    bytes_per_sample: usize,
+    format: SampleFormat,
+    range: Option<RangeCheck>,
+    substitution: Option<Substitution>,
 }
 
-impl<'a> OutBuf<'a> {
-    fn new(buf: &'a mut [u8], bytes_per_sample: usize) -> Self {
-        Self { buf, pos: 0, bytes_per_sample }
+impl<'a, const BPS: usize> OutBuf<'a, BPS> {
+    fn new(
+        buf: &'a mut [u8],
+        format: SampleFormat,
+        range: Option<RangeCheck>,
+        substitution: Option<Substitution>,
+    ) -> Self {
+        Self { buf, pos: 0, format, range, substitution }
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        if BPS != 0 { BPS } else { self.format.bytes_per_sample }
     }
 
     fn len(&self) -> usize {
@@ -635,37 +982,405 @@ impl<'a> OutBuf<'a> {
         self.buf.len()
     }
 
+    fn written_since(&self, pos: usize) -> &[u8] {
+        &self.buf[pos..self.pos]
+    }
+
     fn samples_written(&self) -> usize {
-        self.pos / self.bytes_per_sample
+        self.pos / self.bytes_per_sample()
     }
 }
 
-pub fn decode(input: &[u8], params: AecParams, output_samples: usize) -> Result<Vec<u8>, AecError> {
-    validate_params(params)?;
+pub fn decode(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, AecError> {
+    let mut out = vec![0u8; output_len(params, output_samples, options)?];
+    decode_into(input, params, output_samples, &mut out, options)?;
+    Ok(out)
+}
 
-    let bytes_per_sample = bytes_per_sample(params)?;
-    let output_bytes = output_samples
-        .checked_mul(bytes_per_sample)
-        .ok_or(AecError::InvalidInput("output too large"))?;
+pub fn decode_no_panic(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, AecError> {
+    let output_bytes = output_len(params, output_samples, options)?;
+
+    // `vec![0; n]` aborts or panics when the allocation fails; report it instead.
+    let mut out = Vec::new();
+    out.try_reserve_exact(output_bytes)
+        .map_err(|_| options.tag(AecError::InvalidInput("cannot allocate output buffer")))?;
+    out.resize(output_bytes, 0);
+    decode_into(input, params, output_samples, &mut out, options)?;
+    Ok(out)
+}
+
+pub fn decode_append(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    out: &mut Vec<u8>,
+    options: &DecodeOptions,
+) -> Result<(), AecError> {
+    let output_bytes = output_len(params, output_samples, options)?;
+    let start = out.len();
+    out.reserve_exact(output_bytes);
+    out.resize(start + output_bytes, 0);
+    let result = decode_into(input, params, output_samples, &mut out[start..], options);
+    if result.is_err() {
+        out.truncate(start);
+    }
+    result
+}
+
+pub fn decode_decimated(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    step: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, AecError> {
+    if step == 0 {
+        return Err(options.tag(AecError::InvalidInput("decimation step must be at least 1")));
+    }
+    let kept = output_samples.div_ceil(step);
+    let mut out = Vec::with_capacity(output_len(params, kept, options)?);
 
-    let mut out = vec![0u8; output_bytes];
-    decode_into(input, params, output_samples, &mut out)?;
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    dec.push_input(input);
+    let mut sink = StrideSink::new(VecSink::new(&mut out, dec.format), step);
+    dec.decode_to(&mut sink, Flush::Flush, 0)?;
+    Ok(out)
+}
+
+/// Samples [`SampleIter`] decodes per batch.
+const SAMPLE_BATCH: usize = 1024;
+
+/// Lazily decoded samples of a [`Decoder`], from [`Decoder::samples`].
+pub struct SampleIter<'a> {
+    dec: &'a mut Decoder,
+    batch: Vec<i64>,
+    pos: usize,
+    /// Block count when iteration started; the whole iteration is one call for
+    /// [`DecodeOptions::max_blocks_per_call`].
+    blocks_at_call_start: u64,
+    /// Error to report once the samples decoded before it are handed out.
+    error: Option<AecError>,
+    done: bool,
+}
+
+impl Iterator for SampleIter<'_> {
+    type Item = Result<u32, AecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.batch.len() {
+            if let Some(e) = self.error.take() {
+                return Some(Err(e));
+            }
+            if self.done {
+                return None;
+            }
+            self.batch.clear();
+            self.pos = 0;
+            let mut sink = BatchSink { out: &mut self.batch, room: SAMPLE_BATCH };
+            match self.dec.decode_to(&mut sink, Flush::Flush, self.blocks_at_call_start) {
+                Ok(DecodeStatus::NeedOutput) => {}
+                Ok(_) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    self.error = Some(e);
+                }
+            }
+            if self.batch.is_empty() {
+                return self.error.take().map(Err);
+            }
+        }
+        let value = self.batch[self.pos];
+        self.pos += 1;
+        Some(Ok(value as u32))
+    }
+}
+
+/// Decode into `T` values of at most `max_bits` bits; `convert` keeps the low bits of each
+/// (sign-extended) sample.
+pub fn decode_typed<T>(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    max_bits: u8,
+    convert: impl Fn(i64) -> T,
+    options: &DecodeOptions,
+) -> Result<Vec<T>, AecError> {
+    if params.bits_per_sample > max_bits {
+        return Err(options.tag(AecError::InvalidInput("bits_per_sample too wide for the sample type")));
+    }
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    let mut out = Vec::with_capacity(output_samples);
+    dec.push_input(input);
+    dec.decode_values(|x| out.push(convert(x)))?;
+    Ok(out)
+}
+
+pub fn decode_field(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<DecodedField, AecError> {
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    let stats = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&stats);
+    dec.set_rsi_callback(move |s| sink.lock().unwrap().push(s.clone()));
+    let mut samples = Vec::with_capacity(output_samples);
+    dec.push_input(input);
+    dec.decode_values(|x| samples.push(x as u32))?;
+    let stats = std::mem::take(&mut *stats.lock().unwrap());
+    Ok(DecodedField {
+        samples,
+        bits_per_sample: params.bits_per_sample,
+        signed: params.flags.contains(AecFlags::DATA_SIGNED),
+        bytes_consumed: dec.report().bits_consumed.div_ceil(8),
+        stats,
+    })
+}
+
+/// Decode `output_samples` samples, handing them to `sink` one block at a time; the last batch
+/// may be shorter. Batches decoded before an error are still delivered.
+pub fn decode_with_sink(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    mut sink: impl FnMut(&[u32]),
+    options: &DecodeOptions,
+) -> Result<(), AecError> {
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    let block_size = params.block_size as usize;
+    let mut batch = Vec::with_capacity(block_size);
+    dec.push_input(input);
+    let result = dec.decode_values(|x| {
+        batch.push(x as u32);
+        if batch.len() == block_size {
+            sink(&batch);
+            batch.clear();
+        }
+    });
+    if !batch.is_empty() {
+        sink(&batch);
+    }
+    result
+}
+
+/// Like [`decode_typed`], into the caller's `output`, one sample per element.
+pub fn decode_into_typed<T>(
+    input: &[u8],
+    params: AecParams,
+    output: &mut [T],
+    max_bits: u8,
+    convert: impl Fn(i64) -> T,
+    options: &DecodeOptions,
+) -> Result<(), AecError> {
+    if params.bits_per_sample > max_bits {
+        return Err(options.tag(AecError::InvalidInput("bits_per_sample too wide for the sample type")));
+    }
+    let mut dec = Decoder::with_options(params, output.len(), options)?;
+    dec.push_input(input);
+    dec.decode_to(&mut TypedSliceSink::new(output, convert), Flush::Flush, 0)?;
+    Ok(())
+}
+
+/// Decode into `T` samples, which must hold every value `params` allows.
+pub fn decode_samples<T: Sample>(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<T>, AecError> {
+    check_sample_type::<T>(params).map_err(|e| options.tag(e))?;
+    decode_typed(input, params, output_samples, T::BITS, T::from_i64, options)
+}
+
+pub fn decode_bit_packed(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, AecError> {
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    let packed_bytes = output_samples
+        .checked_mul(params.bits_per_sample as usize)
+        .ok_or_else(|| options.tag(AecError::InvalidInput("output too large")))?
+        .div_ceil(8);
+    let mut out = Vec::with_capacity(packed_bytes);
+    dec.push_input(input);
+    let mut sink = BitPackSink::new(&mut out, params);
+    dec.decode_to(&mut sink, Flush::Flush, 0)?;
+    sink.finish();
+    Ok(out)
+}
+
+pub fn decode_requantized(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    target_bits: u8,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, AecError> {
+    if !(1..=params.bits_per_sample).contains(&target_bits) {
+        return Err(options.tag(AecError::InvalidInput("target_bits must be 1..=bits_per_sample")));
+    }
+    let target = AecParams { bits_per_sample: target_bits, ..params };
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    let format = SampleFormat::new(target, bytes_per_sample(target).map_err(|e| options.tag(e))?);
+    let mut out = Vec::with_capacity(output_len(target, output_samples, options)?);
+    dec.push_input(input);
+    let mut sink = RequantizeSink::new(VecSink::new(&mut out, format), params, target_bits);
+    dec.decode_to(&mut sink, Flush::Flush, 0)?;
     Ok(out)
 }
 
+pub fn decode_into_column_major(
+    input: &[u8],
+    params: AecParams,
+    nx: usize,
+    ny: usize,
+    out: &mut [u8],
+    options: &DecodeOptions,
+) -> Result<(), AecError> {
+    let output_samples = nx.checked_mul(ny).ok_or_else(|| options.tag(AecError::InvalidInput("output too large")))?;
+    if out.len() != output_len(params, output_samples, options)? {
+        return Err(options.tag(AecError::InvalidInput("output buffer has wrong length")));
+    }
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    dec.push_input(input);
+    let mut sink = TransposeSink::new(out, dec.format, nx, ny);
+    dec.decode_to(&mut sink, Flush::Flush, 0)?;
+    Ok(())
+}
+
+pub fn decode_with_report(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<(Vec<u8>, DecodeReport), AecError> {
+    let mut out = vec![0u8; output_len(params, output_samples, options)?];
+    let report = decode_into_with_report(input, params, output_samples, &mut out, options)?;
+    Ok((out, report))
+}
+
 pub fn decode_into(
     input: &[u8],
     params: AecParams,
     output_samples: usize,
     output: &mut [u8],
+    options: &DecodeOptions,
 ) -> Result<(), AecError> {
-    validate_params(params)?;
+    decode_into_with_report(input, params, output_samples, output, options)?;
+    Ok(())
+}
+
+pub fn decode_into_with_report(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    output: &mut [u8],
+    options: &DecodeOptions,
+) -> Result<DecodeReport, AecError> {
+    let ctx = DecodeContext::new(params, options)?;
+    decode_into_ctx(&ctx, input, output_samples, output, &mut Vec::new())
+}
+
+/// Output buffer length for a one-shot decode; errors are tagged with the options' context.
+pub(crate) fn output_len(params: AecParams, output_samples: usize, options: &DecodeOptions) -> Result<usize, AecError> {
+    validate_params(params, options)
+        .and_then(|()| SampleFormat::for_output(params, options))
+        .and_then(|f| output_samples.checked_mul(f.bytes_per_sample).ok_or(AecError::InvalidInput("output too large")))
+        .map_err(|e| options.tag(e))
+}
+
+/// Parameter-derived constants of the one-shot decoder, computed once per parameter set.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodeContext {
+    pub(crate) params: AecParams,
+    pub(crate) bytes_per_sample: usize,
+    format: SampleFormat,
+    pub(crate) id_len: usize,
+    unary_limit: u32,
+    check_input_length: bool,
+    max_blocks: Option<u64>,
+    trace_sample: Option<usize>,
+    context: Option<Arc<str>>,
+    crc32c: bool,
+    expected_range: Option<RangeInclusive<i64>>,
+    substitution: Option<Substitution>,
+    state_hash: bool,
+}
 
-    let trace_sample: Option<usize> = std::env::var("RUST_AEC_TRACE_SAMPLE")
-        .ok()
-        .and_then(|v| v.parse::<usize>().ok());
+impl DecodeContext {
+    pub(crate) fn new(params: AecParams, options: &DecodeOptions) -> Result<Self, AecError> {
+        validate_params(params, options).map_err(|e| options.tag(e))?;
+
+        let trace_sample: Option<usize> = std::env::var("RUST_AEC_TRACE_SAMPLE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok());
+
+        let format = SampleFormat::for_output(params, options)?;
+        Ok(Self {
+            params,
+            bytes_per_sample: format.bytes_per_sample,
+            format,
+            id_len: id_len(params)?,
+            unary_limit: unary_limit(params, options),
+            check_input_length: options.check_input_length,
+            // A one-shot call is both "overall" and "per call".
+            max_blocks: match (options.max_blocks, options.max_blocks_per_call) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            trace_sample,
+            context: options.context.clone(),
+            crc32c: options.crc32c,
+            expected_range: options.expected_range.clone(),
+            substitution: Substitution::new(options),
+            state_hash: options.state_hash,
+        })
+    }
+}
+
+/// One-shot decode with precomputed constants; `scratch` is reused for Rice split blocks.
+pub(crate) fn decode_into_ctx(
+    ctx: &DecodeContext,
+    input: &[u8],
+    output_samples: usize,
+    output: &mut [u8],
+    scratch: &mut Vec<u32>,
+) -> Result<DecodeReport, AecError> {
+    match decode_into_ctx_fixed::<0, 0>(ctx, input, output_samples, output, scratch) {
+        Ok(report) => Ok(DecodeReport { context: ctx.context.clone(), ..report }),
+        Err(e) => Err(e.tagged(ctx.context.as_ref())),
+    }
+}
+
+/// [`decode_into_ctx`] with block size `BS` and output sample width `BPS` fixed at compile time
+/// (0 = taken from `ctx`). The caller guarantees non-zero constants match `ctx`.
+pub(crate) fn decode_into_ctx_fixed<const BS: u32, const BPS: usize>(
+    ctx: &DecodeContext,
+    input: &[u8],
+    output_samples: usize,
+    output: &mut [u8],
+    scratch: &mut Vec<u32>,
+) -> Result<DecodeReport, AecError> {
+    let params = ctx.params;
+    let trace_sample = ctx.trace_sample;
+    let bytes_per_sample = if BPS != 0 { BPS } else { ctx.bytes_per_sample };
+    let block_size: u32 = if BS != 0 { BS } else { params.block_size };
+    let id_len = ctx.id_len;
+    let unary_limit = ctx.unary_limit;
 
-    let bytes_per_sample = bytes_per_sample(params)?;
     let output_bytes = output_samples
         .checked_mul(bytes_per_sample)
         .ok_or(AecError::InvalidInput("output too large"))?;
@@ -674,10 +1389,15 @@ pub fn decode_into(
         return Err(AecError::InvalidInput("output buffer has wrong length"));
     }
 
-    let mut out = OutBuf::new(output, bytes_per_sample);
-    let mut r = BitReader::new(input);
+    if ctx.check_input_length && (input.len() as u64).saturating_mul(8) < min_payload_bits(params, output_samples) {
+        return Err(AecError::InvalidInput(
+            "payload is shorter than any valid encoding of output_samples (swapped arguments?)",
+        ));
+    }
 
-    let id_len = id_len(params)?;
+    let range = RangeCheck::new(ctx.expected_range.as_ref());
+    let mut out = OutBuf::<BPS>::new(output, ctx.format, range, ctx.substitution.clone());
+    let mut r = BitReader::new(input);
 
     let preprocess = params.flags.contains(AecFlags::DATA_PREPROCESS);
 
@@ -687,7 +1407,29 @@ pub fn decode_into(
     // Predictor state (only used with preprocessing enabled).
     let mut predictor_x: Option<i64> = None;
 
+    let mut blocks_decoded: u64 = 0;
+    let mut padding_bits: usize = 0;
+
+    // Output is hashed block by block, while it is still in cache.
+    let mut crc = ctx.crc32c.then(Crc32c::new);
+    let mut hashed = 0usize;
+    let mut state_hash = ctx.state_hash.then(StateHash::new);
+    let mut rsis_completed = 0u64;
+
+    // A constant field: every sample is 0 and the payload, usually empty, is not read.
+    if params.bits_per_sample == 0 {
+        while out.len() < output_bytes {
+            write_sample(&mut out, 0)?;
+        }
+    }
+
     while out.len() < output_bytes {
+        if let Some(crc) = crc.as_mut() {
+            crc.update(out.written_since(hashed));
+            hashed = out.len();
+        }
+        check_block_budget(blocks_decoded, ctx.max_blocks, "blocks")?;
+
         // Start of RSI interval.
         if preprocess && block_index_within_rsi == 0 {
             predictor_x = None;
@@ -718,7 +1460,7 @@ pub fn decode_into(
         let mut remaining_in_block: usize;
 
         // Helper: consume the RSI reference sample (when preprocessing is enabled).
-        let mut consume_reference = |r: &mut BitReader, out: &mut OutBuf<'_>| -> Result<(), AecError> {
+        let mut consume_reference = |r: &mut BitReader, out: &mut OutBuf<'_, BPS>| -> Result<(), AecError> {
             let ref_raw = match r.read_bits_u32(params.bits_per_sample as usize) {
                 Ok(v) => v,
                 Err(AecError::UnexpectedEof { bit_pos }) => {
@@ -735,7 +1477,7 @@ pub fn decode_into(
                 ref_raw as i64
             };
 
-            write_sample(out, ref_val, params)?;
+            write_sample(out, ref_val)?;
             predictor_x = Some(ref_val);
             reference_sample_consumed = true;
             sample_index_within_rsi += 1;
@@ -756,7 +1498,7 @@ pub fn decode_into(
             };
 
             if let Some(ts) = trace_sample {
-                let block_end = block_start_sample + params.block_size as usize;
+                let block_end = block_start_sample + block_size as usize;
                 if (block_start_sample..block_end).contains(&ts) {
                     eprintln!(
                         "TRACE sample={ts} rsi_block={block_index_within_rsi} bits={} id=0 mode=LE selector={} block_samples=[{}, {})",
@@ -776,14 +1518,14 @@ pub fn decode_into(
                 }
             }
 
-            remaining_in_block = params.block_size as usize;
+            remaining_in_block = block_size as usize;
             if reference_sample_consumed {
                 remaining_in_block = remaining_in_block.saturating_sub(1);
             }
 
             if !selector {
                 // Zero-block run.
-                let fs = match read_unary(&mut r) {
+                let fs = match read_unary(&mut r, unary_limit) {
                     Ok(v) => v,
                     Err(AecError::UnexpectedEof { bit_pos }) => {
                         return Err(AecError::UnexpectedEofDuringDecode {
@@ -799,16 +1541,13 @@ pub fn decode_into(
 
                 if z_blocks == ROS {
                     // Fill-to-boundary; bounded by RSI.
-                    let b = block_index_within_rsi;
-                    let fill1 = params.rsi.saturating_sub(b);
-                    let fill2 = 64u32.saturating_sub(b % 64);
-                    z_blocks = fill1.min(fill2);
+                    z_blocks = blocks_to_segment_end(params, block_index_within_rsi);
                 } else if z_blocks > ROS {
                     z_blocks = z_blocks.saturating_sub(1);
                 }
 
                 let mut zeros_samples = z_blocks
-                    .checked_mul(params.block_size)
+                    .checked_mul(block_size)
                     .ok_or(AecError::InvalidInput("zero-run overflow"))? as usize;
 
                 // If we already emitted the reference sample for the first block, the zero-run
@@ -818,9 +1557,7 @@ pub fn decode_into(
                 }
 
                 if let Some(ts) = trace_sample {
-                    let total_samples = (z_blocks as usize)
-                        .checked_mul(params.block_size as usize)
-                        .unwrap_or(usize::MAX);
+                    let total_samples = (z_blocks as usize).saturating_mul(block_size as usize);
                     let run_end = block_start_sample.saturating_add(total_samples);
                     if (block_start_sample..run_end).contains(&ts) {
                         eprintln!(
@@ -847,13 +1584,16 @@ pub fn decode_into(
 
                 // Advance block counter by z_blocks.
                 // We have already consumed the current block header as part of the run.
+                blocks_decoded += z_blocks as u64;
                 block_index_within_rsi = block_index_within_rsi.saturating_add(z_blocks);
                 if block_index_within_rsi >= params.rsi {
                     block_index_within_rsi %= params.rsi;
                     if params.flags.contains(AecFlags::PAD_RSI) {
-                        r.align_to_byte();
+                        padding_bits += align_to_byte(&mut r);
                     }
                     sample_index_within_rsi = 0;
+                    let emitted = out.samples_written();
+                    hash_rsi_end(state_hash.as_mut(), &mut rsis_completed, params, emitted, r.bits_read(), predictor_x);
                 }
 
                 continue;
@@ -870,11 +1610,12 @@ pub fn decode_into(
                 reference_sample_consumed,
                 &mut sample_index_within_rsi,
                 output_bytes,
+                unary_limit,
             )?;
         } else if id == max_id {
             // Uncompressed block.
             if let Some(ts) = trace_sample {
-                let block_end = block_start_sample + params.block_size as usize;
+                let block_end = block_start_sample + block_size as usize;
                 if (block_start_sample..block_end).contains(&ts) {
                     eprintln!(
                         "TRACE sample={ts} rsi_block={block_index_within_rsi} bits={} id={} mode=UNCOMP block_samples=[{}, {})",
@@ -891,9 +1632,9 @@ pub fn decode_into(
                 if out.len() >= output_bytes {
                     break;
                 }
-                remaining_in_block = params.block_size as usize - 1;
+                remaining_in_block = block_size as usize - 1;
             } else {
-                remaining_in_block = params.block_size as usize;
+                remaining_in_block = block_size as usize;
             }
 
             for _ in 0..remaining_in_block {
@@ -926,7 +1667,7 @@ pub fn decode_into(
             let k = (id - 1) as usize;
 
             if let Some(ts) = trace_sample {
-                let block_end = block_start_sample + params.block_size as usize;
+                let block_end = block_start_sample + block_size as usize;
                 if (block_start_sample..block_end).contains(&ts) {
                     eprintln!(
                         "TRACE sample={ts} rsi_block={block_index_within_rsi} bits={} id={} mode=SPLIT k={} block_samples=[{}, {})",
@@ -946,13 +1687,15 @@ pub fn decode_into(
                 }
             }
 
-            remaining_in_block = params.block_size as usize;
+            remaining_in_block = block_size as usize;
             if reference_sample_consumed {
                 remaining_in_block = remaining_in_block.saturating_sub(1);
             }
 
             let n = remaining_in_block;
-            let mut tmp: Vec<u32> = vec![0u32; n];
+            // Grown while reading (every value costs at least one input bit), so a corrupt
+            // nonstandard block size cannot trigger a huge up-front allocation.
+            scratch.clear();
 
             // If tracing is enabled and the trace sample falls within the coded portion of this
             // block, record the quotient/remainder at that offset.
@@ -968,7 +1711,7 @@ pub fn decode_into(
             let mut trace_rem: Option<u32> = None;
 
             for i in 0..n {
-                let q = match read_unary(&mut r) {
+                let q = match read_unary(&mut r, unary_limit) {
                     Ok(v) => v,
                     Err(AecError::UnexpectedEof { bit_pos }) => {
                         return Err(AecError::UnexpectedEofDuringDecode {
@@ -981,13 +1724,12 @@ pub fn decode_into(
                 if trace_offset_in_block == Some(i) {
                     trace_q = Some(q);
                 }
-                tmp[i] = (q as u32)
-                    .checked_shl(k as u32)
-                    .ok_or(AecError::InvalidInput("rice shift overflow"))?;
+                scratch.push(q.checked_shl(k as u32).ok_or(AecError::InvalidInput("rice shift overflow"))?);
             }
+            let tmp = &mut scratch[..];
 
             if k > 0 {
-                for i in 0..n {
+                for (i, slot) in tmp.iter_mut().enumerate() {
                     let rem_bitpos_before = if trace_offset_in_block
                         .map(|off| i + 2 >= off && i <= off + 2)
                         .unwrap_or(false)
@@ -1025,7 +1767,7 @@ pub fn decode_into(
                     if trace_offset_in_block == Some(i) {
                         trace_rem = Some(rem);
                     }
-                    tmp[i] |= rem;
+                    *slot |= rem;
                 }
             }
 
@@ -1051,7 +1793,7 @@ pub fn decode_into(
                 );
             }
 
-            for v in tmp {
+            for &v in tmp.iter() {
                 emit_coded_value(
                     &mut out,
                     &mut predictor_x,
@@ -1068,22 +1810,61 @@ pub fn decode_into(
         }
 
         // Next block.
+        blocks_decoded += 1;
         block_index_within_rsi = block_index_within_rsi.saturating_add(1);
-        if preprocess && block_index_within_rsi >= params.rsi {
+        if block_index_within_rsi >= params.rsi {
             block_index_within_rsi = 0;
             sample_index_within_rsi = 0;
             if params.flags.contains(AecFlags::PAD_RSI) {
-                r.align_to_byte();
+                padding_bits += align_to_byte(&mut r);
             }
+            let emitted = out.samples_written();
+            hash_rsi_end(state_hash.as_mut(), &mut rsis_completed, params, emitted, r.bits_read(), predictor_x);
         }
     }
 
-    Ok(())
+    if let Some(crc) = crc.as_mut() {
+        crc.update(out.written_since(hashed));
+    }
+    let report = DecodeReport {
+        crc32c: crc.map(|c| c.finish()),
+        state_hash: state_hash.map(|h| h.finish()),
+        ..DecodeReport::new(r.bits_read(), padding_bits, input.len())
+    };
+    Ok(RangeCheck::report(out.range.as_ref(), report))
 }
 
-fn validate_params(params: AecParams) -> Result<(), AecError> {
-    if !(1..=32).contains(&params.bits_per_sample) {
-        return Err(AecError::InvalidInput("bits_per_sample must be 1..=32"));
+/// Fold the end of RSI number `*rsi` into `hash` and advance the count. Only RSIs emitted in
+/// full are hashed: decoding may stop inside the last one, where one-shot and streaming state
+/// legitimately differ.
+fn hash_rsi_end(
+    hash: Option<&mut StateHash>,
+    rsi: &mut u64,
+    params: AecParams,
+    samples_emitted: usize,
+    bit_pos: usize,
+    predictor: Option<i64>,
+) {
+    let Some(hash) = hash else { return };
+    let n = *rsi;
+    *rsi += 1;
+    if samples_emitted == geometry::first_sample_of_rsi(params, n as usize + 1) {
+        hash.rsi_boundary(n, bit_pos, predictor);
+    }
+}
+
+/// Align `r` to the next byte boundary; returns the number of bits skipped.
+fn align_to_byte(r: &mut BitReader<'_>) -> usize {
+    let before = r.bits_read();
+    r.align_to_byte();
+    r.bits_read() - before
+}
+
+pub(crate) fn validate_params(params: AecParams, options: &DecodeOptions) -> Result<(), AecError> {
+    // 0 is a constant field (GRIB2 packs one when every value equals the reference value): it
+    // codes no blocks and decodes to zeros.
+    if params.bits_per_sample > 32 {
+        return Err(AecError::InvalidInput("bits_per_sample must be 0..=32"));
     }
     if params.block_size == 0 {
         return Err(AecError::InvalidInput("block_size must be > 0"));
@@ -1093,18 +1874,18 @@ fn validate_params(params: AecParams) -> Result<(), AecError> {
     }
 
     // Common AEC block sizes; keep permissive but avoid pathological values.
-    if ![8u32, 16, 32, 64].contains(&params.block_size) {
+    if !options.allow_nonstandard_block_size && ![8u32, 16, 32, 64].contains(&params.block_size) {
         return Err(AecError::Unsupported("block_size must be one of 8,16,32,64"));
     }
 
     Ok(())
 }
 
-fn bytes_per_sample(params: AecParams) -> Result<usize, AecError> {
+pub(crate) fn bytes_per_sample(params: AecParams) -> Result<usize, AecError> {
     let bps = params.bits_per_sample;
 
     let b = match bps {
-        1..=8 => 1,
+        0..=8 => 1,
         9..=16 => 2,
         17..=24 => {
             if params.flags.contains(AecFlags::DATA_3BYTE) {
@@ -1120,7 +1901,7 @@ fn bytes_per_sample(params: AecParams) -> Result<usize, AecError> {
     Ok(b)
 }
 
-fn id_len(params: AecParams) -> Result<usize, AecError> {
+pub(crate) fn id_len(params: AecParams) -> Result<usize, AecError> {
     let bps = params.bits_per_sample;
 
     let mut id_len = if bps > 16 { 5 } else if bps > 8 { 4 } else { 3 };
@@ -1132,25 +1913,51 @@ fn id_len(params: AecParams) -> Result<usize, AecError> {
     Ok(id_len)
 }
 
-fn read_unary(r: &mut BitReader<'_>) -> Result<u32, AecError> {
-    let mut count: u32 = 0;
-    loop {
-        let bit = r.read_bit()?;
-        if bit {
-            return Ok(count);
-        }
-        count = count.saturating_add(1);
-        // Safety guard against pathological/corrupt inputs.
-        // Valid streams can have unary lengths larger than 90 (Second Extension is the main
-        // mode that constrains it to <= 90), so we only cap at a very large value.
-        if count > 1_000_000 {
-            return Err(AecError::InvalidInput("unary run too long"));
-        }
+fn check_block_budget(blocks: u64, limit: Option<u64>, what: &'static str) -> Result<(), AecError> {
+    match limit {
+        Some(limit) if blocks >= limit => Err(AecError::LimitExceeded { what, limit }),
+        _ => Ok(()),
     }
 }
 
-fn emit_coded_value(
-    out: &mut OutBuf<'_>,
+/// Lower bound on the encoded size of `output_samples` samples, in bits.
+///
+/// Best case per RSI: every 64-block segment is one zero-block run (option id, selector and at
+/// least one unary bit), plus the reference sample when preprocessing is enabled.
+pub(crate) fn min_payload_bits(params: AecParams, output_samples: usize) -> u64 {
+    if params.bits_per_sample == 0 {
+        return 0;
+    }
+    let block_size = params.block_size.max(1) as u64;
+    let rsi = params.rsi.max(1) as u64;
+    let id_len = id_len(params).unwrap_or(1) as u64;
+    let ref_bits = if params.flags.contains(AecFlags::DATA_PREPROCESS) {
+        params.bits_per_sample as u64
+    } else {
+        0
+    };
+    let per_rsi = |blocks: u64| blocks.div_ceil(64) * (id_len + 2) + ref_bits;
+
+    let total_blocks = (output_samples as u64).div_ceil(block_size);
+    let full = total_blocks / rsi;
+    let rem = total_blocks % rsi;
+    full.saturating_mul(per_rsi(rsi)).saturating_add(if rem > 0 { per_rsi(rem) } else { 0 })
+}
+
+/// Longest unary run a valid stream can contain for `params`.
+///
+/// Split-option fundamental sequences encode `d >> k` with `d < 2^bits_per_sample`, zero-block
+/// runs stay within a 64-block segment, and Second Extension symbols are at most 90. Anything
+/// longer can only come from a corrupt stream.
+pub(crate) fn unary_limit(params: AecParams, options: &DecodeOptions) -> u32 {
+    options.max_unary_run.unwrap_or_else(|| {
+        let max_coded = (1u64 << params.bits_per_sample) - 1;
+        max_coded.max(90).min(u32::MAX as u64) as u32
+    })
+}
+
+fn emit_coded_value<const BPS: usize>(
+    out: &mut OutBuf<'_, BPS>,
     predictor_x: &mut Option<i64>,
     params: AecParams,
     _bytes_per_sample: usize,
@@ -1165,20 +1972,21 @@ fn emit_coded_value(
     if params.flags.contains(AecFlags::DATA_PREPROCESS) {
         let x_prev = predictor_x.ok_or(AecError::InvalidInput("missing reference sample"))?;
         let x_next = inverse_preprocess_step(x_prev, v, params);
-        write_sample(out, x_next, params)?;
+        write_sample(out, x_next)?;
         *predictor_x = Some(x_next);
         *sample_index_within_rsi += 1;
         return Ok(());
     }
 
     // No preprocessing: v is the sample value (raw n-bit field).
-    write_sample(out, v as i64, params)?;
+    write_sample(out, v as i64)?;
     *sample_index_within_rsi += 1;
     Ok(())
 }
 
-fn emit_repeated_value(
-    out: &mut OutBuf<'_>,
+#[allow(clippy::too_many_arguments)]
+fn emit_repeated_value<const BPS: usize>(
+    out: &mut OutBuf<'_, BPS>,
     predictor_x: &mut Option<i64>,
     params: AecParams,
     bytes_per_sample: usize,
@@ -1204,9 +2012,10 @@ fn emit_repeated_value(
     Ok(())
 }
 
-fn emit_second_extension(
+#[allow(clippy::too_many_arguments)]
+fn emit_second_extension<const BPS: usize>(
     r: &mut BitReader<'_>,
-    out: &mut OutBuf<'_>,
+    out: &mut OutBuf<'_, BPS>,
     predictor_x: &mut Option<i64>,
     params: AecParams,
     bytes_per_sample: usize,
@@ -1214,6 +2023,7 @@ fn emit_second_extension(
     reference_sample_consumed: bool,
     sample_index_within_rsi: &mut u64,
     output_bytes: usize,
+    unary_limit: u32,
 ) -> Result<(), AecError> {
     // Second Extension yields pairs (a,b) aligned to even sample indices.
     // If we started at an odd sample index because sample 0 was the reference,
@@ -1221,7 +2031,7 @@ fn emit_second_extension(
     let mut need_odd_first = reference_sample_consumed;
 
     while remaining_in_block > 0 && out.len() < output_bytes {
-        let m = read_unary(r)?;
+        let m = read_unary(r, unary_limit)?;
         if m > 90 {
             return Err(AecError::InvalidInput("Second Extension unary symbol too large"));
         }
@@ -1275,7 +2085,7 @@ fn emit_second_extension(
     Ok(())
 }
 
-fn second_extension_pair(m: u32) -> (u32, u32) {
+pub(crate) fn second_extension_pair(m: u32) -> (u32, u32) {
     // Enumerate sums s = 0..=12, then k = 0..=s, mapping m -> (s-k, k).
     let mut idx: u32 = 0;
     for s in 0u32..=12 {
@@ -1291,85 +2101,17 @@ fn second_extension_pair(m: u32) -> (u32, u32) {
     (0, 0)
 }
 
-fn inverse_preprocess_step(x_prev: i64, d: u32, params: AecParams) -> i64 {
-    let n = params.bits_per_sample;
-
-    // Match libaec inverse preprocessing exactly (see vendor/libaec.../src/decode.c).
-    // The coded value `d` is mapped to a signed delta using the LSB as sign, but the
-    // application of that delta is bounded; if it would cross the selected boundary,
-    // a reflection mapping is used instead.
-    let delta: i64 = ((d >> 1) as i64) ^ (!(((d & 1) as i64) - 1));
-    let half_d: i64 = ((d >> 1) + (d & 1)) as i64;
-
-    if params.flags.contains(AecFlags::DATA_SIGNED) {
-        // signed_max matches libaec state->xmax for signed data.
-        let signed_max: i64 = (1i64 << (n - 1)) - 1;
-        let data = x_prev;
-
-        if data < 0 {
-            if half_d <= signed_max + data + 1 {
-                data + delta
-            } else {
-                (d as i64) - signed_max - 1
-            }
-        } else {
-            if half_d <= signed_max - data {
-                data + delta
-            } else {
-                signed_max - (d as i64)
-            }
-        }
-    } else {
-        let unsigned_max: u64 = (1u64 << n) - 1;
-        let data_u: u64 = x_prev as u64;
-
-        // med is a single bit (the MSB) for unsigned samples.
-        let med: u64 = unsigned_max / 2 + 1;
-        let mask: u64 = if (data_u & med) != 0 { unsigned_max } else { 0 };
-
-        if (half_d as u64) <= (mask ^ data_u) {
-            (x_prev + delta) as i64
-        } else {
-            (mask ^ (d as u64)) as i64
-        }
-    }
-}
-
-fn write_sample(out: &mut OutBuf<'_>, value: i64, params: AecParams) -> Result<(), AecError> {
-    let n = params.bits_per_sample as u32;
-    let mask: u64 = if n == 32 { u64::MAX } else { (1u64 << n) - 1 };
-
-    let raw_u = if params.flags.contains(AecFlags::DATA_SIGNED) {
-        (value as i64 as u64) & mask
-    } else {
-        (value.max(0) as u64) & mask
-    };
-
-    let bytes_per_sample = out.bytes_per_sample;
-    if out.pos.checked_add(bytes_per_sample).ok_or(AecError::InvalidInput("output too large"))? > out.capacity() {
+fn write_sample<const BPS: usize>(out: &mut OutBuf<'_, BPS>, value: i64) -> Result<(), AecError> {
+    let bps = out.bytes_per_sample();
+    let end = out.pos.checked_add(bps).ok_or(AecError::InvalidInput("output too large"))?;
+    if end > out.capacity() {
         return Err(AecError::InvalidInput("output buffer too small"));
     }
-
-    let msb = params.flags.contains(AecFlags::MSB);
-    if msb {
-        for i in (0..bytes_per_sample).rev() {
-            out.buf[out.pos] = ((raw_u >> (i * 8)) & 0xff) as u8;
-            out.pos += 1;
-        }
-    } else {
-        for i in 0..bytes_per_sample {
-            out.buf[out.pos] = ((raw_u >> (i * 8)) & 0xff) as u8;
-            out.pos += 1;
-        }
+    if let Some(check) = out.range.as_mut() {
+        check.observe(out.pos / bps, value);
     }
-
+    let value = out.substitution.as_ref().map_or(value, |s| s.apply(value));
+    out.format.write(value, &mut out.buf[out.pos..end]);
+    out.pos = end;
     Ok(())
 }
-
-fn sign_extend(raw: u32, bits: u8) -> i64 {
-    if bits == 32 {
-        return (raw as i32) as i64;
-    }
-    let shift = 32 - bits as u32;
-    (((raw << shift) as i32) >> shift) as i64
-}
@@ -0,0 +1,1082 @@
+//! CCSDS 121.0-B-3 encoder: the inverse of the decoder, producing streams that libaec and
+//! [`crate::decode`] read back.
+//!
+//! Every block gets the cheapest of the options the decoder understands (see [`crate::cost`]): a
+//! zero-block run, the Second Extension, a Rice split with any `k` the option id table allows, or
+//! uncompressed samples. Zero-block runs are collected per 64-block segment, as libaec does; a
+//! run of five or more blocks reaching the end of its segment (or RSI) uses the shorter
+//! "remainder of segment" code.
+//!
+//! With [`AecFlags::DATA_PREPROCESS`], samples are first mapped by the unit-delay predictor and
+//! fold ([`crate::preprocess::preprocess_step`]), and every RSI starts with a raw reference
+//! sample.
+
+#[cfg(feature = "io")]
+use std::io::Write;
+
+use crate::bitwriter::BitWriter;
+use crate::cost::{self, BlockOption};
+use crate::decoder::{bytes_per_sample, id_len, validate_params, DecodeStatus, Decoder, Flush};
+use crate::error::AecError;
+use crate::geometry::{self, blocks_to_segment_end};
+use crate::options::{DecodeOptions, EncodeLevel, EncodeOptions};
+use crate::params::{AecFlags, AecParams};
+use crate::preprocess::{preprocess_step, sign_extend};
+use crate::report::EncodeStats;
+use crate::rice::{self, RiceLayout};
+use crate::sample::Sample;
+
+/// Parameter-derived state (and scratch space) for coding one RSI at a time.
+#[derive(Debug, Clone)]
+pub(crate) struct RsiEncoder {
+    params: AecParams,
+    id_len: u32,
+    max_id: u32,
+    preprocess: bool,
+    level: EncodeLevel,
+    /// Coded values of the RSI being encoded.
+    coded: Vec<u32>,
+    /// Blocks and samples coded so far; the byte counts are left to the caller.
+    stats: EncodeStats,
+}
+
+impl RsiEncoder {
+    pub(crate) fn new(params: AecParams, options: &EncodeOptions) -> Result<Self, AecError> {
+        validate_params(params, &DecodeOptions::default())?;
+        if params.bits_per_sample == 0 {
+            return Err(AecError::InvalidInput("bits_per_sample 0 has nothing to encode"));
+        }
+        let id_len = id_len(params)? as u32;
+        Ok(Self {
+            params,
+            id_len,
+            max_id: (1u32 << id_len) - 1,
+            preprocess: params.flags.contains(AecFlags::DATA_PREPROCESS),
+            level: options.level,
+            coded: Vec::new(),
+            stats: EncodeStats::default(),
+        })
+    }
+
+    /// Encode one RSI of raw sample fields. Only the last RSI of a stream may be shorter than
+    /// `rsi * block_size`; its last block is padded by repeating the final sample.
+    ///
+    /// With preprocessing, the first sample is the reference sample and every other one is coded
+    /// as its fold against the previous sample; the reference keeps slot 0 of the first block.
+    pub(crate) fn encode_rsi(&mut self, w: &mut BitWriter, values: &[u32]) {
+        let block_size = self.params.block_size as usize;
+        let blocks = values.len().div_ceil(block_size);
+        self.stats.samples += values.len();
+        let last = values[values.len() - 1];
+        let padded = values.iter().copied().chain(std::iter::repeat(last)).take(blocks * block_size);
+
+        let mut d = std::mem::take(&mut self.coded);
+        d.clear();
+        let reference = if self.preprocess { Some(values[0]) } else { None };
+        if self.preprocess {
+            let mut prev = self.sample(values[0]);
+            d.push(0);
+            for v in padded.skip(1) {
+                let x = self.sample(v);
+                d.push(preprocess_step(prev, x, self.params));
+                prev = x;
+            }
+        } else {
+            d.extend(padded);
+        }
+        let block = |b: usize| &d[b * block_size..(b + 1) * block_size];
+        // The reference sample rides along with the first block of the RSI.
+        let reference_at = |b: usize| if b == 0 { reference } else { None };
+
+        let mut b = 0;
+        while b < blocks {
+            if block(b).iter().all(|&v| v == 0) {
+                // Zero-block runs end with their segment (and RSI).
+                let to_segment_end = blocks_to_segment_end(self.params, b as u32) as usize;
+                let limit = blocks.min(b + to_segment_end);
+                let mut z = 1;
+                while b + z < limit && block(b + z).iter().all(|&v| v == 0) {
+                    z += 1;
+                }
+                w.put(0, self.id_len);
+                w.put(0, 1);
+                if let Some(r) = reference_at(b) {
+                    w.put(r, self.params.bits_per_sample as u32);
+                }
+                w.put_unary(u64::from(cost::zero_run_fs(z as u32, to_segment_end as u32)));
+                self.stats.zero_runs += 1;
+                self.stats.zero_blocks += z as u64;
+                b += z;
+                continue;
+            }
+            self.encode_block(w, block(b), reference_at(b));
+            b += 1;
+        }
+        self.coded = d;
+
+        if blocks == self.params.rsi as usize && self.params.flags.contains(AecFlags::PAD_RSI) {
+            w.align_to_byte();
+        }
+    }
+
+    /// A raw sample field as the predictor sees it.
+    fn sample(&self, raw: u32) -> i64 {
+        if self.params.flags.contains(AecFlags::DATA_SIGNED) {
+            sign_extend(raw, self.params.bits_per_sample)
+        } else {
+            i64::from(raw)
+        }
+    }
+
+    /// Code a block that is not all zeros with its cheapest option (see [`cost::cheapest`]). With
+    /// a `reference` sample, `d[0]` is only its placeholder (zero).
+    fn encode_block(&mut self, w: &mut BitWriter, d: &[u32], reference: Option<u32>) {
+        let bits = self.params.bits_per_sample as u32;
+        let coded = &d[usize::from(reference.is_some())..];
+
+        let max_k = self.max_id.checked_sub(2);
+        let (best, _) = match self.level {
+            EncodeLevel::Fast => cost::fast_choice(d, coded, bits, max_k),
+            EncodeLevel::Best => cost::cheapest(d, coded, bits, max_k),
+        };
+
+        // The reference follows the option id, or the low-entropy selector.
+        let put_reference = |w: &mut BitWriter| {
+            if let Some(r) = reference {
+                w.put(r, bits);
+            }
+        };
+        match best {
+            BlockOption::SecondExtension => self.stats.second_extension_blocks += 1,
+            BlockOption::Split { k } => {
+                self.stats.split_blocks += 1;
+                self.stats.split_k_sum += u64::from(k);
+            }
+            BlockOption::Uncompressed => self.stats.uncompressed_blocks += 1,
+        }
+        match best {
+            BlockOption::SecondExtension => {
+                w.put(0, self.id_len);
+                w.put(1, 1);
+                put_reference(w);
+                for pair in d.chunks_exact(2) {
+                    let symbol = cost::second_extension_symbol(pair[0], pair[1]).unwrap_or(0);
+                    w.put_unary(u64::from(symbol));
+                }
+            }
+            BlockOption::Split { k } => {
+                w.put(k + 1, self.id_len);
+                put_reference(w);
+                rice::write_values(w, coded, k, RiceLayout::Split);
+            }
+            BlockOption::Uncompressed => {
+                w.put(self.max_id, self.id_len);
+                put_reference(w);
+                for &v in coded {
+                    w.put(v, bits);
+                }
+            }
+        }
+    }
+}
+
+/// Read packed samples, laid out as [`crate::decode`] writes them, into raw `bits_per_sample`
+/// fields.
+///
+/// A sample must fit in `bits_per_sample` bits; with [`AecFlags::DATA_SIGNED`], samples
+/// sign-extended to the whole sample width are accepted too.
+pub(crate) fn read_samples(params: AecParams, input: &[u8], out: &mut Vec<u32>) -> Result<(), AecError> {
+    let width = bytes_per_sample(params)?;
+    if input.len() % width != 0 {
+        return Err(AecError::InvalidInput("input length is not a multiple of the sample size"));
+    }
+    let n = u32::from(params.bits_per_sample);
+    let mask = (1u64 << n) - 1;
+    let signed = params.flags.contains(AecFlags::DATA_SIGNED);
+    let order = params.byte_order();
+    out.reserve(input.len() / width);
+    for sample in input.chunks_exact(width) {
+        let raw = order.get(sample);
+        if raw & !mask != 0 {
+            // Sign-extended to the sample width: everything above bit n-1 must be set.
+            let high = !mask & (u64::MAX >> (64 - 8 * width as u32));
+            if !signed || raw & high != high || raw & (1u64 << (n - 1)) == 0 {
+                return Err(AecError::InvalidInput("sample does not fit in bits_per_sample"));
+            }
+        }
+        out.push((raw & mask) as u32);
+    }
+    Ok(())
+}
+
+/// Read typed samples into raw `bits_per_sample` fields. Values must lie in the unsigned range
+/// of `bits_per_sample` bits, or the signed one with [`AecFlags::DATA_SIGNED`].
+fn read_typed<T: Sample>(params: AecParams, input: &[T], out: &mut Vec<u32>) -> Result<(), AecError> {
+    let n = u32::from(params.bits_per_sample);
+    let (min, max) = if params.flags.contains(AecFlags::DATA_SIGNED) {
+        (-(1i64 << (n - 1)), (1i64 << (n - 1)) - 1)
+    } else {
+        (0, (1i64 << n) - 1)
+    };
+    let mask = (1u64 << n) - 1;
+    out.reserve(input.len());
+    for &sample in input {
+        let v = sample.to_i64();
+        if v < min || v > max {
+            return Err(AecError::InvalidInput("sample does not fit in bits_per_sample"));
+        }
+        out.push((v as u64 & mask) as u32);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeStatus {
+    /// All pushed samples are encoded as far as possible; push more (or flush) to continue.
+    NeedInput,
+    /// The output buffer was filled; provide more output space to continue.
+    NeedOutput,
+    /// Flushed, and the whole stream has been handed out.
+    Finished,
+}
+
+/// Streaming AEC encoder, the counterpart of [`crate::Decoder`].
+///
+/// - call [`Encoder::push_samples`] to append packed sample bytes (any split, even inside a
+///   sample)
+/// - call [`Encoder::encode`] to write the bitstream into a caller buffer
+///
+/// Samples are encoded one RSI at a time, so at most one RSI of samples and its coded bits are
+/// buffered beyond what has been pushed but not yet encoded. Pass [`Flush::Flush`] once all
+/// samples are pushed to encode the final (possibly partial) RSI. The concatenated output is
+/// byte-identical to [`crate::encode`] of all samples.
+///
+/// ```
+/// use rust_aec::{encode, AecFlags, AecParams, EncodeStatus, Encoder, Flush};
+///
+/// let params = AecParams::new(8, 16, 4, AecFlags::empty());
+/// let samples: Vec<u8> = (0..200u32).map(|i| (i * 7 % 13) as u8).collect();
+///
+/// let mut enc = Encoder::new(params)?;
+/// let mut stream = Vec::new();
+/// let mut buf = [0u8; 16];
+/// for chunk in samples.chunks(50) {
+///     enc.push_samples(chunk);
+///     loop {
+///         let (n, status) = enc.encode(&mut buf, Flush::NoFlush)?;
+///         stream.extend_from_slice(&buf[..n]);
+///         if status == EncodeStatus::NeedInput {
+///             break;
+///         }
+///     }
+/// }
+/// loop {
+///     let (n, status) = enc.encode(&mut buf, Flush::Flush)?;
+///     stream.extend_from_slice(&buf[..n]);
+///     if status == EncodeStatus::Finished {
+///         break;
+///     }
+/// }
+/// assert_eq!(stream, encode(&samples, params)?);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    params: AecParams,
+    rsi_encoder: RsiEncoder,
+    bytes_per_sample: usize,
+    samples_per_rsi: usize,
+
+    /// Pushed bytes; `input[input_pos..]` is not yet read.
+    input: Vec<u8>,
+    input_pos: usize,
+    /// Samples of the RSI being collected.
+    values: Vec<u32>,
+    writer: BitWriter,
+
+    total_in: usize,
+    total_out: usize,
+    flushed: bool,
+    /// [`EncodeStatus::Finished`] has been returned.
+    finished: bool,
+}
+
+impl Encoder {
+    pub fn new(params: AecParams) -> Result<Self, AecError> {
+        Self::with_options(params, &EncodeOptions::default())
+    }
+
+    /// Create an encoder with non-default [`EncodeOptions`].
+    pub fn with_options(params: AecParams, options: &EncodeOptions) -> Result<Self, AecError> {
+        Ok(Self {
+            params,
+            rsi_encoder: RsiEncoder::new(params, options)?,
+            bytes_per_sample: bytes_per_sample(params)?,
+            samples_per_rsi: geometry::samples_per_rsi(params),
+            input: Vec::new(),
+            input_pos: 0,
+            values: Vec::new(),
+            writer: BitWriter::new(),
+            total_in: 0,
+            total_out: 0,
+            flushed: false,
+            finished: false,
+        })
+    }
+
+    /// Append packed sample bytes, laid out as [`crate::encode`] expects them.
+    pub fn push_samples(&mut self, samples: &[u8]) {
+        if self.input_pos > 0 && self.input_pos >= self.input.len() / 2 {
+            self.input.drain(..self.input_pos);
+            self.input_pos = 0;
+        }
+        self.input.extend_from_slice(samples);
+    }
+
+    /// Coding options chosen so far; the byte counts are [`Encoder::total_in`] and
+    /// [`Encoder::total_out`].
+    pub fn stats(&self) -> EncodeStats {
+        EncodeStats { input_bytes: self.total_in, output_bytes: self.total_out, ..self.rsi_encoder.stats.clone() }
+    }
+
+    /// Parameters the stream is encoded with.
+    pub fn params(&self) -> AecParams {
+        self.params
+    }
+
+    /// Total number of input bytes encoded so far.
+    pub fn total_in(&self) -> usize {
+        self.total_in
+    }
+
+    /// Total number of output bytes produced so far.
+    pub fn total_out(&self) -> usize {
+        self.total_out
+    }
+
+    /// Pushed bytes not yet encoded.
+    pub fn avail_in(&self) -> usize {
+        self.input.len() - self.input_pos
+    }
+
+    /// Whether the stream has been flushed and handed out completely.
+    pub fn is_finished(&self) -> bool {
+        self.flushed && self.writer.is_drained()
+    }
+
+    /// Encode into `out` and return (written_bytes, status).
+    ///
+    /// As with libaec's `AEC_FLUSH`, [`Flush::Flush`] encodes the final partial RSI (its last
+    /// block padded with the final sample), pads the stream to a byte boundary and returns
+    /// [`EncodeStatus::Finished`] once everything is handed out; until then keep calling with
+    /// more output space. After [`Flush::Flush`], pushing more samples is an error, and so is
+    /// any call after `Finished`.
+    pub fn encode(&mut self, out: &mut [u8], flush: Flush) -> Result<(usize, EncodeStatus), AecError> {
+        let mut written = 0;
+        let status = self.encode_with(flush, |w| {
+            written += w.drain_into(&mut out[written..]);
+            Ok(())
+        })?;
+        self.total_out += written;
+        Ok((written, status))
+    }
+
+    /// Encode into `writer` as far as the pushed samples allow; returns (written_bytes, status).
+    ///
+    /// This is [`Encoder::encode`] without an output buffer limit, so the status is never
+    /// [`EncodeStatus::NeedOutput`]. With [`Flush::Flush`] it encodes to completion. Bytes go to
+    /// `writer` as each RSI is encoded; `writer` is not flushed.
+    #[cfg(feature = "io")]
+    pub fn encode_to_writer<W: Write + ?Sized>(
+        &mut self,
+        writer: &mut W,
+        flush: Flush,
+    ) -> Result<(usize, EncodeStatus), AecError> {
+        let mut written = 0;
+        let status = self.encode_with(flush, |w| {
+            written += w.write_to(writer)?;
+            Ok(())
+        })?;
+        self.total_out += written;
+        Ok((written, status))
+    }
+
+    /// Encoding loop shared by the output flavours: `drain` hands out whole bytes, leaving the
+    /// rest in the bit writer when the output is full.
+    fn encode_with(
+        &mut self,
+        flush: Flush,
+        mut drain: impl FnMut(&mut BitWriter) -> Result<(), AecError>,
+    ) -> Result<EncodeStatus, AecError> {
+        if self.finished {
+            return Err(AecError::InvalidInput("encode called after the stream finished"));
+        }
+        if self.flushed && self.avail_in() > 0 {
+            return Err(AecError::InvalidInput("samples pushed after Flush::Flush"));
+        }
+        let status = loop {
+            drain(&mut self.writer)?;
+            if !self.writer.is_drained() {
+                break EncodeStatus::NeedOutput;
+            }
+            if self.flushed {
+                self.finished = true;
+                break EncodeStatus::Finished;
+            }
+
+            // Collect the rest of the current RSI.
+            let wanted = self.samples_per_rsi - self.values.len();
+            let whole = (self.avail_in() / self.bytes_per_sample).min(wanted) * self.bytes_per_sample;
+            read_samples(self.params, &self.input[self.input_pos..self.input_pos + whole], &mut self.values)?;
+            self.input_pos += whole;
+            self.total_in += whole;
+
+            if self.values.len() == self.samples_per_rsi {
+                self.rsi_encoder.encode_rsi(&mut self.writer, &self.values);
+                self.values.clear();
+            } else if flush == Flush::Flush {
+                if self.avail_in() > 0 {
+                    return Err(AecError::InvalidInput("input ends inside a sample"));
+                }
+                if !self.values.is_empty() {
+                    self.rsi_encoder.encode_rsi(&mut self.writer, &self.values);
+                    self.values.clear();
+                }
+                self.writer.align_to_byte();
+                self.flushed = true;
+            } else {
+                break EncodeStatus::NeedInput;
+            }
+        };
+        Ok(status)
+    }
+}
+
+/// Builder for an [`Encoder`] that checks the whole configuration before any sample is pushed.
+///
+/// [`EncoderBuilder::build`] applies [`AecParams::validate_strict`], so combinations libaec
+/// refuses, such as `DATA_3BYTE` with 12-bit samples, `RESTRICTED` above 4 bits per sample or
+/// more than 4096 blocks per RSI, fail up front instead of producing a stream other decoders
+/// reject. Unset values default to 32-sample blocks, 128 blocks per RSI and no flags.
+///
+/// ```
+/// use rust_aec::{AecFlags, EncoderBuilder};
+///
+/// let flags = AecFlags::DATA_PREPROCESS | AecFlags::MSB;
+/// let encoder = EncoderBuilder::new(12).block_size(32).rsi(128).flags(flags).build()?;
+/// assert_eq!(encoder.params().rsi, 128);
+///
+/// assert!(EncoderBuilder::new(12).flags(AecFlags::DATA_3BYTE).build().is_err());
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderBuilder {
+    bits_per_sample: u8,
+    block_size: u32,
+    rsi: u32,
+    flags: AecFlags,
+    options: EncodeOptions,
+}
+
+impl EncoderBuilder {
+    pub fn new(bits_per_sample: u8) -> Self {
+        Self { bits_per_sample, block_size: 32, rsi: 128, flags: AecFlags::empty(), options: EncodeOptions::default() }
+    }
+
+    /// Samples per block: 8, 16, 32 or 64.
+    pub fn block_size(self, block_size: u32) -> Self {
+        Self { block_size, ..self }
+    }
+
+    /// Blocks per reference sample interval, at most 4096.
+    pub fn rsi(self, rsi: u32) -> Self {
+        Self { rsi, ..self }
+    }
+
+    pub fn flags(self, flags: AecFlags) -> Self {
+        Self { flags, ..self }
+    }
+
+    pub fn options(self, options: EncodeOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    /// Validate the configuration and create the encoder.
+    pub fn build(self) -> Result<Encoder, AecError> {
+        let params = AecParams::new(self.bits_per_sample, self.block_size, self.rsi, self.flags);
+        params.validate_strict()?;
+        Encoder::with_options(params, &self.options)
+    }
+}
+
+/// Encode packed samples into an AEC bitstream (see [`crate::encode`]).
+pub(crate) fn encode(input: &[u8], params: AecParams, options: &EncodeOptions) -> Result<Vec<u8>, AecError> {
+    Ok(encode_rsis(input, params, options, |_| Ok(()))?.0.finish())
+}
+
+/// Encode packed samples and report the coding options chosen (see [`crate::encode_with_stats`]).
+pub(crate) fn encode_with_stats(
+    input: &[u8],
+    params: AecParams,
+    options: &EncodeOptions,
+) -> Result<(Vec<u8>, EncodeStats), AecError> {
+    let (w, stats) = encode_rsis(input, params, options, |_| Ok(()))?;
+    let stream = w.finish();
+    let stats = EncodeStats { input_bytes: input.len(), output_bytes: stream.len(), ..stats };
+    Ok((stream, stats))
+}
+
+/// Encode packed samples and check that the stream decodes back to them (see
+/// [`crate::encode_verified`]).
+pub(crate) fn encode_verified(input: &[u8], params: AecParams) -> Result<Vec<u8>, AecError> {
+    let stream = encode(input, params, &EncodeOptions::default())?;
+    verify(&stream, input, params)?;
+    Ok(stream)
+}
+
+/// Decode `stream` piecewise and compare it with the packed samples it was encoded from.
+fn verify(stream: &[u8], input: &[u8], params: AecParams) -> Result<(), AecError> {
+    let width = bytes_per_sample(params)?;
+    let mask = u64::MAX >> (64 - u32::from(params.bits_per_sample));
+    let order = params.byte_order();
+    let mut dec = Decoder::new(params, input.len() / width)?;
+    dec.push_input(stream);
+    let mut buf = vec![0u8; 4096 * width];
+    let mut checked = 0;
+    loop {
+        let (n, status) = dec.decode(&mut buf, Flush::Flush)?;
+        let expected = &input[checked * width..checked * width + n];
+        for (i, (a, b)) in expected.chunks_exact(width).zip(buf[..n].chunks_exact(width)).enumerate() {
+            // Signed input may be sign-extended beyond `bits_per_sample`; the decoder's is not.
+            let (expected, decoded) = (order.get(a) & mask, order.get(b) & mask);
+            if expected != decoded {
+                return Err(AecError::RoundTripMismatch { sample: checked + i, expected, decoded });
+            }
+        }
+        checked += n / width;
+        if status == DecodeStatus::Finished {
+            return Ok(());
+        }
+    }
+}
+
+/// Encode typed samples into an AEC bitstream (see [`crate::encode_samples`]).
+pub(crate) fn encode_samples<T: Sample>(samples: &[T], params: AecParams) -> Result<Vec<u8>, AecError> {
+    let mut encoder = RsiEncoder::new(params, &EncodeOptions::default())?;
+    let mut values = Vec::new();
+    let mut w = BitWriter::new();
+    for chunk in samples.chunks(geometry::samples_per_rsi(params)) {
+        values.clear();
+        read_typed(params, chunk, &mut values)?;
+        encoder.encode_rsi(&mut w, &values);
+    }
+    Ok(w.finish())
+}
+
+/// Encode packed samples into `output`; returns the number of bytes written (see
+/// [`crate::encode_into`]).
+pub(crate) fn encode_into(input: &[u8], params: AecParams, output: &mut [u8]) -> Result<usize, AecError> {
+    let mut written = 0;
+    let mut drain = |w: &mut BitWriter| {
+        written += w.drain_into(&mut output[written..]);
+        if w.is_drained() { Ok(()) } else { Err(AecError::InvalidInput("output buffer too small")) }
+    };
+    let (mut w, _) = encode_rsis(input, params, &EncodeOptions::default(), &mut drain)?;
+    w.align_to_byte();
+    drain(&mut w)?;
+    Ok(written)
+}
+
+/// Encode `input` one RSI at a time, calling `emit` after each; returns the writer holding
+/// whatever `emit` left in it, not yet padded to a byte, and the block statistics.
+fn encode_rsis(
+    input: &[u8],
+    params: AecParams,
+    options: &EncodeOptions,
+    mut emit: impl FnMut(&mut BitWriter) -> Result<(), AecError>,
+) -> Result<(BitWriter, EncodeStats), AecError> {
+    let mut encoder = RsiEncoder::new(params, options)?;
+    let rsi_bytes = geometry::samples_per_rsi(params).saturating_mul(bytes_per_sample(params)?);
+    let mut values = Vec::new();
+    let mut w = BitWriter::new();
+    for chunk in input.chunks(rsi_bytes) {
+        values.clear();
+        read_samples(params, chunk, &mut values)?;
+        encoder.encode_rsi(&mut w, &values);
+        emit(&mut w)?;
+    }
+    Ok((w, encoder.stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode, encode_with_options};
+
+    fn round_trip(params: AecParams, samples: &[u32]) -> Vec<u8> {
+        let width = bytes_per_sample(params).unwrap();
+        let mut input = vec![0u8; samples.len() * width];
+        for (s, out) in samples.iter().zip(input.chunks_exact_mut(width)) {
+            params.byte_order().put(u64::from(*s), out);
+        }
+        let encoded = encode(&input, params).unwrap();
+        assert_eq!(crate::decode(&encoded, params, samples.len()).unwrap(), input, "{params:?}");
+        encoded
+    }
+
+    #[test]
+    fn every_block_option_round_trips() {
+        let zeros = vec![0u32; 64 * 70 + 5];
+        let small: Vec<u32> = (0..1000).map(|i| (i * 7 % 5) as u32).collect();
+        let ramp: Vec<u32> = (0..1000).map(|i| (i * 37 % 4096) as u32).collect();
+        let mixed: Vec<u32> = (0..3000).map(|i| if (i / 256) % 3 == 0 { 0 } else { (i % 300) as u32 }).collect();
+        let preprocess = AecFlags::DATA_PREPROCESS;
+        for flags in [
+            AecFlags::MSB,
+            AecFlags::empty(),
+            AecFlags::PAD_RSI | AecFlags::MSB,
+            preprocess | AecFlags::MSB,
+            preprocess | AecFlags::DATA_SIGNED | AecFlags::PAD_RSI,
+        ] {
+            for (block_size, rsi) in [(8, 1), (16, 3), (32, 128), (64, 70)] {
+                let params = AecParams::new(12, block_size, rsi, flags);
+                for samples in [&zeros, &small, &ramp, &mixed] {
+                    round_trip(params, samples);
+                }
+            }
+        }
+    }
+
+    /// Coding options of the blocks of `stream`, one entry per block or zero-block run.
+    fn block_kinds(stream: &[u8], params: AecParams, blocks: usize) -> Vec<crate::BlockKind> {
+        let mut r = crate::bitreader::BitReader::new(stream);
+        let mut state = crate::PredictorState::default();
+        let mut kinds = Vec::new();
+        let mut seen = 0;
+        while seen < blocks {
+            let block = crate::decode_block(&mut r, &mut state, params).unwrap();
+            seen += block.samples.len() / params.block_size as usize;
+            kinds.push(block.kind);
+        }
+        kinds
+    }
+
+    #[test]
+    fn zero_runs_follow_segments_and_rsis() {
+        use crate::BlockKind::{Split, ZeroRun};
+
+        // 70 blocks in one RSI: a full segment, then the 6-block remainder up to the RSI end.
+        let params = AecParams::new(8, 8, 70, AecFlags::empty());
+        let stream = round_trip(params, &[0; 8 * 70]);
+        assert_eq!(block_kinds(&stream, params, 70), [ZeroRun { blocks: 64 }, ZeroRun { blocks: 6 }]);
+        assert_eq!(stream.len(), (2 * (3 + 1 + 5) as usize).div_ceil(8));
+
+        // Five zero blocks short of the segment end are counted, not coded as "remainder".
+        let mut samples = vec![0; 8 * 5];
+        samples.extend([1; 8]);
+        let params = AecParams::new(8, 8, 64, AecFlags::empty());
+        let kinds = block_kinds(&round_trip(params, &samples), params, 6);
+        assert_eq!(kinds, [ZeroRun { blocks: 5 }, Split { k: 0 }]);
+
+        // Runs stop at RSI boundaries, also below the five-block remainder threshold.
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+        let kinds = block_kinds(&round_trip(params, &[0; 8 * 11]), params, 11);
+        assert_eq!(kinds, [ZeroRun { blocks: 4 }, ZeroRun { blocks: 4 }, ZeroRun { blocks: 3 }]);
+
+        // With preprocessing, a constant field is a reference sample plus zero-block runs.
+        let params = AecParams::new(8, 8, 100, AecFlags::DATA_PREPROCESS);
+        let kinds = block_kinds(&round_trip(params, &[77; 8 * 200]), params, 200);
+        assert_eq!(kinds, [64, 36, 64, 36].map(|blocks| ZeroRun { blocks }));
+    }
+
+    #[test]
+    fn second_extension_shares_its_first_pair_with_the_reference() {
+        // Mostly flat with sparse steps: cheapest as Second Extension, also in the first block,
+        // where the reference sample takes slot 0 and the first symbol only carries sample 1.
+        let params = AecParams::new(12, 16, 4, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+        let samples: Vec<u32> = (0..16 * 4).map(|i| 100 + u32::from(i % 8 == 1)).collect();
+        let stream = round_trip(params, &samples);
+
+        let mut r = crate::bitreader::BitReader::new(&stream);
+        let first = crate::decode_block(&mut r, &mut crate::PredictorState::default(), params).unwrap();
+        assert_eq!(first.kind, crate::BlockKind::SecondExtension);
+        assert_eq!(first.reference, Some(100));
+        assert_eq!(block_kinds(&stream, params, 4), [crate::BlockKind::SecondExtension; 4]);
+    }
+
+    #[test]
+    fn pad_rsi_starts_every_interval_on_a_byte() {
+        let samples: Vec<u32> = (0..16 * 23u32).map(|i| (i * 13 % 97) * u32::from(i % 48 > 20)).collect();
+        for flags in [AecFlags::PAD_RSI, AecFlags::PAD_RSI | AecFlags::DATA_PREPROCESS] {
+            for rsi in [1, 3, 5] {
+                let params = AecParams::new(7, 16, rsi, flags);
+                let stream = round_trip(params, &samples);
+
+                let mut r = crate::bitreader::BitReader::new(&stream);
+                let mut state = crate::PredictorState::default();
+                let mut blocks = 0;
+                while blocks < 23 {
+                    if state.block_index_within_rsi == 0 {
+                        assert_eq!(r.bits_read() % 8, 0, "{params:?}, block {blocks}");
+                    }
+                    let block = crate::decode_block(&mut r, &mut state, params).unwrap();
+                    blocks += block.samples.len() / 16;
+                }
+                assert_eq!(r.bits_read().div_ceil(8), stream.len());
+
+                // Without the flag the same samples pack tighter.
+                let unpadded = AecParams::new(7, 16, rsi, flags - AecFlags::PAD_RSI);
+                assert!(round_trip(unpadded, &samples).len() <= stream.len());
+            }
+        }
+    }
+
+    #[test]
+    fn signed_samples_round_trip_through_the_signed_decode_path() {
+        for bits in [1u8, 5, 12, 17, 32] {
+            let (min, max) = (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1);
+            // Extremes, full-range jumps (reflected by the fold) and a slow ramp through zero.
+            let mut values = vec![min, max, min, 0, -1, max, max, min];
+            values.extend((0..300).map(|i| (i - 150).clamp(min, max)));
+            values.extend((0..300i64).map(|i| if i % 7 == 0 { min } else { max - i % 3 }.clamp(min, max)));
+
+            for flags in [AecFlags::DATA_SIGNED, AecFlags::DATA_SIGNED | AecFlags::DATA_PREPROCESS | AecFlags::MSB] {
+                let params = AecParams::new(bits, 16, 8, flags);
+                let width = bytes_per_sample(params).unwrap();
+                // Two's complement, sign-extended to the sample width.
+                let mut input = vec![0u8; values.len() * width];
+                for (&v, out) in values.iter().zip(input.chunks_exact_mut(width)) {
+                    params.byte_order().put(v as u64, out);
+                }
+                let stream = encode(&input, params).unwrap();
+
+                let mut r = crate::bitreader::BitReader::new(&stream);
+                let mut state = crate::PredictorState::default();
+                let mut decoded = Vec::new();
+                while decoded.len() < values.len() {
+                    decoded.extend(crate::decode_block(&mut r, &mut state, params).unwrap().samples);
+                }
+                decoded.truncate(values.len());
+                assert_eq!(decoded, values, "{params:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn three_byte_samples_read_in_either_byte_order() {
+        for bits in [17u8, 20, 24] {
+            let values: Vec<u32> = (0..700u32).map(|i| i.wrapping_mul(0x9e37_79b9) >> (32 - u32::from(bits)) >> (i % 9)).collect();
+            for order in [AecFlags::MSB, AecFlags::empty()] {
+                let packed = AecParams::new(bits, 32, 16, AecFlags::DATA_3BYTE | AecFlags::DATA_PREPROCESS | order);
+                let stream = round_trip(packed, &values);
+                // Only the sample layout differs from 4-byte input, not the bitstream.
+                let wide = AecParams::new(bits, 32, 16, AecFlags::DATA_PREPROCESS | order);
+                assert_eq!(round_trip(wide, &values), stream);
+            }
+        }
+
+        let msb = AecParams::new(24, 8, 1, AecFlags::DATA_3BYTE | AecFlags::MSB);
+        let lsb = AecParams::new(24, 8, 1, AecFlags::DATA_3BYTE);
+        let sample = [0x12, 0x34, 0x56];
+        let first = |params| {
+            let stream = encode(&sample, params).unwrap();
+            let mut r = crate::bitreader::BitReader::new(&stream);
+            crate::decode_block(&mut r, &mut crate::PredictorState::default(), params).unwrap().samples[0]
+        };
+        assert_eq!((first(msb), first(lsb)), (0x12_3456, 0x56_3412));
+        assert!(encode(&[0; 4], msb).is_err());
+    }
+
+    #[test]
+    fn max_encoded_size_bounds_every_stream() {
+        let noise = |bits: u8, n: usize| -> Vec<u32> {
+            (0..n as u32).map(|i| i.wrapping_mul(0x9e37_79b9).rotate_left(i % 32) >> (32 - u32::from(bits))).collect()
+        };
+        for (bits, block_size, rsi, flags) in [
+            (8u8, 8u32, 1u32, AecFlags::PAD_RSI),
+            (12, 32, 128, AecFlags::DATA_PREPROCESS | AecFlags::MSB),
+            (16, 16, 3, AecFlags::PAD_RSI | AecFlags::DATA_PREPROCESS),
+            (2, 64, 5, AecFlags::RESTRICTED),
+            (32, 64, 2, AecFlags::PAD_RSI),
+        ] {
+            let params = AecParams::new(bits, block_size, rsi, flags);
+            for n in [0, 1, 7, 100, 5000] {
+                let samples = noise(bits, n);
+                let len = round_trip(params, &samples).len();
+                let bound = params.max_encoded_size(n);
+                assert!(len <= bound, "{params:?}, {n} samples: {len} > {bound}");
+
+                let width = bytes_per_sample(params).unwrap();
+                let mut input = vec![0u8; n * width];
+                for (s, out) in samples.iter().zip(input.chunks_exact_mut(width)) {
+                    params.byte_order().put(u64::from(*s), out);
+                }
+                let mut output = vec![0u8; bound];
+                assert_eq!(encode_into(&input, params, &mut output).unwrap(), len);
+                assert_eq!(output[..len], encode(&input, params).unwrap());
+                if len > 0 {
+                    assert!(encode_into(&input, params, &mut output[..len - 1]).is_err());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn picks_compact_options() {
+        let params = AecParams::new(16, 32, 64, AecFlags::MSB);
+        // 64 all-zero blocks: one "remainder of segment" run (id, selector, `fs = 4`).
+        assert_eq!(round_trip(params, &[0; 32 * 64]), [0x00, 0x40]);
+        // Sparse ones: the Second Extension spends about half a bit per sample, less than any split.
+        let sparse: Vec<u32> = (0..32 * 64).map(|i| u32::from(i % 16 == 0)).collect();
+        assert!(round_trip(params, &sparse).len() < 32 * 64 / 8);
+    }
+
+    #[test]
+    fn preprocessing_codes_smooth_fields_compactly() {
+        // GRIB2 ccsdsFlags 0x0e: DATA_PREPROCESS | MSB | DATA_3BYTE.
+        let params = AecParams::new(12, 32, 128, AecFlags::DATA_PREPROCESS | AecFlags::MSB | AecFlags::DATA_3BYTE);
+        let field: Vec<u32> = (0..10_000).map(|i| 2048 + ((i as f64 / 400.0).sin() * 1500.0) as u32).collect();
+        let encoded = round_trip(params, &field);
+        assert!(encoded.len() < field.len() / 4, "{} bytes", encoded.len());
+
+        // A constant RSI is a reference sample and one zero-block run per 64-block segment.
+        let constant = round_trip(params, &[4095; 4096]);
+        assert_eq!(constant.len(), ((4 + 1 + 12 + 5) + (4 + 1 + 5)) / 8);
+    }
+
+    #[test]
+    fn fast_level_round_trips_close_to_the_best() {
+        let fast = EncodeOptions { level: EncodeLevel::Fast };
+        let field: Vec<u8> = (0..20_000u32)
+            .flat_map(|i| ((2000.0 + (i as f64 / 90.0).sin() * 900.0 + (i % 7) as f64) as u16).to_le_bytes())
+            .collect();
+        for flags in [AecFlags::DATA_PREPROCESS, AecFlags::empty(), AecFlags::RESTRICTED] {
+            let params = AecParams::new(if flags.contains(AecFlags::RESTRICTED) { 4 } else { 12 }, 32, 64, flags);
+            let samples: Vec<u8> = if params.bits_per_sample == 4 {
+                (0..5000u32).map(|i| (i / 300 % 16) as u8).collect()
+            } else {
+                field.clone()
+            };
+            let stream = encode_with_options(&samples, params, &fast).unwrap();
+            let best = encode(&samples, params).unwrap();
+            let n = samples.len() / bytes_per_sample(params).unwrap();
+            assert_eq!(crate::decode(&stream, params, n).unwrap(), samples);
+            assert!(stream.len() >= best.len() && stream.len() * 100 <= best.len() * 105, "{params:?}");
+        }
+    }
+
+    #[test]
+    fn restricted_id_table_limits_the_options() {
+        use crate::BlockKind::Split;
+
+        for bits in 1..=4u8 {
+            let max = (1u32 << bits) - 1;
+            // Noise (uncompressed), small values (low entropy or small k) and constant runs.
+            let samples: Vec<u32> = (0..16 * 40u32)
+                .map(|i| match (i / 64) % 3 {
+                    0 => i.wrapping_mul(0x9e37_79b9) >> (32 - u32::from(bits)),
+                    1 => u32::from(i % 5 == 0).min(max),
+                    _ => max,
+                })
+                .collect();
+            for flags in [AecFlags::RESTRICTED, AecFlags::RESTRICTED | AecFlags::DATA_PREPROCESS] {
+                let params = AecParams::new(bits, 16, 8, flags);
+                let kinds = block_kinds(&round_trip(params, &samples), params, 40);
+                // One id bit up to 2 bits per sample (no splits), two bits (k <= 1) up to 4.
+                let max_k = if bits <= 2 { None } else { Some(1) };
+                for kind in kinds {
+                    if let Split { k } = kind {
+                        assert!(Some(k) <= max_k, "{params:?}: {kind:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wide_samples_round_trip() {
+        let wide: Vec<u32> = (0..500u32).map(|i| i.wrapping_mul(0x9e37_79b9)).collect();
+        round_trip(AecParams::new(32, 16, 8, AecFlags::MSB), &wide);
+        round_trip(AecParams::new(32, 16, 8, AecFlags::DATA_PREPROCESS | AecFlags::DATA_SIGNED), &wide);
+        let wide24: Vec<u32> = wide.iter().map(|v| v >> 8).collect();
+        round_trip(AecParams::new(24, 16, 8, AecFlags::DATA_3BYTE), &wide24);
+    }
+
+    #[test]
+    fn streaming_matches_one_shot_for_any_chunking() {
+        let params = AecParams::new(12, 16, 5, AecFlags::MSB | AecFlags::PAD_RSI);
+        let samples: Vec<u8> = (0..1234u32).flat_map(|i| (((i * i) % 4096) as u16 & !(i as u16 & 0xf0)).to_be_bytes()).collect();
+        let expected = encode(&samples, params).unwrap();
+        for (in_chunk, out_chunk) in [(1, 1), (3, 7), (160, 2), (5000, 5000)] {
+            let mut enc = Encoder::new(params).unwrap();
+            let mut stream = Vec::new();
+            let mut buf = vec![0u8; out_chunk];
+            let mut chunks = samples.chunks(in_chunk);
+            loop {
+                let flush = if chunks.len() == 0 { Flush::Flush } else { Flush::NoFlush };
+                let (n, status) = enc.encode(&mut buf, flush).unwrap();
+                stream.extend_from_slice(&buf[..n]);
+                match status {
+                    EncodeStatus::Finished => break,
+                    EncodeStatus::NeedOutput => {}
+                    EncodeStatus::NeedInput => enc.push_samples(chunks.next().unwrap()),
+                }
+            }
+            assert_eq!(stream, expected, "input chunks of {in_chunk}, output chunks of {out_chunk}");
+            assert_eq!((enc.total_in(), enc.total_out()), (samples.len(), expected.len()));
+            assert!(enc.is_finished());
+        }
+
+        let mut enc = Encoder::new(params).unwrap();
+        enc.push_samples(&[0x01]);
+        assert!(enc.encode(&mut [0u8; 8], Flush::Flush).is_err());
+    }
+
+    #[test]
+    fn flush_pads_the_final_block_and_finishes_once() {
+        // 5 samples of a 16-sample block, padded with the last one.
+        let params = AecParams::new(8, 16, 4, AecFlags::empty());
+        let samples = [0x10, 0xf0, 0x33, 0x81, 0x7e];
+        let mut enc = Encoder::new(params).unwrap();
+        enc.push_samples(&samples);
+        let mut buf = [0u8; 32];
+        assert_eq!(enc.encode(&mut buf, Flush::NoFlush).unwrap(), (0, EncodeStatus::NeedInput));
+
+        let (n, status) = enc.encode(&mut buf[..10], Flush::Flush).unwrap();
+        assert_eq!((n, status), (10, EncodeStatus::NeedOutput));
+        let (m, status) = enc.encode(&mut buf[10..], Flush::Flush).unwrap();
+        assert_eq!(status, EncodeStatus::Finished);
+        assert_eq!(&buf[..n + m], encode(&samples, params).unwrap());
+        assert_eq!(crate::decode(&buf[..n + m], params, 5).unwrap(), samples);
+
+        assert!(enc.encode(&mut buf, Flush::Flush).is_err());
+        assert!(enc.is_finished());
+    }
+
+    #[test]
+    fn typed_samples_encode_like_packed_bytes() {
+        let unsigned: Vec<u16> = (0..3000).map(|i| (i * 37 % 4096) as u16).collect();
+        for flags in [AecFlags::DATA_PREPROCESS, AecFlags::MSB | AecFlags::PAD_RSI] {
+            let params = AecParams::new(12, 16, 32, flags);
+            let packed: Vec<u8> = unsigned.iter().flat_map(|v| {
+                if flags.contains(AecFlags::MSB) { v.to_be_bytes() } else { v.to_le_bytes() }
+            }).collect();
+            assert_eq!(encode_samples(&unsigned, params).unwrap(), encode(&packed, params).unwrap());
+        }
+
+        let params = AecParams::new(20, 32, 8, AecFlags::DATA_SIGNED | AecFlags::DATA_PREPROCESS | AecFlags::DATA_3BYTE);
+        let signed: Vec<i32> = (0..1000).map(|i| (i - 500) * 1000 + i % 3).collect();
+        let decoded = crate::decode(&encode_samples(&signed, params).unwrap(), params, signed.len()).unwrap();
+        // Decoded samples are 20-bit two's complement fields.
+        let back: Vec<i32> =
+            decoded.chunks_exact(3).map(|b| i32::from_le_bytes([b[0], b[1], b[2], 0]) << 12 >> 12).collect();
+        assert_eq!(back, signed);
+
+        assert!(encode_samples(&[4096u16], AecParams::new(12, 16, 32, AecFlags::empty())).is_err());
+        assert!(encode_samples(&[-1i32], AecParams::new(12, 16, 32, AecFlags::empty())).is_err());
+        assert!(encode_samples(&[2048i16], AecParams::new(12, 16, 32, AecFlags::DATA_SIGNED)).is_err());
+    }
+
+    #[cfg(feature = "io")]
+    #[test]
+    fn writer_output_matches_one_shot() {
+        let params = AecParams::new(16, 32, 16, AecFlags::DATA_PREPROCESS | AecFlags::PAD_RSI);
+        let samples: Vec<u8> = (0..20_000u32).flat_map(|i| ((i * i / 97) as u16).to_le_bytes()).collect();
+
+        let mut enc = Encoder::new(params).unwrap();
+        let mut sink = Vec::new();
+        let mut written = 0;
+        for chunk in samples.chunks(3001) {
+            enc.push_samples(chunk);
+            let (n, status) = enc.encode_to_writer(&mut sink, Flush::NoFlush).unwrap();
+            assert_eq!(status, EncodeStatus::NeedInput);
+            written += n;
+        }
+        let (n, status) = enc.encode_to_writer(&mut sink, Flush::Flush).unwrap();
+        assert_eq!((status, written + n, enc.total_out()), (EncodeStatus::Finished, sink.len(), sink.len()));
+        assert_eq!(sink, encode(&samples, params).unwrap());
+    }
+
+    #[test]
+    fn builder_rejects_what_libaec_rejects() {
+        let flags = AecFlags::DATA_PREPROCESS | AecFlags::DATA_3BYTE;
+        let mut enc = EncoderBuilder::new(20).block_size(16).rsi(8).flags(flags).build().unwrap();
+        let samples: Vec<u8> = (0..999u32).flat_map(|i| (i * 1013 % (1 << 20)).to_le_bytes()[..3].to_vec()).collect();
+        enc.push_samples(&samples);
+        let mut out = vec![0u8; AecParams::new(20, 16, 8, flags).max_encoded_size(999)];
+        let (n, _) = enc.encode(&mut out, Flush::Flush).unwrap();
+        assert_eq!(out[..n], encode(&samples, enc.params()).unwrap());
+
+        for builder in [
+            EncoderBuilder::new(12).flags(AecFlags::DATA_3BYTE),
+            EncoderBuilder::new(8).flags(AecFlags::RESTRICTED),
+            EncoderBuilder::new(8).rsi(4097),
+            EncoderBuilder::new(8).block_size(24),
+            EncoderBuilder::new(33),
+        ] {
+            assert!(builder.build().is_err(), "{builder:?}");
+        }
+    }
+
+    #[test]
+    fn verified_encoding_reports_the_first_bad_sample() {
+        let params = AecParams::new(20, 16, 4, AecFlags::DATA_SIGNED | AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+        // Negative samples sign-extended to all 32 bits.
+        let samples: Vec<u8> = (0..777i32).flat_map(|i| ((i - 300) * 977).to_be_bytes()).collect();
+        let stream = encode_verified(&samples, params).unwrap();
+        assert_eq!(stream, encode(&samples, params).unwrap());
+
+        // Random 8-bit samples code as uncompressed blocks: a flipped bit changes one sample.
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+        let samples: Vec<u8> = (0..64u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        let mut stream = encode(&samples, params).unwrap();
+        assert_eq!(block_kinds(&stream, params, 8), [crate::BlockKind::Uncompressed; 8]);
+        stream[20] ^= 0x01;
+        let err = verify(&stream, &samples, params).unwrap_err();
+        assert!(matches!(err, AecError::RoundTripMismatch { sample: 19, .. }), "{err}");
+    }
+
+    #[test]
+    fn stats_count_every_block_once() {
+        // Blocks: a zero run of 3, a single one (Second Extension) and a ramp (split), twice.
+        let params = AecParams::new(8, 8, 5, AecFlags::PAD_RSI);
+        let rsi = [[0u8; 24].as_slice(), &[0, 0, 0, 1, 0, 0, 0, 0], &[40, 41, 42, 43, 44, 45, 46, 47]].concat();
+        let samples = [rsi.as_slice(), &rsi, &[200, 3, 250, 7, 99, 180, 1, 255]].concat();
+        let (stream, stats) = crate::encode_with_stats(&samples, params, &EncodeOptions::default()).unwrap();
+        use crate::BlockKind::{SecondExtension, Split, ZeroRun};
+        assert_eq!(block_kinds(&stream, params, 11)[..3], [ZeroRun { blocks: 3 }, SecondExtension, Split { k: 4 }]);
+        let expected = EncodeStats {
+            samples: 88,
+            zero_runs: 2,
+            zero_blocks: 6,
+            second_extension_blocks: 2,
+            split_blocks: 2,
+            split_k_sum: 8,
+            uncompressed_blocks: 1,
+            input_bytes: 88,
+            output_bytes: stream.len(),
+        };
+        assert_eq!(stats, expected);
+        assert_eq!(stats.average_k(), Some(4.0));
+        assert_eq!(stats.ratio(), stream.len() as f64 / 88.0);
+
+        let mut enc = Encoder::new(params).unwrap();
+        enc.push_samples(&samples);
+        let mut out = vec![0u8; stream.len()];
+        enc.encode(&mut out, Flush::Flush).unwrap();
+        assert_eq!(enc.stats(), expected);
+    }
+
+    #[test]
+    fn rejects_samples_wider_than_bits_per_sample() {
+        let params = AecParams::new(12, 32, 128, AecFlags::MSB);
+        assert!(encode(&[0x10, 0x00], params).is_err());
+        assert!(encode(&[0x0f], params).is_err());
+
+        // Sign-extended negative samples are accepted and decode to their low 12 bits.
+        let signed = AecParams::new(12, 32, 128, AecFlags::MSB | AecFlags::DATA_SIGNED);
+        let encoded = encode(&[0xff, 0xfe], signed).unwrap();
+        assert_eq!(crate::decode(&encoded, signed, 1).unwrap(), [0x0f, 0xfe]);
+        assert!(encode(&[0xf0, 0x00], signed).is_err());
+    }
+}
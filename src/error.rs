@@ -1,4 +1,5 @@
 use core::fmt;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub enum AecError {
@@ -7,6 +8,40 @@ pub enum AecError {
     NotImplemented(&'static str),
     UnexpectedEof { bit_pos: usize },
     UnexpectedEofDuringDecode { bit_pos: usize, samples_written: usize },
+    /// A configured resource budget (see [`crate::DecodeOptions`]) was exhausted.
+    LimitExceeded { what: &'static str, limit: u64 },
+    /// Writing decoded output (or reading input) through `std::io` failed.
+    Io(Arc<std::io::Error>),
+    /// [`crate::encode_verified`] decoded sample `sample` (zero-based) as `decoded` instead of
+    /// `expected` (both `bits_per_sample`-bit fields).
+    RoundTripMismatch { sample: usize, expected: u64, decoded: u64 },
+    /// An error from a decode call tagged with [`crate::DecodeOptions::context`].
+    Context { context: Arc<str>, error: Box<AecError> },
+}
+
+impl AecError {
+    /// The caller context attached to this error, if any.
+    pub fn context(&self) -> Option<&str> {
+        match self {
+            AecError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The underlying error with any context tag removed, for matching on the failure kind.
+    pub fn untagged(&self) -> &AecError {
+        match self {
+            AecError::Context { error, .. } => error.untagged(),
+            e => e,
+        }
+    }
+
+    pub(crate) fn tagged(self, context: Option<&Arc<str>>) -> Self {
+        match context {
+            Some(context) => AecError::Context { context: context.clone(), error: Box::new(self) },
+            None => self,
+        }
+    }
 }
 
 impl fmt::Display for AecError {
@@ -19,8 +54,28 @@ impl fmt::Display for AecError {
             AecError::UnexpectedEofDuringDecode { bit_pos, samples_written } => {
                 write!(f, "unexpected end of input at bit {bit_pos} (wrote {samples_written} samples)")
             }
+            AecError::LimitExceeded { what, limit } => write!(f, "limit exceeded: {what} (limit {limit})"),
+            AecError::Io(e) => write!(f, "I/O error: {e}"),
+            AecError::RoundTripMismatch { sample, expected, decoded } => {
+                write!(f, "round trip mismatch at sample {sample}: expected {expected:#x}, decoded {decoded:#x}")
+            }
+            AecError::Context { context, error } => write!(f, "{context}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for AecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AecError::Io(e) => Some(e.as_ref()),
+            AecError::Context { error, .. } => Some(error.as_ref()),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for AecError {}
+impl From<std::io::Error> for AecError {
+    fn from(e: std::io::Error) -> Self {
+        AecError::Io(Arc::new(e))
+    }
+}
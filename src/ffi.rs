@@ -0,0 +1,81 @@
+//! C ABI for embedding the decoder in non-Rust ground-segment software (feature `ffi`).
+//!
+//! Return codes follow libaec's convention: `0` on success, negative on failure.
+
+use crate::error::AecError;
+use crate::params::{AecFlags, AecParams};
+
+/// Success.
+pub const RUST_AEC_OK: i32 = 0;
+/// Invalid parameters or a corrupt payload.
+pub const RUST_AEC_DATA_ERROR: i32 = -3;
+/// The payload ended before `output_samples` samples were decoded.
+pub const RUST_AEC_STREAM_ERROR: i32 = -2;
+/// The configuration is not supported, or a budget was exceeded.
+pub const RUST_AEC_CONF_ERROR: i32 = -1;
+
+fn error_code(e: &AecError) -> i32 {
+    match e {
+        AecError::UnexpectedEof { .. } | AecError::UnexpectedEofDuringDecode { .. } => RUST_AEC_STREAM_ERROR,
+        AecError::Unsupported(_) | AecError::NotImplemented(_) | AecError::LimitExceeded { .. } => {
+            RUST_AEC_CONF_ERROR
+        }
+        AecError::InvalidInput(_) | AecError::Io(_) | AecError::RoundTripMismatch { .. } => RUST_AEC_DATA_ERROR,
+        AecError::Context { error, .. } => error_code(error),
+    }
+}
+
+/// Decode `input_len` bytes at `input` into `output_len` bytes at `output`.
+///
+/// `flags` uses the [`AecFlags`] bit values (libaec's `AEC_DATA_*` flags). `output_len` must be
+/// exactly `output_samples` times the output sample width.
+///
+/// # Safety
+///
+/// `input` must be valid for reads of `input_len` bytes and `output` valid for writes of
+/// `output_len` bytes; the two regions must not overlap. Null pointers are only allowed with a
+/// zero length.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rust_aec_decode(
+    input: *const u8,
+    input_len: usize,
+    bits_per_sample: u8,
+    block_size: u32,
+    rsi: u32,
+    flags: u32,
+    output: *mut u8,
+    output_len: usize,
+    output_samples: usize,
+) -> i32 {
+    if (input.is_null() && input_len != 0) || (output.is_null() && output_len != 0) {
+        return RUST_AEC_DATA_ERROR;
+    }
+    // SAFETY: the caller guarantees the regions are valid; empty slices need no valid pointer.
+    let input = if input_len == 0 { &[][..] } else { unsafe { std::slice::from_raw_parts(input, input_len) } };
+    let output =
+        if output_len == 0 { &mut [][..] } else { unsafe { std::slice::from_raw_parts_mut(output, output_len) } };
+
+    let params = AecParams::new(bits_per_sample, block_size, rsi, AecFlags::from_bits_truncate(flags));
+    match crate::decode_into(input, params, output_samples, output) {
+        Ok(()) => RUST_AEC_OK,
+        Err(e) => error_code(&e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_through_the_c_abi() {
+        // One uncompressed block of 8-bit samples 1..=8 (id 7, 3 bits).
+        let payload = [0xe0, 0x20, 0x40, 0x60, 0x80, 0xa0, 0xc0, 0xe1, 0x00];
+        let mut out = [0u8; 8];
+        let rc = unsafe { rust_aec_decode(payload.as_ptr(), payload.len(), 8, 8, 1, 0, out.as_mut_ptr(), 8, 8) };
+        assert_eq!(rc, RUST_AEC_OK);
+        assert_eq!(out, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let rc = unsafe { rust_aec_decode(payload.as_ptr(), 2, 8, 8, 1, 0, out.as_mut_ptr(), 8, 8) };
+        assert_eq!(rc, RUST_AEC_STREAM_ERROR);
+    }
+}
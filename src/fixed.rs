@@ -0,0 +1,41 @@
+//! Decoding with the block size and sample width fixed at compile time.
+//!
+//! Embedded deployments that only ever decode one known format can call [`decode_into_fixed`]
+//! with that format's constants: the decode loop is then monomorphized for them, letting the
+//! compiler fold the per-sample width and per-block size logic. A constant of 0 leaves that
+//! dimension dynamic.
+//!
+//! ```
+//! use rust_aec::fixed::decode_into_fixed;
+//! use rust_aec::{AecFlags, AecParams};
+//!
+//! let params = AecParams::new(12, 32, 128, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+//! let mut out = [0u8; 0];
+//! decode_into_fixed::<32, 2>(&[], params, 0, &mut out)?;
+//! # Ok::<(), rust_aec::AecError>(())
+//! ```
+
+use crate::decoder::{decode_into_ctx_fixed, DecodeContext};
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+use crate::params::AecParams;
+
+/// Like [`crate::decode_into`], specialized for `BLOCK_SIZE` and `BYTES_PER_SAMPLE`.
+///
+/// Fails with [`AecError::Unsupported`] if `params` does not match a non-zero constant.
+pub fn decode_into_fixed<const BLOCK_SIZE: u32, const BYTES_PER_SAMPLE: usize>(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    output: &mut [u8],
+) -> Result<(), AecError> {
+    let ctx = DecodeContext::new(params, &DecodeOptions::default())?;
+    if BLOCK_SIZE != 0 && params.block_size != BLOCK_SIZE {
+        return Err(AecError::Unsupported("block_size differs from the compile-time block size"));
+    }
+    if BYTES_PER_SAMPLE != 0 && ctx.bytes_per_sample != BYTES_PER_SAMPLE {
+        return Err(AecError::Unsupported("bytes per sample differ from the compile-time sample width"));
+    }
+    decode_into_ctx_fixed::<BLOCK_SIZE, BYTES_PER_SAMPLE>(&ctx, input, output_samples, output, &mut Vec::new())?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Block, RSI and segment arithmetic.
+//!
+//! A stream is a sequence of blocks of `block_size` samples; every `rsi` blocks form a reference
+//! sample interval (RSI), and within an RSI, blocks are grouped into segments of
+//! [`SEGMENT_BLOCKS`] for the zero-block "remainder of segment" code. Indices are zero-based and
+//! counted from the start of the stream unless named `*_within_rsi`.
+//!
+//! A zero `block_size` or `rsi` is treated as 1, so these helpers never panic; such parameters
+//! are rejected by the decoders anyway.
+//!
+//! ```
+//! use rust_aec::geometry::{blocks_to_segment_end, first_sample_of_rsi, rsi_of_sample, samples_per_rsi};
+//! use rust_aec::{AecFlags, AecParams};
+//!
+//! let params = AecParams::new(12, 32, 128, AecFlags::DATA_PREPROCESS);
+//! assert_eq!(samples_per_rsi(params), 4096);
+//! assert_eq!(rsi_of_sample(params, 5000), 1);
+//! assert_eq!(first_sample_of_rsi(params, 1), 4096);
+//! assert_eq!(blocks_to_segment_end(params, 70), 58);
+//! ```
+
+use crate::params::AecParams;
+
+/// Blocks per segment, the unit of the zero-block "remainder of segment" code.
+pub const SEGMENT_BLOCKS: u32 = 64;
+
+fn block_size(params: AecParams) -> usize {
+    (params.block_size as usize).max(1)
+}
+
+fn rsi(params: AecParams) -> usize {
+    (params.rsi as usize).max(1)
+}
+
+/// Samples per full RSI (`rsi * block_size`), saturating.
+pub fn samples_per_rsi(params: AecParams) -> usize {
+    rsi(params).saturating_mul(block_size(params))
+}
+
+/// Number of RSIs, the last possibly partial, covering `output_samples` samples.
+pub fn rsi_count(params: AecParams, output_samples: usize) -> usize {
+    output_samples.div_ceil(samples_per_rsi(params))
+}
+
+/// Block containing sample `sample`.
+pub fn block_of_sample(params: AecParams, sample: usize) -> usize {
+    sample / block_size(params)
+}
+
+/// Index of the first sample of block `block`, saturating.
+pub fn first_sample_of_block(params: AecParams, block: usize) -> usize {
+    block.saturating_mul(block_size(params))
+}
+
+/// RSI containing sample `sample`.
+pub fn rsi_of_sample(params: AecParams, sample: usize) -> usize {
+    sample / samples_per_rsi(params)
+}
+
+/// Index of the first sample of RSI `n`, saturating.
+pub fn first_sample_of_rsi(params: AecParams, n: usize) -> usize {
+    n.saturating_mul(samples_per_rsi(params))
+}
+
+/// RSI containing block `block`.
+pub fn rsi_of_block(params: AecParams, block: usize) -> usize {
+    block / rsi(params)
+}
+
+/// Index of the first block of RSI `n`, saturating.
+pub fn first_block_of_rsi(params: AecParams, n: usize) -> usize {
+    n.saturating_mul(rsi(params))
+}
+
+/// Position of block `block` within its RSI.
+pub fn block_within_rsi(params: AecParams, block: usize) -> u32 {
+    (block % rsi(params)) as u32
+}
+
+/// Blocks from `block_index_within_rsi` (inclusive) to the end of its segment, cut short by the
+/// end of the RSI: the length of a zero-block run coded as "remainder of segment".
+pub fn blocks_to_segment_end(params: AecParams, block_index_within_rsi: u32) -> u32 {
+    let b = block_index_within_rsi;
+    params.rsi.saturating_sub(b).min(SEGMENT_BLOCKS - b % SEGMENT_BLOCKS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::AecFlags;
+
+    #[test]
+    fn conversions_round_trip() {
+        let params = AecParams::new(16, 16, 3, AecFlags::empty());
+        assert_eq!(samples_per_rsi(params), 48);
+        assert_eq!(rsi_count(params, 0), 0);
+        assert_eq!(rsi_count(params, 97), 3);
+        for sample in [0usize, 15, 16, 47, 48, 1000] {
+            let block = block_of_sample(params, sample);
+            assert!((first_sample_of_block(params, block)..first_sample_of_block(params, block + 1)).contains(&sample));
+            assert_eq!(rsi_of_block(params, block), rsi_of_sample(params, sample));
+            let n = rsi_of_sample(params, sample);
+            assert_eq!(first_block_of_rsi(params, n) + block_within_rsi(params, block) as usize, block);
+            assert_eq!(first_sample_of_rsi(params, n), first_sample_of_block(params, first_block_of_rsi(params, n)));
+        }
+    }
+
+    #[test]
+    fn segment_remainder_is_bounded_by_the_rsi() {
+        let params = AecParams::new(8, 8, 100, AecFlags::empty());
+        assert_eq!(blocks_to_segment_end(params, 0), 64);
+        assert_eq!(blocks_to_segment_end(params, 63), 1);
+        assert_eq!(blocks_to_segment_end(params, 64), 36);
+        assert_eq!(blocks_to_segment_end(params, 99), 1);
+
+        let degenerate = AecParams::new(8, 0, 0, AecFlags::empty());
+        assert_eq!((samples_per_rsi(degenerate), blocks_to_segment_end(degenerate, 0)), (1, 0));
+    }
+}
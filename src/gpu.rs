@@ -0,0 +1,116 @@
+//! Decoding into mapped `wgpu` staging buffers (feature `wgpu`).
+//!
+//! A mapped range (`wgpu::BufferViewMut`) dereferences to `&mut [u8]`, so decoded imagery can
+//! be written straight into a staging buffer and uploaded without a CPU-side copy. The functions
+//! here add what plain [`crate::decode_into`] leaves to the caller:
+//!
+//! - buffer sizes and copy ranges must be multiples of [`wgpu::COPY_BUFFER_ALIGNMENT`], so the
+//!   bytes between the end of the decoded data and the next aligned offset are zeroed;
+//! - mapped memory is often write-combined, so output is written strictly front to back and
+//!   never read back;
+//! - large grids can be decoded in chunks, one mapped range at a time, with samples allowed to
+//!   straddle chunk boundaries.
+//!
+//! WebGPU buffer contents are little-endian on every host, so shaders reading `u32` words expect
+//! [`crate::ByteOrder::LittleEndian`] output (`MSB` clear), not the host's native order.
+
+use wgpu::{BufferAddress, COPY_BUFFER_ALIGNMENT};
+
+use crate::decoder::{output_len, DecodeStatus, Decoder, Flush};
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+use crate::params::AecParams;
+
+/// Staging buffer size for `output_samples` decoded samples, rounded up to
+/// [`wgpu::COPY_BUFFER_ALIGNMENT`].
+pub fn staging_buffer_size(params: AecParams, output_samples: usize) -> Result<BufferAddress, AecError> {
+    let bytes = output_len(params, output_samples, &DecodeOptions::default())?;
+    Ok(align(bytes as BufferAddress))
+}
+
+/// Decode a whole payload into a mapped staging range of at least
+/// [`staging_buffer_size`] bytes; returns the number of decoded bytes.
+///
+/// The alignment padding after the decoded bytes is zeroed; the rest of `view` is left untouched.
+pub fn decode_into_staging(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    view: &mut [u8],
+) -> Result<usize, AecError> {
+    let len = output_len(params, output_samples, &DecodeOptions::default())?;
+    let padded = align(len as BufferAddress) as usize;
+    let view = view.get_mut(..padded).ok_or(AecError::InvalidInput("staging buffer too small"))?;
+    crate::decode_into(input, params, output_samples, &mut view[..len])?;
+    view[len..].fill(0);
+    Ok(len)
+}
+
+/// Decode the next chunk of output from `decoder` into a mapped staging range; returns
+/// (written_bytes, status) like [`Decoder::decode`].
+///
+/// Every chunk but the last must be fully written before the next one is mapped, so `view`
+/// must be a multiple of [`wgpu::COPY_BUFFER_ALIGNMENT`] long; a sample that does not fit is
+/// continued in the next chunk. Once the status is [`DecodeStatus::Finished`], the bytes up to
+/// the next aligned offset are zeroed (they are not counted in `written_bytes`).
+pub fn decode_staging_chunk(
+    decoder: &mut Decoder,
+    view: &mut [u8],
+    flush: Flush,
+) -> Result<(usize, DecodeStatus), AecError> {
+    if view.len() as BufferAddress % COPY_BUFFER_ALIGNMENT != 0 {
+        return Err(AecError::InvalidInput("staging chunk is not a multiple of COPY_BUFFER_ALIGNMENT"));
+    }
+    let (written, status) = decoder.decode(view, flush)?;
+    if status == DecodeStatus::Finished {
+        let end = (align(written as BufferAddress) as usize).min(view.len());
+        view[written..end].fill(0);
+    }
+    Ok((written, status))
+}
+
+fn align(n: BufferAddress) -> BufferAddress {
+    n.next_multiple_of(COPY_BUFFER_ALIGNMENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::AecFlags;
+
+    // One uncompressed block (id 7) of 8-bit samples 1..=8, then padding.
+    const PAYLOAD: [u8; 9] = [0xe0, 0x20, 0x40, 0x60, 0x80, 0xa0, 0xc0, 0xe1, 0x00];
+
+    #[test]
+    fn whole_payload_is_padded_to_copy_alignment() {
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+        assert_eq!(staging_buffer_size(params, 5).unwrap(), 8);
+
+        let mut view = [0xffu8; 12];
+        assert_eq!(decode_into_staging(&PAYLOAD, params, 5, &mut view).unwrap(), 5);
+        assert_eq!(view, [1, 2, 3, 4, 5, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        assert!(decode_into_staging(&PAYLOAD, params, 5, &mut view[..7]).is_err());
+    }
+
+    #[test]
+    fn chunks_split_samples_and_pad_the_last_one() {
+        let params = AecParams::new(12, 8, 4, AecFlags::MSB);
+        let payload = [0xf0, 0x01, 0x00, 0x20, 0x03, 0x00, 0x40, 0x05, 0x00, 0x60, 0x07, 0x00, 0x80];
+        let expected = crate::decode(&payload, params, 7).unwrap();
+
+        let mut dec = Decoder::new(params, 7).unwrap();
+        dec.push_input(&payload);
+        let mut out = Vec::new();
+        let mut chunk = [0xffu8; 4];
+        assert!(decode_staging_chunk(&mut dec, &mut chunk[..3], Flush::Flush).is_err());
+        loop {
+            let (n, status) = decode_staging_chunk(&mut dec, &mut chunk, Flush::Flush).unwrap();
+            out.extend_from_slice(&chunk[..n]);
+            if status == DecodeStatus::Finished {
+                assert_eq!(chunk[n..], [0, 0][..]);
+                break;
+            }
+        }
+        assert_eq!(out, expected);
+    }
+}
@@ -0,0 +1,1368 @@
+//! GRIB2 Data Representation Template 5.42 (CCSDS) helpers (feature `grib2`).
+
+use std::ops::{Range, RangeInclusive};
+
+use crate::bitmap::Bitmap;
+use crate::decoder::Decoder;
+use crate::error::AecError;
+use crate::index::RsiIndex;
+use crate::options::DecodeOptions;
+use crate::params::AecParams;
+
+// The flag conversions live with `AecFlags` so builds without `grib2` keep them.
+pub use crate::params::{flags_from_grib2_ccsds_flags, flags_to_grib2_ccsds_flags};
+
+/// Build [`AecParams`] from the template 5.42 octets: bits per value, `ccsdsFlags`,
+/// `ccsdsBlockSize` and `ccsdsRsi`.
+pub fn params_from_template_5_42(bits_per_sample: u8, ccsds_flags: u8, block_size: u8, rsi: u16) -> AecParams {
+    AecParams::new(bits_per_sample, block_size as u32, rsi as u32, flags_from_grib2_ccsds_flags(ccsds_flags))
+}
+
+/// GRIB2 simple-packing scaling (template 5.42 octets 12-19): `Y = (R + X * 2^E) / 10^D`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grib2Scaling {
+    /// Reference value `R`.
+    pub reference_value: f32,
+    /// Binary scale factor `E`.
+    pub binary_scale_factor: i16,
+    /// Decimal scale factor `D`.
+    pub decimal_scale_factor: i16,
+}
+
+impl Grib2Scaling {
+    /// Scale one decoded value.
+    pub fn apply(&self, x: i64) -> f32 {
+        self.apply_f64(x) as f32
+    }
+
+    /// Like [`Self::apply`], without rounding the result to `f32`.
+    pub fn apply_f64(&self, x: i64) -> f64 {
+        let (offset, factor) = self.coefficients();
+        offset + x as f64 * factor
+    }
+
+    /// Packed values whose scaled value lies in `min..=max`, for
+    /// [`crate::DecodeOptions::expected_range`].
+    pub fn packed_range(&self, min: f64, max: f64) -> RangeInclusive<i64> {
+        // `X = (Y * 10^D - R) / 2^E`; scaling by `10^D` first keeps round bounds exact.
+        let decimal = 10f64.powi(self.decimal_scale_factor as i32);
+        let binary = 2f64.powi(self.binary_scale_factor as i32);
+        let packed = |y: f64| (y * decimal - self.reference_value as f64) / binary;
+        // Float-to-int casts saturate, so ranges beyond `i64` stay well-formed.
+        packed(min).ceil() as i64..=packed(max).floor() as i64
+    }
+
+    /// `(R / 10^D, 2^E / 10^D)`, so that `Y = offset + X * factor`.
+    pub(crate) fn coefficients(&self) -> (f64, f64) {
+        let decimal = 10f64.powi(-(self.decimal_scale_factor as i32));
+        let binary = 2f64.powi(self.binary_scale_factor as i32);
+        (self.reference_value as f64 * decimal, binary * decimal)
+    }
+}
+
+/// Section 5 (Data Representation Section) content of a template 5.42 field, as produced by
+/// [`pack_field`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Section5Params {
+    /// Number of packed values (octets 6-9).
+    pub num_values: usize,
+    /// Reference value and scale factors (octets 12-19).
+    pub scaling: Grib2Scaling,
+    /// Bits per value (octet 20); 0 for a constant field.
+    pub bits_per_value: u8,
+    /// `ccsdsFlags` (octet 22).
+    pub ccsds_flags: u8,
+    /// `ccsdsBlockSize` (octet 23).
+    pub block_size: u8,
+    /// `ccsdsRsi` (octets 24-25).
+    pub rsi: u16,
+}
+
+impl Section5Params {
+    /// Parse a complete template 5.42 Section 5, starting at its length octets; the inverse of
+    /// [`Self::to_bytes`].
+    ///
+    /// ```
+    /// use rust_aec::grib2::Section5Params;
+    ///
+    /// let section5 = [
+    ///     0, 0, 0, 25, 5, // length, section number
+    ///     0, 0, 0x10, 0, // 4096 values
+    ///     0, 42, // template 5.42
+    ///     0x43, 0x88, 0x80, 0, // reference value 273.0
+    ///     0x80, 3, 0, 1, // E = -3, D = 1
+    ///     16, 0, 0x0e, 32, 0, 128, // bits per value, type, ccsdsFlags, block size, RSI
+    /// ];
+    /// let s5 = Section5Params::parse(&section5)?;
+    /// assert_eq!((s5.num_values, s5.bits_per_value, s5.rsi), (4096, 16, 128));
+    /// assert_eq!((s5.scaling.reference_value, s5.scaling.binary_scale_factor), (273.0, -3));
+    /// assert_eq!(s5.to_bytes()?, section5);
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn parse(section5: &[u8]) -> Result<Self, AecError> {
+        let s = section5;
+        if s.len() < 11 {
+            return Err(AecError::InvalidInput("GRIB2 Section 5 is truncated"));
+        }
+        if s[4] != 5 {
+            return Err(AecError::InvalidInput("not a GRIB2 Section 5"));
+        }
+        if be(&s[9..11]) != 42 {
+            return Err(AecError::InvalidInput("GRIB2 Section 5 is not template 5.42"));
+        }
+        let t = s.get(11..25).ok_or(AecError::InvalidInput("GRIB2 template 5.42 is truncated"))?;
+        Ok(Self {
+            num_values: be(&s[5..9]) as usize,
+            scaling: Grib2Scaling {
+                reference_value: f32::from_be_bytes([t[0], t[1], t[2], t[3]]),
+                binary_scale_factor: sign_magnitude(&t[4..6]),
+                decimal_scale_factor: sign_magnitude(&t[6..8]),
+            },
+            bits_per_value: t[8],
+            ccsds_flags: t[10],
+            block_size: t[11],
+            rsi: be(&t[12..14]) as u16,
+        })
+    }
+
+    /// AEC parameters for decoding Section 7.
+    pub fn params(&self) -> AecParams {
+        params_from_template_5_42(self.bits_per_value, self.ccsds_flags, self.block_size, self.rsi)
+    }
+
+    /// Decode the Section 7 `payload` (without its five header octets) into physical values.
+    ///
+    /// This is the whole template 5.42 unpacking step, for GRIB2 readers that parse Section 5
+    /// themselves and only lack an AEC decoder: an implementation of gribberish's data
+    /// representation template, for one, can parse its Section 5 bytes with [`Self::parse`] and
+    /// return this.
+    ///
+    /// ```
+    /// use rust_aec::grib2::{pack_field, PackOptions, Section5Params};
+    ///
+    /// let values: Vec<f32> = (0..500).map(|i| 250.0 + (i % 40) as f32 * 0.5).collect();
+    /// let (section5, payload) = pack_field(&values, &PackOptions { decimal_scale_factor: 1, ..Default::default() })?;
+    /// let unpacked = Section5Params::parse(&section5.to_bytes()?)?.unpack(&payload)?;
+    /// assert!(unpacked.iter().zip(&values).all(|(u, &v)| (u - f64::from(v)).abs() < 0.05));
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn unpack(&self, payload: &[u8]) -> Result<Vec<f64>, AecError> {
+        decode_grib2_f64(payload, self.params(), self.scaling, self.num_values)
+    }
+
+    /// The complete 25-octet Section 5, including its length and section number. Original values
+    /// are declared floating point (octet 21).
+    pub fn to_bytes(&self) -> Result<[u8; 25], AecError> {
+        let num_values =
+            u32::try_from(self.num_values).map_err(|_| AecError::InvalidInput("GRIB2 field has too many values"))?;
+        let mut s = [0u8; 25];
+        s[..4].copy_from_slice(&25u32.to_be_bytes());
+        s[4] = 5;
+        s[5..9].copy_from_slice(&num_values.to_be_bytes());
+        s[9..11].copy_from_slice(&42u16.to_be_bytes());
+        s[11..15].copy_from_slice(&self.scaling.reference_value.to_be_bytes());
+        s[15..17].copy_from_slice(&to_sign_magnitude(self.scaling.binary_scale_factor));
+        s[17..19].copy_from_slice(&to_sign_magnitude(self.scaling.decimal_scale_factor));
+        s[19] = self.bits_per_value;
+        s[20] = 0;
+        s[21] = self.ccsds_flags;
+        s[22] = self.block_size;
+        s[23..25].copy_from_slice(&self.rsi.to_be_bytes());
+        Ok(s)
+    }
+}
+
+/// Template 5.42 as a data representation object, for GRIB2 readers that parse Section 5 into a
+/// per-template value and hand it the Section 7 payload.
+///
+/// The methods follow gribberish's `DataRepresentationTemplate` (`compression_type`,
+/// `bit_count`, `unpack`), so a reader whose template 5.42 currently errors or shells out to
+/// eccodes can wrap this type and forward each method:
+///
+/// ```text
+/// impl DataRepresentationTemplate<f64> for Ccsds {
+///     fn compression_type(&self) -> String { self.0.compression_type().into() }
+///     fn bit_count(&self) -> usize { self.0.bit_count() }
+///     fn unpack(&self, bits: Vec<u8>) -> Result<Vec<f64>, GribberishError> {
+///         self.0.unpack(&bits).map_err(|e| GribberishError::MessageError(e.to_string()))
+///     }
+/// }
+/// ```
+///
+/// ```
+/// use rust_aec::grib2::{pack_field, CcsdsTemplate, PackOptions};
+///
+/// let values: Vec<f32> = (0..500).map(|i| 250.0 + (i % 40) as f32 * 0.5).collect();
+/// let (section5, payload) = pack_field(&values, &PackOptions { decimal_scale_factor: 1, ..Default::default() })?;
+/// let template = CcsdsTemplate::from_section5(&section5.to_bytes()?)?;
+/// assert_eq!((template.compression_type(), template.bit_count()), ("CCSDS", 8));
+/// assert_eq!(template.unpack(&payload)?.len(), values.len());
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CcsdsTemplate(pub Section5Params);
+
+impl CcsdsTemplate {
+    /// Parse a complete template 5.42 Section 5, like [`Section5Params::parse`].
+    pub fn from_section5(section5: &[u8]) -> Result<Self, AecError> {
+        Section5Params::parse(section5).map(Self)
+    }
+
+    /// Name of the compression, `"CCSDS"`.
+    pub fn compression_type(&self) -> &'static str {
+        "CCSDS"
+    }
+
+    /// Bits per packed value.
+    pub fn bit_count(&self) -> usize {
+        self.0.bits_per_value as usize
+    }
+
+    /// Number of values the payload holds.
+    pub fn num_values(&self) -> usize {
+        self.0.num_values
+    }
+
+    /// Decode the Section 7 payload (without its five header octets) into physical values; see
+    /// [`Section5Params::unpack`].
+    pub fn unpack(&self, payload: &[u8]) -> Result<Vec<f64>, AecError> {
+        self.0.unpack(payload)
+    }
+}
+
+/// Choices for [`pack_field`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackOptions {
+    /// Decimal scale factor `D`: values are kept to a precision of `10^-D`.
+    pub decimal_scale_factor: i16,
+    /// Bits per value. `None` uses as many as the decimal precision needs (at most 32); a fixed
+    /// width is reached with the binary scale factor instead.
+    pub bits_per_value: Option<u8>,
+    /// `ccsdsFlags`; the default 0x0e is `DATA_PREPROCESS | MSB | DATA_3BYTE`.
+    pub ccsds_flags: u8,
+    /// `ccsdsBlockSize` (octet 23).
+    pub block_size: u8,
+    /// `ccsdsRsi` (octets 24-25).
+    pub rsi: u16,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        Self { decimal_scale_factor: 0, bits_per_value: None, ccsds_flags: 0x0e, block_size: 32, rsi: 128 }
+    }
+}
+
+/// Pack a field into GRIB2 template 5.42: choose the reference value and scale factors, quantize
+/// to `bits_per_value` bits and AEC-encode.
+///
+/// Returns the Section 5 content and the Section 7 payload. A constant field packs into zero
+/// bits per value and an empty payload, as GRIB2 specifies; readers take every value to be the
+/// reference value.
+///
+/// ```
+/// use rust_aec::decode;
+/// use rust_aec::grib2::{pack_field, PackOptions};
+///
+/// let values: Vec<f32> = (0..1000).map(|i| 273.15 + (i as f32 / 50.0).sin() * 20.0).collect();
+/// let options = PackOptions { decimal_scale_factor: 2, ..Default::default() };
+/// let (section5, payload) = pack_field(&values, &options)?;
+///
+/// // A 40 K range at 0.01 K precision needs 12 bits, packed in 2-byte samples.
+/// assert_eq!(section5.bits_per_value, 12);
+/// let packed = decode(&payload, section5.params(), section5.num_values)?;
+/// let first = u16::from_be_bytes([packed[0], packed[1]]);
+/// assert!((section5.scaling.apply(first as i64) - values[0]).abs() <= 0.005);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn pack_field(values: &[f32], options: &PackOptions) -> Result<(Section5Params, Vec<u8>), AecError> {
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err(AecError::InvalidInput("field contains NaN or infinite values"));
+    }
+    if options.ccsds_flags & 1 != 0 {
+        return Err(AecError::InvalidInput("packed values are unsigned; clear ccsdsFlags bit 0"));
+    }
+    let decimal = 10f64.powi(options.decimal_scale_factor as i32);
+    let scaled = || values.iter().map(|&v| v as f64 * decimal);
+    let min = scaled().fold(f64::INFINITY, f64::min);
+    let max = scaled().fold(f64::NEG_INFINITY, f64::max);
+
+    // `R` is stored as `f32`; round it down so no value packs below zero.
+    let mut reference_value = if values.is_empty() { 0.0 } else { min as f32 };
+    if reference_value as f64 > min {
+        // Next `f32` towards negative infinity (`f32::next_down` needs Rust 1.86).
+        let bits = reference_value.to_bits();
+        reference_value = f32::from_bits(match reference_value {
+            0.0 => 0x8000_0001,
+            r if r > 0.0 => bits - 1,
+            _ => bits + 1,
+        });
+    }
+    let range = if values.is_empty() { 0.0 } else { max - reference_value as f64 };
+
+    let mut section5 = Section5Params {
+        num_values: values.len(),
+        scaling: Grib2Scaling {
+            reference_value,
+            binary_scale_factor: 0,
+            decimal_scale_factor: options.decimal_scale_factor,
+        },
+        bits_per_value: 0,
+        ccsds_flags: options.ccsds_flags,
+        block_size: options.block_size,
+        rsi: options.rsi,
+    };
+    if range == 0.0 || (range.round() == 0.0 && options.bits_per_value.is_none()) {
+        return Ok((section5, Vec::new()));
+    }
+
+    // Smallest binary scale that fits the range into the chosen width.
+    let needed = (range.round() + 1.0).log2().ceil().max(1.0);
+    let bits = options.bits_per_value.unwrap_or(needed.min(32.0) as u8);
+    if !(1..=32).contains(&bits) {
+        return Err(AecError::InvalidInput("bits_per_value must be 1..=32"));
+    }
+    let max_packed = ((1u64 << bits) - 1) as f64;
+    let mut e = (range / max_packed).log2().ceil() as i32;
+    while (range / 2f64.powi(e)).round() > max_packed {
+        e += 1;
+    }
+    while e > i16::MIN as i32 && (range / 2f64.powi(e - 1)).round() <= max_packed {
+        e -= 1;
+    }
+    let e = if options.bits_per_value.is_none() { e.max(0) } else { e };
+    let binary_scale_factor = i16::try_from(e).map_err(|_| AecError::InvalidInput("binary scale factor out of range"))?;
+    section5.scaling.binary_scale_factor = binary_scale_factor;
+    section5.bits_per_value = bits;
+
+    let params = section5.params();
+    let width = crate::decoder::bytes_per_sample(params)?;
+    let binary = 2f64.powi(e);
+    let mut samples = vec![0u8; values.len() * width];
+    for (v, out) in scaled().zip(samples.chunks_exact_mut(width)) {
+        let x = ((v - reference_value as f64) / binary).round().clamp(0.0, max_packed) as u64;
+        params.byte_order().put(x, out);
+    }
+    let payload = crate::encode(&samples, params)?;
+    Ok((section5, payload))
+}
+
+/// The complete Section 7 (Data Section) around an AEC `payload`: its length octets, section
+/// number 7 and the payload.
+pub fn section7_bytes(payload: &[u8]) -> Result<Vec<u8>, AecError> {
+    let len = payload
+        .len()
+        .checked_add(5)
+        .and_then(|len| u32::try_from(len).ok())
+        .ok_or(AecError::InvalidInput("GRIB2 Section 7 too large"))?;
+    let mut section = Vec::with_capacity(len as usize);
+    section.extend_from_slice(&len.to_be_bytes());
+    section.push(7);
+    section.extend_from_slice(payload);
+    Ok(section)
+}
+
+/// Like [`pack_field`], returning the complete Sections 5 and 7 as they go into a message.
+///
+/// A GRIB2 producer writes Sections 0 to 4 (and 6, if the field has a bitmap) itself and
+/// appends these two as they are.
+///
+/// ```
+/// use rust_aec::grib2::{decode_grib2_field, pack_sections, PackOptions};
+///
+/// let values: Vec<f32> = (0..500).map(|i| 1000.0 + (i % 25) as f32).collect();
+/// let (section5, section7) = pack_sections(&values, &PackOptions::default())?;
+/// assert_eq!((section5[4], &section5[9..11], section7[4]), (5, &[0, 42][..], 7));
+///
+/// let body = [&section5[..], &section7, b"7777"].concat();
+/// let message = [&b"GRIB\0\0\0\x02"[..], &(16 + body.len() as u64).to_be_bytes(), &body].concat();
+/// assert_eq!(decode_grib2_field(&message)?, values);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn pack_sections(values: &[f32], options: &PackOptions) -> Result<([u8; 25], Vec<u8>), AecError> {
+    let (section5, payload) = pack_field(values, options)?;
+    Ok((section5.to_bytes()?, section7_bytes(&payload)?))
+}
+
+/// Check that a field's Sections 3, 5 and 7 agree before decoding it.
+///
+/// - `section3_npoints`: number of grid points (Section 3 octets 7-10);
+/// - `section5`: the parsed template 5.42 Section 5;
+/// - `section7_len`: Section 7 length (its octets 1-4, including the five header octets).
+///
+/// Catches mismatched sections, truncated messages and wrong parameters up front, each with
+/// its own [`AecError::InvalidInput`] message, instead of a decode failing midway: more coded
+/// values than grid points, invalid AEC parameters, a payload too short for the values (or for
+/// even one RSI), or longer than any encoding of them. A constant field (zero bits per value)
+/// needs no payload.
+///
+/// ```
+/// use rust_aec::grib2::{pack_field, validate, PackOptions};
+///
+/// let values: Vec<f32> = (0..4000).map(|i| 12.5 + (i % 9) as f32).collect();
+/// let (s5, payload) = pack_field(&values, &PackOptions::default())?;
+/// validate(4000, &s5, payload.len() + 5)?;
+/// assert!(validate(3999, &s5, payload.len() + 5).is_err());
+/// // Section 7 cut off after its header.
+/// assert!(validate(4000, &s5, 5).is_err());
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn validate(section3_npoints: usize, section5: &Section5Params, section7_len: usize) -> Result<(), AecError> {
+    if section5.num_values > section3_npoints {
+        return Err(AecError::InvalidInput("Section 5 declares more values than Section 3 has grid points"));
+    }
+    let payload_bits = section7_len
+        .checked_sub(5)
+        .ok_or(AecError::InvalidInput("GRIB2 Section 7 is shorter than its header"))? as u64
+        * 8;
+    let params = section5.params();
+    params.validate()?;
+    if params.bits_per_sample == 0 || section5.num_values == 0 {
+        return Ok(());
+    }
+    let one_rsi = section5.num_values.min(crate::geometry::samples_per_rsi(params));
+    if payload_bits < crate::decoder::min_payload_bits(params, one_rsi) {
+        return Err(AecError::InvalidInput("payload shorter than one RSI"));
+    }
+    if payload_bits < crate::decoder::min_payload_bits(params, section5.num_values) {
+        return Err(AecError::InvalidInput("payload too short for the number of values in Section 5"));
+    }
+    if payload_bits > params.max_encoded_size(section5.num_values) as u64 * 8 {
+        return Err(AecError::InvalidInput("payload longer than any encoding of the values in Section 5"));
+    }
+    Ok(())
+}
+
+/// Decode a template 5.42 payload into physical values, applying `scaling` to every sample.
+///
+/// A field with zero bits per value is constant: it has no payload and every value is the
+/// reference value, scaled by `10^-D`.
+///
+/// ```
+/// use rust_aec::grib2::{decode_grib2_f32, pack_field, PackOptions};
+///
+/// let values: Vec<f32> = (0..1000).map(|i| 101_325.0 - i as f32 * 3.5).collect();
+/// let options = PackOptions { decimal_scale_factor: 1, ..Default::default() };
+/// let (section5, payload) = pack_field(&values, &options)?;
+/// let decoded = decode_grib2_f32(&payload, section5.params(), section5.scaling, section5.num_values)?;
+/// assert!(decoded.iter().zip(&values).all(|(d, v)| (d - v).abs() <= 0.05));
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_grib2_f32(
+    input: &[u8],
+    params: AecParams,
+    scaling: Grib2Scaling,
+    output_samples: usize,
+) -> Result<Vec<f32>, AecError> {
+    if params.bits_per_sample == 0 {
+        return Ok(vec![scaling.apply(0); output_samples]);
+    }
+    let (offset, factor) = scaling.coefficients();
+    let scale = |x: i64| (offset + x as f64 * factor) as f32;
+    crate::decoder::decode_typed(input, params, output_samples, 32, scale, &DecodeOptions::default())
+}
+
+/// Like [`decode_grib2_f32`], into `f64` values.
+///
+/// The scaling is computed in `f64` either way; this keeps its full precision, which matters
+/// for fields whose values need more than the 24 significant bits of an `f32` (wide packed
+/// values, or large decimal scale factors).
+///
+/// ```
+/// use rust_aec::grib2::{decode_grib2_f64, Grib2Scaling};
+/// use rust_aec::{encode_samples, AecFlags, AecParams};
+///
+/// // Pressure in Pa at 0.001 Pa resolution: 100000.123 does not fit an `f32`.
+/// let scaling = Grib2Scaling { reference_value: 1e8, binary_scale_factor: 0, decimal_scale_factor: 3 };
+/// let params = AecParams::new(24, 32, 128, AecFlags::DATA_PREPROCESS);
+/// let payload = encode_samples(&[123u32, 456], params)?;
+/// let values = decode_grib2_f64(&payload, params, scaling, 2)?;
+/// assert!((values[0] - 100_000.123).abs() < 1e-9 && (values[1] - 100_000.456).abs() < 1e-9);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_grib2_f64(
+    input: &[u8],
+    params: AecParams,
+    scaling: Grib2Scaling,
+    output_samples: usize,
+) -> Result<Vec<f64>, AecError> {
+    if params.bits_per_sample == 0 {
+        return Ok(vec![scaling.apply_f64(0); output_samples]);
+    }
+    let (offset, factor) = scaling.coefficients();
+    let scale = |x: i64| offset + x as f64 * factor;
+    crate::decoder::decode_typed(input, params, output_samples, 32, scale, &DecodeOptions::default())
+}
+
+/// Like [`decode_grib2_f32`], placing the values on the full grid of `grid_points` points.
+///
+/// `bitmap` is the GRIB2 Section 6 bitmap (MSB-first, one bit per grid point), or `None` if
+/// every point has a value; the payload codes one value per set bit, in grid order. Points
+/// without a value are set to `missing`, typically `f32::NAN`. The parallel counterpart is
+/// `pipeline::decode_grid_f32` (feature `rayon`).
+///
+/// ```
+/// use rust_aec::grib2::{decode_grib2_grid_f32, pack_field, PackOptions};
+///
+/// // Land points of a 4x4 grid; the sea is masked out.
+/// let bitmap = [0b1100_1110, 0b0000_0001];
+/// let land = [12.5, 13.0, 11.5, 12.0, 14.5, 9.0];
+/// let (section5, payload) = pack_field(&land, &PackOptions { decimal_scale_factor: 1, ..Default::default() })?;
+/// let grid = decode_grib2_grid_f32(&payload, section5.params(), section5.scaling, Some(&bitmap), 16, f32::NAN)?;
+/// assert_eq!((grid[0], grid[1], grid[4], grid[15]), (12.5, 13.0, 11.5, 9.0));
+/// assert!(grid[2].is_nan() && grid[14].is_nan());
+/// assert_eq!(grid.iter().filter(|v| !v.is_nan()).count(), land.len());
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_grib2_grid_f32(
+    input: &[u8],
+    params: AecParams,
+    scaling: Grib2Scaling,
+    bitmap: Option<&[u8]>,
+    grid_points: usize,
+    missing: f32,
+) -> Result<Vec<f32>, AecError> {
+    let bitmap = bitmap.map(|b| Bitmap::new(b, grid_points)).transpose()?;
+    let values = bitmap.map_or(grid_points, |b| b.count_present());
+    let mut grid = vec![missing; grid_points];
+    let mut points: Box<dyn Iterator<Item = usize>> = match bitmap {
+        Some(b) => Box::new(b.present_indices()),
+        None => Box::new(0..grid_points),
+    };
+    if params.bits_per_sample == 0 {
+        points.for_each(|i| grid[i] = scaling.apply(0));
+        return Ok(grid);
+    }
+
+    let (offset, factor) = scaling.coefficients();
+    let mut dec = Decoder::with_options(params, values, &DecodeOptions::default())?;
+    dec.push_input(input);
+    dec.decode_values(|x| {
+        // The decoder stops after `values` samples, one per present point.
+        if let Some(i) = points.next() {
+            grid[i] = (offset + x as f64 * factor) as f32;
+        }
+    })?;
+    Ok(grid)
+}
+
+/// Decode only the window `rows` x `cols` of a field on a grid `ni` points wide, in row-major
+/// order (see [`crate::scanning`] for other scanning modes), into its physical values.
+///
+/// Decoding stops at the last point of the window, and only window points are scaled and
+/// stored, so cropping a tile from a global field costs a fraction of a full decode. With an
+/// `index` (see [`RsiIndex::build`]) decoding also starts at the RSI holding the first point
+/// instead of at the beginning of the payload. The field must not have a bitmap: window
+/// positions are grid positions.
+///
+/// ```
+/// use rust_aec::grib2::{decode_grib2_region_f32, pack_field, PackOptions};
+///
+/// // A 360 x 181 field; crop the 3 x 4 points at rows 10..13, columns 100..104.
+/// let field: Vec<f32> = (0..360 * 181).map(|i| (i % 360) as f32 + (i / 360) as f32 * 1000.0).collect();
+/// let (s5, payload) = pack_field(&field, &PackOptions::default())?;
+/// let tile = decode_grib2_region_f32(&payload, s5.params(), s5.scaling, 360, 10..13, 100..104, None)?;
+/// assert_eq!(tile.len(), 12);
+/// assert_eq!((tile[0], tile[11]), (10_100.0, 12_103.0));
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_grib2_region_f32(
+    input: &[u8],
+    params: AecParams,
+    scaling: Grib2Scaling,
+    ni: usize,
+    rows: Range<usize>,
+    cols: Range<usize>,
+    index: Option<&RsiIndex>,
+) -> Result<Vec<f32>, AecError> {
+    if cols.end > ni {
+        return Err(AecError::InvalidInput("region extends past the grid width"));
+    }
+    let area = rows.len() * cols.len();
+    if area == 0 {
+        return Ok(Vec::new());
+    }
+    if params.bits_per_sample == 0 {
+        return Ok(vec![scaling.apply(0); area]);
+    }
+    let first = rows.start * ni + cols.start;
+    let end = (rows.end - 1) * ni + cols.end;
+
+    let (mut dec, start) = match index {
+        Some(index) => {
+            let rsi = index.rsi_of_sample(first);
+            let start = index.first_sample(rsi);
+            let mut dec = Decoder::with_index(params, end - start, index, rsi)?;
+            dec.push_input(&input[index.byte_offset(rsi).unwrap_or(input.len()).min(input.len())..]);
+            (dec, start)
+        }
+        None => {
+            let mut dec = Decoder::new(params, end)?;
+            dec.push_input(input);
+            (dec, 0)
+        }
+    };
+    let (offset, factor) = scaling.coefficients();
+    let mut region = Vec::with_capacity(area);
+    let mut pos = start;
+    dec.decode_values(|x| {
+        if rows.contains(&(pos / ni)) && cols.contains(&(pos % ni)) {
+            region.push((offset + x as f64 * factor) as f32);
+        }
+        pos += 1;
+    })?;
+    Ok(region)
+}
+
+/// One CCSDS-coded (template 5.42) field found in a GRIB2 file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grib2Field<'a> {
+    /// Message number within the file, starting at 0.
+    pub message: usize,
+    /// Field number within the message, starting at 0.
+    pub field: usize,
+    /// Byte offset of the message within the file.
+    pub message_offset: usize,
+    /// Number of coded values (Section 5 octets 6-9).
+    pub num_values: usize,
+    /// AEC parameters from template 5.42.
+    pub params: AecParams,
+    /// Scaling from template 5.42.
+    pub scaling: Grib2Scaling,
+    /// Section 7 data: the AEC payload.
+    pub payload: &'a [u8],
+}
+
+impl Grib2Field<'_> {
+    /// Decode the coded values into physical values (see [`decode_grib2_f32`]).
+    pub fn decode_f32(&self) -> Result<Vec<f32>, AecError> {
+        decode_grib2_f32(self.payload, self.params, self.scaling, self.num_values)
+    }
+}
+
+/// One section of a GRIB2 message, from [`MessageReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grib2Section<'a> {
+    /// Section number: 0 for the indicator, 1 to 7, and 8 for the end section.
+    pub number: u8,
+    /// Byte offset of the section within the message.
+    pub offset: usize,
+    /// The whole section, including its length and number octets.
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Grib2Section<'a> {
+    /// Template number of a grid definition (3), product definition (4) or data representation
+    /// (5) section; `None` for other sections or a truncated one.
+    pub fn template(&self) -> Option<u16> {
+        let at = match self.number {
+            3 => 12,
+            4 => 7,
+            5 => 9,
+            _ => return None,
+        };
+        self.bytes.get(at..at + 2).map(|t| u16::from_be_bytes([t[0], t[1]]))
+    }
+
+    /// Whether this is a Section 5 of template 5.42 (CCSDS), whose Section 7 this crate decodes.
+    pub fn is_ccsds(&self) -> bool {
+        self.number == 5 && self.template() == Some(42)
+    }
+
+    /// The bitmap of a Section 6 that carries one (bitmap indicator 0): one bit per grid point,
+    /// MSB-first, as [`decode_grib2_grid_f32`] takes it.
+    pub fn bitmap(&self) -> Option<&'a [u8]> {
+        (self.number == 6 && self.bytes.get(5) == Some(&0)).then(|| &self.bytes[6..])
+    }
+
+    /// Section 7 data: the payload of the field.
+    pub fn data(&self) -> Option<&'a [u8]> {
+        (self.number == 7).then(|| &self.bytes[5..])
+    }
+}
+
+/// Walks the sections of one GRIB2 message, from Section 0 to the end section (8).
+///
+/// Messages with several fields repeat sections 2 to 7 or 3 to 7 (or 4 to 7); they come out in
+/// file order. A truncated section is an error, after which the reader stops.
+///
+/// ```
+/// use rust_aec::grib2::{decode_grib2_f32, pack_field, MessageReader, PackOptions, Section5Params};
+///
+/// # let values: Vec<f32> = (0..100).map(|i| i as f32 * 0.5).collect();
+/// # let (s5, payload) = pack_field(&values, &PackOptions { decimal_scale_factor: 1, ..Default::default() })?;
+/// # let s7 = [&((payload.len() + 5) as u32).to_be_bytes()[..], &[7], &payload].concat();
+/// # let body = [&s5.to_bytes()?[..], &s7, b"7777"].concat();
+/// # let message = [&b"GRIB\0\0\0\x02"[..], &(16 + body.len() as u64).to_be_bytes(), &body].concat();
+/// let mut section5 = None;
+/// for section in MessageReader::new(&message)? {
+///     let section = section?;
+///     if section.is_ccsds() {
+///         section5 = Some(Section5Params::parse(section.bytes)?);
+///     }
+///     if let (Some(s5), Some(data)) = (section5, section.data()) {
+///         let field = decode_grib2_f32(data, s5.params(), s5.scaling, s5.num_values)?;
+///         assert_eq!(field[3], 1.5);
+///     }
+/// }
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct MessageReader<'a> {
+    message: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> MessageReader<'a> {
+    /// Start reading the message at the beginning of `data` (its `GRIB` indicator); bytes after
+    /// the message's total length are ignored.
+    pub fn new(data: &'a [u8]) -> Result<Self, AecError> {
+        let indicator = data.get(..16).ok_or(AecError::InvalidInput("GRIB2 message is truncated"))?;
+        if &indicator[..4] != b"GRIB" {
+            return Err(AecError::InvalidInput("not a GRIB message"));
+        }
+        if indicator[7] != 2 {
+            return Err(AecError::Unsupported("GRIB edition other than 2"));
+        }
+        let total = usize::try_from(be(&indicator[8..16])).map_err(|_| AecError::InvalidInput("GRIB2 message too large"))?;
+        let message = data.get(..total).filter(|m| m.len() >= 20);
+        let message = message.ok_or(AecError::InvalidInput("GRIB2 message is truncated"))?;
+        Ok(Self { message, offset: 0, done: false })
+    }
+
+    /// The whole message, Section 0 to the end section.
+    pub fn message(&self) -> &'a [u8] {
+        self.message
+    }
+
+    fn next_section(&mut self) -> Result<Option<Grib2Section<'a>>, AecError> {
+        let (offset, msg) = (self.offset, self.message);
+        let len = match offset {
+            0 => 16,
+            _ if offset + 4 > msg.len() => return Err(AecError::InvalidInput("GRIB2 message has no end section")),
+            _ if &msg[offset..offset + 4] == b"7777" => 4,
+            _ => be(&msg[offset..offset + 4]) as usize,
+        };
+        let bytes = msg.get(offset..offset.saturating_add(len)).filter(|s| s.len() >= 4);
+        let bytes = bytes.ok_or(AecError::InvalidInput("GRIB2 section is truncated"))?;
+        let number = match (offset, len) {
+            (0, _) => 0,
+            (_, 4) if bytes == b"7777" => 8,
+            _ => *bytes.get(4).ok_or(AecError::InvalidInput("GRIB2 section is truncated"))?,
+        };
+        self.offset += len;
+        self.done = number == 8;
+        Ok(Some(Grib2Section { number, offset, bytes }))
+    }
+}
+
+impl<'a> Iterator for MessageReader<'a> {
+    type Item = Result<Grib2Section<'a>, AecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let section = self.next_section().transpose();
+        if let Some(Err(_)) = section {
+            self.done = true;
+        }
+        section
+    }
+}
+
+/// Decode the first field of a GRIB2 message into its grid values, the one-call path from a
+/// message to physical values.
+///
+/// Section 5 supplies the AEC parameters and scaling, Section 3 the number of grid points and
+/// Section 6 the bitmap, if any; points without a value are `NaN`. A Section 6 that refers to a
+/// bitmap defined earlier in the message (indicator 254) reuses it. Fields with another data
+/// representation template and predefined bitmaps are [`AecError::Unsupported`].
+///
+/// ```
+/// use rust_aec::grib2::{decode_grib2_field, pack_field, PackOptions};
+///
+/// # let values: Vec<f32> = (0..100).map(|i| i as f32 * 0.5).collect();
+/// # let (s5, payload) = pack_field(&values, &PackOptions { decimal_scale_factor: 1, ..Default::default() })?;
+/// # let s7 = [&((payload.len() + 5) as u32).to_be_bytes()[..], &[7], &payload].concat();
+/// # let body = [&s5.to_bytes()?[..], &s7, b"7777"].concat();
+/// # let message = [&b"GRIB\0\0\0\x02"[..], &(16 + body.len() as u64).to_be_bytes(), &body].concat();
+/// let grid = decode_grib2_field(&message)?;
+/// assert_eq!((grid.len(), grid[3]), (100, 1.5));
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_grib2_field(message: &[u8]) -> Result<Vec<f32>, AecError> {
+    let mut grid_points = None;
+    let mut section5 = None;
+    let mut bitmap = None;
+    let mut last_bitmap = None;
+    for section in MessageReader::new(message)? {
+        let section = section?;
+        match section.number {
+            3 => {
+                let points = section.bytes.get(6..10).ok_or(AecError::InvalidInput("GRIB2 Section 3 is truncated"))?;
+                grid_points = Some(be(points) as usize);
+            }
+            5 if section.is_ccsds() => section5 = Some(Section5Params::parse(section.bytes)?),
+            5 => return Err(AecError::Unsupported("GRIB2 field is not CCSDS-packed (template 5.42)")),
+            6 => {
+                bitmap = match section.bytes.get(5) {
+                    Some(0) => section.bitmap(),
+                    Some(254) => Some(last_bitmap.ok_or(AecError::InvalidInput("GRIB2 bitmap reused before defined"))?),
+                    Some(255) => None,
+                    Some(_) => return Err(AecError::Unsupported("predefined GRIB2 bitmap")),
+                    None => return Err(AecError::InvalidInput("GRIB2 Section 6 is truncated")),
+                };
+                last_bitmap = bitmap.or(last_bitmap);
+            }
+            7 => {
+                let s5 = section5.ok_or(AecError::InvalidInput("GRIB2 Section 7 without Section 5"))?;
+                let points = match bitmap {
+                    Some(b) => {
+                        let points = grid_points.ok_or(AecError::InvalidInput("GRIB2 bitmap without Section 3"))?;
+                        if Bitmap::new(b, points)?.count_present() != s5.num_values {
+                            return Err(AecError::InvalidInput("GRIB2 bitmap does not match the number of values"));
+                        }
+                        points
+                    }
+                    None => s5.num_values,
+                };
+                let data = section.data().unwrap_or_default();
+                return decode_grib2_grid_f32(data, s5.params(), s5.scaling, bitmap, points, f32::NAN);
+            }
+            _ => {}
+        }
+    }
+    Err(AecError::InvalidInput("GRIB2 message has no Section 7"))
+}
+
+/// One message of a GRIB2 file, from [`iter_messages`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grib2Message<'a> {
+    /// Message number within the file, starting at 0.
+    pub index: usize,
+    /// Byte offset of the message within the file.
+    pub offset: usize,
+    /// The message, Section 0 to the end section.
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Grib2Message<'a> {
+    /// The sections of the message.
+    pub fn sections(&self) -> MessageReader<'a> {
+        MessageReader { message: self.bytes, offset: 0, done: false }
+    }
+
+    /// Every template 5.42 field of the message, with its payload, parameters and scaling;
+    /// fields with other data representation templates are skipped.
+    pub fn aec_fields(&self) -> Result<Vec<Grib2Field<'a>>, AecError> {
+        let mut fields = Vec::new();
+        let mut field = 0usize;
+        let mut section5: Option<(usize, Option<(AecParams, Grib2Scaling)>)> = None;
+        for section in self.sections() {
+            let Grib2Section { number, bytes: section, .. } = section?;
+            match number {
+                5 => section5 = Some(parse_section5(section)?),
+                7 => {
+                    let (num_values, template) =
+                        section5.ok_or(AecError::InvalidInput("GRIB2 Section 7 without Section 5"))?;
+                    if let Some((params, scaling)) = template {
+                        fields.push(Grib2Field {
+                            message: self.index,
+                            field,
+                            message_offset: self.offset,
+                            num_values,
+                            params,
+                            scaling,
+                            payload: &section[5..],
+                        });
+                    }
+                    field += 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(fields)
+    }
+}
+
+/// Iterator over the messages of a GRIB2 file, from [`iter_messages`].
+#[derive(Debug, Clone)]
+pub struct Messages<'a> {
+    file: &'a [u8],
+    pos: usize,
+    index: usize,
+    done: bool,
+}
+
+/// Iterate over the messages of a (possibly multi-message) GRIB2 file, such as the output of a
+/// model run with one message per field.
+///
+/// Messages are found by their `GRIB` indicator and framed by the total length in Section 0;
+/// bytes between messages (e.g. WMO bulletin headers) are skipped. A malformed message ends
+/// the iteration with its error. `read_messages` (feature `io`) does the same for a reader.
+///
+/// ```
+/// use rust_aec::grib2::{iter_messages, pack_field, PackOptions};
+///
+/// # let message = |values: &[f32]| -> Result<Vec<u8>, rust_aec::AecError> {
+/// #     let (s5, payload) = pack_field(values, &PackOptions { decimal_scale_factor: 1, ..Default::default() })?;
+/// #     let s7 = [&((payload.len() + 5) as u32).to_be_bytes()[..], &[7], &payload].concat();
+/// #     let body = [&s5.to_bytes()?[..], &s7, b"7777"].concat();
+/// #     Ok([&b"GRIB\0\0\0\x02"[..], &(16 + body.len() as u64).to_be_bytes(), &body].concat())
+/// # };
+/// # let file = [message(&[280.5; 50])?, message(&[1013.0; 70])?].concat();
+/// for message in iter_messages(&file) {
+///     let message = message?;
+///     for field in message.aec_fields()? {
+///         let values = field.decode_f32()?;
+///         println!("message {} field {}: {} values", message.index, field.field, values.len());
+///     }
+/// }
+/// assert_eq!(iter_messages(&file).count(), 2);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn iter_messages(file: &[u8]) -> Messages<'_> {
+    Messages { file, pos: 0, index: 0, done: false }
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<Grib2Message<'a>, AecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let Some(offset) = find(&self.file[self.pos..], b"GRIB").map(|i| self.pos + i) else {
+            self.done = true;
+            return None;
+        };
+        match MessageReader::new(&self.file[offset..]) {
+            Ok(reader) => {
+                let message = Grib2Message { index: self.index, offset, bytes: reader.message() };
+                self.pos = offset + message.bytes.len();
+                self.index += 1;
+                Some(Ok(message))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Like [`iter_messages`], reading the messages from `reader` one at a time (feature `io`).
+///
+/// Every item is one whole message, to be decoded with [`decode_grib2_field`] or taken apart
+/// with [`MessageReader`]. The reader is read a byte at a time between messages, so it should
+/// be buffered (e.g. a `BufReader` around a `File`). The end of the input ends the iteration,
+/// except inside a message, which is an error.
+#[cfg(feature = "io")]
+pub fn read_messages<R: std::io::Read>(reader: R) -> ReadMessages<R> {
+    ReadMessages { reader, done: false }
+}
+
+/// Iterator over the messages read from a reader, from [`read_messages`].
+#[cfg(feature = "io")]
+#[derive(Debug)]
+pub struct ReadMessages<R> {
+    reader: R,
+    done: bool,
+}
+
+#[cfg(feature = "io")]
+impl<R: std::io::Read> ReadMessages<R> {
+    fn next_message(&mut self) -> Result<Option<Vec<u8>>, AecError> {
+        let mut indicator = [0u8; 16];
+        let mut seen = 0usize;
+        while seen < 4 || &indicator[..4] != b"GRIB" {
+            let mut byte = [0u8];
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+            indicator.copy_within(1..4, 0);
+            indicator[3] = byte[0];
+            seen += 1;
+        }
+        self.read_all(&mut indicator[4..])?;
+        let total = usize::try_from(be(&indicator[8..16]))
+            .ok()
+            .filter(|&total| total >= 20)
+            .ok_or(AecError::InvalidInput("GRIB2 message has an invalid length"))?;
+        let mut message = indicator.to_vec();
+        message.resize(total, 0);
+        self.read_all(&mut message[16..])?;
+        MessageReader::new(&message)?;
+        Ok(Some(message))
+    }
+
+    fn read_all(&mut self, buf: &mut [u8]) -> Result<(), AecError> {
+        self.reader.read_exact(buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => AecError::InvalidInput("GRIB2 message is truncated"),
+            _ => e.into(),
+        })
+    }
+}
+
+#[cfg(feature = "io")]
+impl<R: std::io::Read> Iterator for ReadMessages<R> {
+    type Item = Result<Vec<u8>, AecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let message = self.next_message().transpose();
+        if !matches!(message, Some(Ok(_))) {
+            self.done = true;
+        }
+        message
+    }
+}
+
+/// Find every template 5.42 field in a (possibly multi-message) GRIB2 file.
+///
+/// Only the section framing is parsed; fields with other data representation templates are
+/// skipped. Bytes between messages (e.g. WMO bulletin headers) are skipped as well.
+pub fn aec_fields(file: &[u8]) -> Result<Vec<Grib2Field<'_>>, AecError> {
+    let mut fields = Vec::new();
+    for message in iter_messages(file) {
+        fields.extend(message?.aec_fields()?);
+    }
+    Ok(fields)
+}
+
+/// Number of values and, for template 5.42, parameters and scaling of a Section 5.
+fn parse_section5(s: &[u8]) -> Result<(usize, Option<(AecParams, Grib2Scaling)>), AecError> {
+    if s.len() < 11 {
+        return Err(AecError::InvalidInput("GRIB2 Section 5 is truncated"));
+    }
+    let num_values = be(&s[5..9]) as usize;
+    if be(&s[9..11]) != 42 {
+        return Ok((num_values, None));
+    }
+    let section5 = Section5Params::parse(s)?;
+    Ok((num_values, Some((section5.params(), section5.scaling))))
+}
+
+fn be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u64)
+}
+
+/// GRIB2 signed integers use sign-and-magnitude, not two's complement.
+fn to_sign_magnitude(v: i16) -> [u8; 2] {
+    let magnitude = v.unsigned_abs() & 0x7fff;
+    (if v < 0 { magnitude | 0x8000 } else { magnitude }).to_be_bytes()
+}
+
+fn sign_magnitude(bytes: &[u8]) -> i16 {
+    let v = be(bytes) as u16;
+    let magnitude = (v & 0x7fff) as i16;
+    if v & 0x8000 != 0 { -magnitude } else { magnitude }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::AecFlags;
+
+    #[test]
+    fn scaling_follows_the_simple_packing_formula() {
+        let s = Grib2Scaling { reference_value: 250.0, binary_scale_factor: -2, decimal_scale_factor: 1 };
+        assert_eq!(s.apply(0), 25.0);
+        assert_eq!(s.apply(8), 25.2);
+        assert_eq!(s.packed_range(25.0, 26.0), 0..=40);
+        assert_eq!(s.packed_range(24.99, 25.03), 0..=1);
+    }
+
+    /// A GRIB2 message with one template 5.42 field and one field of another template.
+    fn message(payload: &[u8]) -> Vec<u8> {
+        let mut s5 = vec![0, 0, 0, 25, 5, 0, 0, 0, 40, 0, 42];
+        s5.extend(250f32.to_be_bytes());
+        s5.extend([0x80, 2, 0, 1, 8, 0, 0x0e, 8, 0, 2]);
+        let mut s7 = vec![0, 0, 0, 0, 7];
+        s7.extend(payload);
+        let len = s7.len() as u32;
+        s7[..4].copy_from_slice(&len.to_be_bytes());
+        let other5 = [0, 0, 0, 11, 5, 0, 0, 0, 3, 0, 0];
+        let other7 = [0, 0, 0, 6, 7, 0xaa];
+
+        let body: Vec<u8> = [&s5[..], &s7, &other5, &other7, b"7777"].concat();
+        let mut msg = b"GRIB\0\0\0\x02".to_vec();
+        msg.extend((16 + body.len() as u64).to_be_bytes());
+        msg.extend(body);
+        msg
+    }
+
+    #[test]
+    fn aec_fields_walks_messages_and_skips_other_templates() {
+        let file = [&b"TTAA00 header\r\r\n"[..], &message(&[1, 2, 3]), &message(&[4])].concat();
+        let fields = aec_fields(&file).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!((fields[0].message, fields[0].field, fields[0].num_values), (0, 0, 40));
+        assert_eq!(fields[0].payload, [1, 2, 3]);
+        assert_eq!(fields[1].message, 1);
+        assert_eq!(fields[1].payload, [4]);
+        assert_eq!(fields[0].params, params_from_template_5_42(8, 0x0e, 8, 2));
+        assert_eq!(
+            fields[0].scaling,
+            Grib2Scaling { reference_value: 250.0, binary_scale_factor: -2, decimal_scale_factor: 1 }
+        );
+
+        let truncated = message(&[1, 2, 3]);
+        assert!(aec_fields(&truncated[..truncated.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn messages_are_framed_in_slices_and_readers_alike() {
+        let (first, second) = (message(&[1, 2, 3]), message(&[4]));
+        let file = [&b"TTAA00 header\r\r\n"[..], &first, b"\r\r\n", &second, b"trailer"].concat();
+        let messages: Vec<Grib2Message<'_>> = iter_messages(&file).collect::<Result<_, _>>().unwrap();
+        assert_eq!(messages.iter().map(|m| (m.index, m.offset)).collect::<Vec<_>>(), [(0, 16), (1, 19 + first.len())]);
+        assert_eq!((messages[0].bytes, messages[1].bytes), (&first[..], &second[..]));
+        assert_eq!(messages[1].aec_fields().unwrap()[0].payload, [4]);
+        assert_eq!(messages[0].sections().count(), 6);
+
+        let truncated = &file[..file.len() - 10];
+        assert!(matches!(iter_messages(truncated).nth(1), Some(Err(_))));
+        assert_eq!(iter_messages(truncated).count(), 2);
+
+        #[cfg(feature = "io")]
+        {
+            let read: Vec<Vec<u8>> = read_messages(&file[..]).collect::<Result<_, _>>().unwrap();
+            assert_eq!(read, [first, second]);
+            let mut read = read_messages(truncated);
+            assert!(read.next().unwrap().is_ok() && read.next().unwrap().is_err() && read.next().is_none());
+        }
+    }
+
+    #[test]
+    fn message_reader_yields_every_section_in_order() {
+        let msg = message(&[1, 2, 3]);
+        let file = [&msg[..], b"trailing"].concat();
+        let sections: Vec<_> = MessageReader::new(&file).unwrap().map(Result::unwrap).collect();
+        let numbers: Vec<u8> = sections.iter().map(|s| s.number).collect();
+        assert_eq!(numbers, [0, 5, 7, 5, 7, 8]);
+        assert_eq!(sections.iter().map(|s| s.bytes.len()).sum::<usize>(), msg.len());
+        assert!(sections[1].is_ccsds() && !sections[3].is_ccsds());
+        assert_eq!((sections[3].template(), sections[2].data()), (Some(0), Some(&[1u8, 2, 3][..])));
+        assert_eq!(sections[0].data(), None);
+
+        let mut with_bitmap = msg[..16].to_vec();
+        with_bitmap.extend([0, 0, 0, 8, 6, 0, 0b1010_0000, 0xff]);
+        with_bitmap.extend_from_slice(&msg[16..]);
+        let total = with_bitmap.len() as u64;
+        with_bitmap[8..16].copy_from_slice(&total.to_be_bytes());
+        let section6 = MessageReader::new(&with_bitmap).unwrap().nth(1).unwrap().unwrap();
+        assert_eq!(section6.bitmap(), Some(&[0b1010_0000, 0xff][..]));
+
+        // A truncated message ends with an error.
+        let mut reader = MessageReader::new(&msg).unwrap();
+        reader.message = &msg[..msg.len() - 2];
+        assert!(reader.by_ref().last().unwrap().is_err());
+        assert!(reader.next().is_none());
+        assert!(MessageReader::new(&msg[..msg.len() - 1]).is_err());
+        assert!(MessageReader::new(&msg[1..]).is_err());
+    }
+
+    #[test]
+    fn whole_messages_decode_to_grids() {
+        let values: Vec<f32> = (0..12).map(|i| 280.0 + i as f32).collect();
+        let (s5, payload) = pack_field(&values, &PackOptions::default()).unwrap();
+        let section = |number: u8, content: &[u8]| {
+            [&((content.len() + 5) as u32).to_be_bytes()[..], &[number], content].concat()
+        };
+        // Section 3 declares 16 grid points; four of them are masked out.
+        let s3 = section(3, &[0, 0, 0, 0, 16, 0, 0, 0, 0]);
+        let bitmap = [0b1110_1110, 0b1110_1110];
+        let s6 = [section(6, &[&[0][..], &bitmap].concat()), section(6, &[254])];
+        let s7 = section(7, &payload);
+        let wrap = |sections: &[&[u8]]| {
+            let body = [sections.concat(), b"7777".to_vec()].concat();
+            [&b"GRIB\0\0\0\x02"[..], &(16 + body.len() as u64).to_be_bytes(), &body].concat()
+        };
+        let s5 = s5.to_bytes().unwrap();
+
+        let grid = decode_grib2_field(&wrap(&[&s3, &s5, &s6[0], &s7])).unwrap();
+        let present: Vec<f32> = grid.iter().copied().filter(|v| !v.is_nan()).collect();
+        assert_eq!((grid.len(), present), (16, values.clone()));
+        assert!(grid[3].is_nan() && grid[15].is_nan());
+        assert_eq!(decode_grib2_field(&wrap(&[&s5, &s7])).unwrap(), values);
+
+        assert!(decode_grib2_field(&wrap(&[&s3, &s5, &s6[1], &s7])).is_err());
+        assert!(decode_grib2_field(&wrap(&[&s5, &s6[0], &s7])).is_err());
+        assert!(decode_grib2_field(&wrap(&[&s3, &s5, &section(6, &[0, 0xff, 0xff]), &s7])).is_err());
+        assert!(decode_grib2_field(&wrap(&[&s3, &s5])).is_err());
+        let mut simple = s5;
+        simple[10] = 0;
+        assert!(matches!(decode_grib2_field(&wrap(&[&simple, &s7])), Err(AecError::Unsupported(_))));
+    }
+
+    #[test]
+    fn regions_match_the_cropped_full_grid() {
+        let (ni, nj) = (37, 23);
+        let field: Vec<f32> = (0..ni * nj).map(|i| ((i * 7919) % 1000) as f32 * 0.1).collect();
+        let options = PackOptions { decimal_scale_factor: 1, block_size: 8, rsi: 4, ..Default::default() };
+        let (s5, payload) = pack_field(&field, &options).unwrap();
+        let full = decode_grib2_f32(&payload, s5.params(), s5.scaling, field.len()).unwrap();
+        let index = RsiIndex::build(&payload, s5.params(), field.len()).unwrap();
+
+        for (rows, cols) in [(0..1, 0..1), (5..9, 30..37), (22..23, 0..37), (3..20, 11..12), (0..23, 0..37)] {
+            let expected: Vec<f32> =
+                rows.clone().flat_map(|r| cols.clone().map(move |c| r * ni + c)).map(|i| full[i]).collect();
+            for index in [None, Some(&index)] {
+                let region =
+                    decode_grib2_region_f32(&payload, s5.params(), s5.scaling, ni, rows.clone(), cols.clone(), index);
+                assert_eq!(region.unwrap(), expected, "{rows:?} {cols:?}");
+            }
+        }
+        let params = s5.params();
+        assert!(decode_grib2_region_f32(&payload, params, s5.scaling, ni, 0..2, 30..38, None).is_err());
+        assert!(decode_grib2_region_f32(&payload, params, s5.scaling, ni, 4..4, 0..3, None).unwrap().is_empty());
+        assert!(decode_grib2_region_f32(&payload[..40], params, s5.scaling, ni, 20..23, 0..3, None).is_err());
+    }
+
+    #[test]
+    fn validation_names_the_inconsistency() {
+        let values: Vec<f32> = (0..100_000).map(|i| (i % 1000) as f32).collect();
+        let (s5, payload) = pack_field(&values, &PackOptions::default()).unwrap();
+        let s7_len = payload.len() + 5;
+        assert!(validate(values.len(), &s5, s7_len).is_ok());
+        assert!(validate(values.len() + 10, &s5, s7_len).is_ok());
+
+        let message = |r: Result<(), AecError>| match r {
+            Err(AecError::InvalidInput(m)) => m,
+            other => panic!("{other:?}"),
+        };
+        assert!(message(validate(values.len() - 1, &s5, s7_len)).contains("grid points"));
+        assert!(message(validate(values.len(), &s5, 4)).contains("header"));
+        assert_eq!(message(validate(values.len(), &s5, 6)), "payload shorter than one RSI");
+        assert!(message(validate(values.len(), &s5, 30)).contains("too short"));
+        assert!(message(validate(values.len(), &s5, 2 * s7_len + 300_000)).contains("longer"));
+        let bad = Section5Params { block_size: 12, ..s5 };
+        assert!(matches!(validate(values.len(), &bad, s7_len), Err(AecError::Unsupported(_))));
+
+        let (constant, _) = pack_field(&[1.0; 50], &PackOptions::default()).unwrap();
+        assert!(validate(50, &constant, 5).is_ok());
+    }
+
+    #[test]
+    fn packed_sections_frame_the_payload() {
+        let values: Vec<f32> = (0..300).map(|i| (i as f32 * 0.37).sin() * 12.0).collect();
+        let options = PackOptions { decimal_scale_factor: 2, ccsds_flags: 0x2e, ..Default::default() };
+        let (s5, s7) = pack_sections(&values, &options).unwrap();
+        let (section5, payload) = pack_field(&values, &options).unwrap();
+        assert_eq!(Section5Params::parse(&s5).unwrap(), section5);
+        assert_eq!((be(&s7[..4]) as usize, s7[4], &s7[5..]), (s7.len(), 7, &payload[..]));
+
+        let (_, constant) = pack_sections(&[3.5; 10], &options).unwrap();
+        assert_eq!(constant, [0, 0, 0, 5, 7]);
+    }
+
+    #[test]
+    fn packed_fields_read_back_through_section5_and_decode() {
+        let values: Vec<f32> = (0..5000).map(|i| -40.0 + (i as f32 / 300.0).cos() * 35.5 + (i % 7) as f32 * 0.01).collect();
+        for options in [
+            PackOptions { decimal_scale_factor: 2, ..Default::default() },
+            PackOptions { decimal_scale_factor: 1, bits_per_value: Some(12), ..Default::default() },
+            PackOptions { bits_per_value: Some(24), ccsds_flags: 0x2e, block_size: 16, rsi: 64, ..Default::default() },
+        ] {
+            let (section5, payload) = pack_field(&values, &options).unwrap();
+            let section5_bytes = section5.to_bytes().unwrap();
+            let file = [&b"GRIB\0\0\0\x02"[..], &[0; 8], &section5_bytes, &[0, 0, 0, 0, 7], &payload].concat();
+            let (num_values, template) = parse_section5(&file[16..41]).unwrap();
+            assert_eq!(template, Some((section5.params(), section5.scaling)));
+            assert_eq!(num_values, values.len());
+
+            let step = 2f64.powi(section5.scaling.binary_scale_factor as i32)
+                / 10f64.powi(section5.scaling.decimal_scale_factor as i32);
+            let width = crate::decoder::bytes_per_sample(section5.params()).unwrap();
+            let decoded = crate::decode(&payload, section5.params(), num_values).unwrap();
+            let physical = decode_grib2_f32(&payload, section5.params(), section5.scaling, num_values).unwrap();
+            let precise = decode_grib2_f64(&payload, section5.params(), section5.scaling, num_values).unwrap();
+            for (((v, x), y), z) in values.iter().zip(decoded.chunks_exact(width)).zip(physical).zip(precise) {
+                let x = section5.params().byte_order().get(x) as i64;
+                assert!((section5.scaling.apply(x) as f64 - *v as f64).abs() <= step / 2.0 + 1e-4, "{options:?}");
+                assert_eq!((y, z), (section5.scaling.apply(x), section5.scaling.apply_f64(x)));
+            }
+        }
+
+        let (constant, payload) = pack_field(&[5.0; 10], &PackOptions::default()).unwrap();
+        assert_eq!((constant.bits_per_value, constant.scaling.reference_value, payload.len()), (0, 5.0, 0));
+        assert_eq!(decode_grib2_f32(&payload, constant.params(), constant.scaling, 10).unwrap(), [5.0; 10]);
+        assert!(pack_field(&[f32::NAN], &PackOptions::default()).is_err());
+        assert_eq!(to_sign_magnitude(-2), [0x80, 2]);
+    }
+
+    #[test]
+    fn bitmapped_fields_fill_missing_points() {
+        let values: Vec<f32> = (0..40).map(|i| i as f32 * 0.5).collect();
+        let options = PackOptions { decimal_scale_factor: 1, ..Default::default() };
+        let (section5, payload) = pack_field(&values, &options).unwrap();
+        let (params, scaling) = (section5.params(), section5.scaling);
+        // Two of every three points of a 60-point grid are present; the last byte is padding.
+        let bitmap: Vec<u8> = [0b1101_1011, 0b0110_1101, 0b1011_0110].repeat(3).into_iter().take(8).collect();
+        let grid = decode_grib2_grid_f32(&payload, params, scaling, Some(&bitmap), 60, -999.0).unwrap();
+        let present: Vec<f32> = grid.iter().copied().filter(|&v| v != -999.0).collect();
+        assert_eq!((present.len(), grid[2], grid[58]), (40, -999.0, 19.5));
+        assert_eq!(present, decode_grib2_f32(&payload, params, scaling, 40).unwrap());
+
+        assert!(decode_grib2_grid_f32(&payload, params, scaling, Some(&bitmap[..7]), 60, f32::NAN).is_err());
+        assert!(decode_grib2_grid_f32(&payload[..10], params, scaling, Some(&bitmap), 60, f32::NAN).is_err());
+        let (constant, empty) = pack_field(&[2.0; 40], &PackOptions::default()).unwrap();
+        let grid = decode_grib2_grid_f32(&empty, constant.params(), constant.scaling, Some(&bitmap), 60, 0.0).unwrap();
+        assert_eq!(grid.iter().sum::<f32>(), 80.0);
+    }
+
+    #[test]
+    fn template_octets_map_to_params() {
+        let p = params_from_template_5_42(12, 0x0e, 32, 128);
+        assert_eq!(p, AecParams::new(12, 32, 128, AecFlags::DATA_3BYTE | AecFlags::MSB | AecFlags::DATA_PREPROCESS));
+    }
+
+    #[test]
+    fn section5_parses_back_from_its_bytes() {
+        let values: Vec<f32> = (0..300).map(|i| -3.25 + i as f32 * 0.125).collect();
+        let options =
+            PackOptions { decimal_scale_factor: 3, ccsds_flags: 0x2e, block_size: 16, rsi: 64, ..Default::default() };
+        let (section5, _) = pack_field(&values, &options).unwrap();
+        let bytes = section5.to_bytes().unwrap();
+        assert_eq!(Section5Params::parse(&bytes).unwrap(), section5);
+        assert_eq!(AecParams::from_grib2_drt42(&bytes).unwrap(), section5.params());
+
+        assert!(Section5Params::parse(&bytes[..24]).is_err());
+        let mut other = bytes;
+        other[10] = 0;
+        assert!(AecParams::from_grib2_drt42(&other).is_err());
+        other = bytes;
+        other[4] = 6;
+        assert!(Section5Params::parse(&other).is_err());
+    }
+
+    #[test]
+    fn ccsds_template_unpacks_like_section5() {
+        let values: Vec<f32> = (0..1000).map(|i| 1000.0 - (i % 97) as f32 * 2.5).collect();
+        let options = PackOptions { decimal_scale_factor: 1, bits_per_value: Some(16), ..Default::default() };
+        let (section5, payload) = pack_field(&values, &options).unwrap();
+        let template = CcsdsTemplate::from_section5(&section5.to_bytes().unwrap()).unwrap();
+        assert_eq!(template, CcsdsTemplate(section5));
+        assert_eq!((template.compression_type(), template.bit_count(), template.num_values()), ("CCSDS", 16, 1000));
+
+        let unpacked = template.unpack(&payload).unwrap();
+        assert_eq!(unpacked, section5.unpack(&payload).unwrap());
+        assert!(unpacked.iter().zip(&values).all(|(u, &v)| (u - f64::from(v)).abs() < 0.05));
+        assert!(template.unpack(&payload[..payload.len() / 2]).is_err());
+
+        let mut other = section5.to_bytes().unwrap();
+        other[10] = 0;
+        assert!(CcsdsTemplate::from_section5(&other).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+//! Adapter for the `grib` crate (grib-rs) (feature `grib`).
+//!
+//! grib-rs parses GRIB2 messages but decodes template 5.42 fields only through libaec, behind
+//! its `ccsds-unpack-with-libaec` feature. With that feature off, [`decode_submessage`] takes
+//! over those fields: it reads Sections 5 to 7 of a submessage from the message bytes and
+//! decodes them like [`crate::grib2::decode_grib2_field`]. Other templates are left to
+//! `grib::Grib2SubmessageDecoder` (see `examples/grib_rs_ccsds.rs`).
+
+use grib::{SectionInfo, SubMessage};
+
+use crate::error::AecError;
+use crate::grib2::{decode_grib2_grid_f32, Section5Params};
+
+/// Whether `submessage` is packed with template 5.42, so [`decode_submessage`] decodes it.
+pub fn is_ccsds<R>(submessage: &SubMessage<'_, R>) -> bool {
+    submessage.5.template_code().is_some_and(|code| code.1 == 42)
+}
+
+/// Decode a template 5.42 submessage into its grid values, `NaN` where the bitmap has no value.
+///
+/// `data` is the buffer grib-rs read the submessage from (as given to `grib::from_bytes`):
+/// grib-rs records where the sections are but does not hand out their bytes.
+pub fn decode_submessage<R>(data: &[u8], submessage: &SubMessage<'_, R>) -> Result<Vec<f32>, AecError> {
+    let s5 = Section5Params::parse(section_bytes(data, submessage.5.body, 5)?)?;
+    let s6 = section_bytes(data, submessage.6.body, 6)?;
+    let s7 = section_bytes(data, submessage.7.body, 7)?;
+    let grid_points = submessage.grid_def().num_points() as usize;
+    let bitmap = match s6.get(5) {
+        Some(0) => Some(&s6[6..]),
+        Some(255) => None,
+        Some(_) => return Err(AecError::Unsupported("GRIB2 bitmap indicator")),
+        None => return Err(AecError::InvalidInput("GRIB2 Section 6 is truncated")),
+    };
+    let payload = s7.get(5..).ok_or(AecError::InvalidInput("GRIB2 Section 7 is truncated"))?;
+    decode_grib2_grid_f32(payload, s5.params(), s5.scaling, bitmap, grid_points, f32::NAN)
+}
+
+fn section_bytes<'a>(data: &'a [u8], section: &SectionInfo, number: u8) -> Result<&'a [u8], AecError> {
+    if section.num != number {
+        return Err(AecError::InvalidInput("unexpected GRIB2 section"));
+    }
+    data.get(section.offset..)
+        .and_then(|rest| rest.get(..section.size))
+        .ok_or(AecError::InvalidInput("GRIB2 section lies outside the data"))
+}
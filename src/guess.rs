@@ -0,0 +1,146 @@
+//! Heuristics for payloads with incomplete metadata.
+//!
+//! Decoding with the wrong flags rarely fails outright; it produces noise. [`guess_flags`]
+//! decodes a prefix of the payload under every plausible flag combination and ranks the results
+//! by whether they decode without stream errors and how smooth the decoded values are, which is
+//! what real fields look like.
+
+use crate::decoder::Decoder;
+use crate::error::AecError;
+use crate::geometry;
+use crate::params::{AecFlags, AecParams};
+
+/// How well a parameter set decodes a payload prefix.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// The parameters tried.
+    pub params: AecParams,
+    /// Samples decoded before the prefix ended or an error occurred.
+    pub decoded_samples: usize,
+    /// Samples the prefix was supposed to hold.
+    pub prefix_samples: usize,
+    /// Error that stopped decoding, if any.
+    pub error: Option<AecError>,
+    /// Mean absolute difference of consecutive samples relative to the sample range `2^bits`
+    /// (0 = constant, around 1/3 = uniform noise).
+    pub roughness: f64,
+}
+
+impl Candidate {
+    /// Whether the whole prefix decoded without error.
+    pub fn is_clean(&self) -> bool {
+        self.error.is_none() && self.decoded_samples == self.prefix_samples
+    }
+
+    /// Whether decoding hit a stream error other than running out of payload. With the sample
+    /// count unknown, running out of payload is expected; anything else means wrong parameters.
+    pub fn is_invalid(&self) -> bool {
+        self.error.as_ref().is_some_and(|e| {
+            !matches!(e.untagged(), AecError::UnexpectedEof { .. } | AecError::UnexpectedEofDuringDecode { .. })
+        })
+    }
+
+    /// Order best first: valid decodes, then non-constant output (wrong parameters often decode
+    /// to long zero runs), then smoother output, then more samples decoded, then fewer flags.
+    pub fn rank(a: &Self, b: &Self) -> std::cmp::Ordering {
+        let key = |c: &Self| (c.is_invalid(), c.decoded_samples > 1 && c.roughness == 0.0);
+        let flag_count = |c: &Self| c.params.flags.bits().count_ones();
+        key(a)
+            .cmp(&key(b))
+            .then(a.roughness.total_cmp(&b.roughness))
+            .then(b.decoded_samples.cmp(&a.decoded_samples))
+            .then(flag_count(a).cmp(&flag_count(b)))
+    }
+}
+
+/// Reference sample intervals decoded per candidate.
+const PREFIX_RSIS: usize = 4;
+
+/// Decode the first RSIs (at most `output_samples` samples) of `payload` with `params` and
+/// score the result.
+pub fn score_params(payload: &[u8], params: AecParams, output_samples: usize) -> Candidate {
+    let prefix_samples = geometry::first_sample_of_rsi(params, PREFIX_RSIS).min(output_samples);
+
+    // Never buffer more than the largest valid encoding of the prefix.
+    let bits = params.bits_per_sample as usize;
+    let blocks = prefix_samples.div_ceil((params.block_size as usize).max(1));
+    let max_bits = blocks
+        .saturating_mul(5 + bits + (params.block_size as usize).saturating_mul(bits))
+        .saturating_add(8 * PREFIX_RSIS);
+    let payload = &payload[..payload.len().min(max_bits / 8 + 1)];
+
+    let mut decoded_samples = 0usize;
+    let mut total_step = 0f64;
+    let mut prev: Option<i64> = None;
+    let result = Decoder::new(params, prefix_samples).and_then(|mut dec| {
+        dec.push_input(payload);
+        dec.decode_values(|x| {
+            if let Some(p) = prev {
+                total_step += x.abs_diff(p) as f64;
+            }
+            prev = Some(x);
+            decoded_samples += 1;
+        })
+    });
+
+    let range = 2f64.powi(params.bits_per_sample as i32);
+    let roughness = if decoded_samples > 1 { total_step / (decoded_samples - 1) as f64 / range } else { 0.0 };
+    Candidate { params, decoded_samples, prefix_samples, error: result.err(), roughness }
+}
+
+/// Try the flag combinations that change how a payload decodes and rank them, best first.
+///
+/// Combinations of `DATA_PREPROCESS`, `DATA_SIGNED` and `PAD_RSI` are tried (plus `RESTRICTED`
+/// for `bits_per_sample <= 4`). `MSB` and `DATA_3BYTE` only change the output byte layout, so
+/// they cannot be inferred from the payload and are not part of the returned flags.
+pub fn guess_flags(
+    payload: &[u8],
+    bits_per_sample: u8,
+    block_size: u32,
+    rsi: u32,
+    output_samples: usize,
+) -> Vec<Candidate> {
+    let mut optional = vec![AecFlags::DATA_PREPROCESS, AecFlags::DATA_SIGNED, AecFlags::PAD_RSI];
+    if bits_per_sample <= 4 {
+        optional.push(AecFlags::RESTRICTED);
+    }
+
+    let mut candidates: Vec<Candidate> = (0..1u32 << optional.len())
+        .map(|mask| {
+            optional
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| mask & (1 << i) != 0)
+                .fold(AecFlags::empty(), |acc, (_, &f)| acc | f)
+        })
+        .map(|flags| score_params(payload, AecParams::new(bits_per_sample, block_size, rsi, flags), output_samples))
+        .collect();
+    candidates.sort_by(Candidate::rank);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_smooth_data_ranks_signed_first() {
+        // Uncompressed 8-bit blocks (id 7) of a slow oscillation around zero, two's complement.
+        let samples: Vec<u8> = (0..64).map(|i: i32| ((i % 16 - 8) / 2) as u8).collect();
+        let mut bits = Vec::new();
+        for block in samples.chunks(8) {
+            bits.extend([1, 1, 1]);
+            for &s in block {
+                bits.extend((0..8).rev().map(|i| (s >> i) & 1));
+            }
+        }
+        let payload: Vec<u8> =
+            bits.chunks(8).map(|c| c.iter().enumerate().fold(0, |acc, (i, &b)| acc | (b << (7 - i)))).collect();
+
+        let ranked = guess_flags(&payload, 8, 8, 2, samples.len());
+        assert_eq!(ranked.len(), 8);
+        assert!(ranked[0].is_clean());
+        assert_eq!(ranked[0].params.flags, AecFlags::DATA_SIGNED);
+        assert!(ranked[0].roughness < ranked[1].roughness || !ranked[1].is_clean());
+    }
+}
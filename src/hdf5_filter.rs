@@ -0,0 +1,153 @@
+//! HDF5 filter glue for szip-compressed datasets (feature `hdf5-filter`).
+//!
+//! [`SZIP_FILTER_CLASS`] is an `H5Z_class2_t` for the szip filter (id [`H5Z_FILTER_SZIP`])
+//! whose callback decodes chunks with [`crate::szip::decode_hdf5_chunk`], taking the
+//! [`SzipParams`] from the filter's `cd_values`. Registering it replaces the libaec/SZIP
+//! filter HDF5 was built with, so `hdf5`-rs (or any HDF5 binding) reads CCSDS/szip datasets
+//! without a native szip library:
+//!
+//! ```text
+//! // With the hdf5-sys bindings hdf5-rs uses:
+//! unsafe { hdf5_sys::h5z::H5Zregister(rust_aec::hdf5_filter::filter_class()) };
+//! ```
+//!
+//! The filter only decodes; writing a dataset through it fails. Nothing here links against
+//! HDF5: the class layout is declared locally, and buffers are allocated with the C `malloc`
+//! HDF5 frees them with.
+
+use std::ffi::{c_char, c_int, c_uint, c_void};
+
+use crate::szip::{decode_hdf5_chunk, SzipParams};
+
+/// HDF5's id for the szip filter (`H5Z_FILTER_SZIP`).
+pub const H5Z_FILTER_SZIP: c_int = 4;
+/// Filter flag set when HDF5 calls a filter to decode (`H5Z_FLAG_REVERSE`).
+pub const H5Z_FLAG_REVERSE: c_uint = 0x0100;
+
+/// The filter callback's type (`H5Z_func_t`).
+pub type H5ZFunc = unsafe extern "C" fn(
+    flags: c_uint,
+    cd_nelmts: usize,
+    cd_values: *const c_uint,
+    nbytes: usize,
+    buf_size: *mut usize,
+    buf: *mut *mut c_void,
+) -> usize;
+
+/// HDF5's filter class (`H5Z_class2_t`), as passed to `H5Zregister`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct H5ZClass2 {
+    /// Layout version (`H5Z_CLASS_T_VERS`, 1).
+    pub version: c_int,
+    /// Filter id.
+    pub id: c_int,
+    /// Whether the filter can encode.
+    pub encoder_present: c_uint,
+    /// Whether the filter can decode.
+    pub decoder_present: c_uint,
+    /// NUL-terminated filter name.
+    pub name: *const c_char,
+    /// `H5Z_can_apply_func_t`; unused.
+    pub can_apply: *const c_void,
+    /// `H5Z_set_local_func_t`; unused.
+    pub set_local: *const c_void,
+    /// The filter callback.
+    pub filter: Option<H5ZFunc>,
+}
+
+// SAFETY: the class is immutable and only points to static data.
+unsafe impl Sync for H5ZClass2 {}
+
+/// The szip filter class, decoding with this crate.
+pub static SZIP_FILTER_CLASS: H5ZClass2 = H5ZClass2 {
+    version: 1,
+    id: H5Z_FILTER_SZIP,
+    encoder_present: 0,
+    decoder_present: 1,
+    name: c"szip (rust-aec)".as_ptr(),
+    can_apply: std::ptr::null(),
+    set_local: std::ptr::null(),
+    filter: Some(szip_filter),
+};
+
+/// [`SZIP_FILTER_CLASS`] as the `const void *` `H5Zregister` takes.
+pub fn filter_class() -> *const c_void {
+    (&raw const SZIP_FILTER_CLASS).cast()
+}
+
+unsafe extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+/// The szip filter callback: decodes the `nbytes` of the chunk at `*buf` into a new buffer,
+/// returning its length, or 0 (HDF5's failure value) when asked to encode or when decoding
+/// fails.
+///
+/// # Safety
+///
+/// HDF5's filter contract: `cd_values` holds `cd_nelmts` values, `*buf` is a `malloc`ed
+/// buffer of `*buf_size` bytes with `nbytes` of data, and `buf_size` and `buf` are writable.
+pub unsafe extern "C" fn szip_filter(
+    flags: c_uint,
+    cd_nelmts: usize,
+    cd_values: *const c_uint,
+    nbytes: usize,
+    buf_size: *mut usize,
+    buf: *mut *mut c_void,
+) -> usize {
+    if flags & H5Z_FLAG_REVERSE == 0 || cd_values.is_null() || buf.is_null() || buf_size.is_null() {
+        return 0;
+    }
+    // SAFETY: the caller passes `cd_nelmts` values and a chunk of `nbytes` bytes.
+    let cd_values = unsafe { std::slice::from_raw_parts(cd_values, cd_nelmts) };
+    let chunk = unsafe { std::slice::from_raw_parts((*buf).cast::<u8>(), nbytes) };
+    let Ok(decoded) = SzipParams::from_hdf5_cd_values(cd_values).and_then(|p| decode_hdf5_chunk(chunk, &p)) else {
+        return 0;
+    };
+    // SAFETY: a fresh allocation of at least `decoded.len()` bytes replaces the caller's buffer.
+    unsafe {
+        let out = malloc(decoded.len().max(1));
+        if out.is_null() {
+            return 0;
+        }
+        std::ptr::copy_nonoverlapping(decoded.as_ptr(), out.cast::<u8>(), decoded.len());
+        free(*buf);
+        *buf = out;
+        *buf_size = decoded.len();
+    }
+    decoded.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode;
+    use crate::szip::SZ_NN_OPTION_MASK;
+
+    unsafe fn run(flags: c_uint, cd_values: &[c_uint], chunk: &[u8]) -> Option<Vec<u8>> {
+        unsafe {
+            let mut buf = malloc(chunk.len());
+            std::ptr::copy_nonoverlapping(chunk.as_ptr(), buf.cast::<u8>(), chunk.len());
+            let mut size = chunk.len();
+            let n = szip_filter(flags, cd_values.len(), cd_values.as_ptr(), chunk.len(), &mut size, &mut buf);
+            let out = (n != 0).then(|| std::slice::from_raw_parts(buf.cast::<u8>(), n).to_vec());
+            free(buf);
+            out
+        }
+    }
+
+    #[test]
+    fn the_filter_decodes_hdf5_chunks() {
+        let cd_values = [SZ_NN_OPTION_MASK, 8, 8, 16];
+        let pixels: Vec<u8> = (0..64).map(|i| (i * 3 % 50) as u8).collect();
+        let params = SzipParams::from_hdf5_cd_values(&cd_values).unwrap();
+        let chunk = [&64u32.to_le_bytes()[..], &encode(&pixels, params.aec_params().unwrap()).unwrap()].concat();
+
+        assert_eq!(unsafe { run(H5Z_FLAG_REVERSE, &cd_values, &chunk) }, Some(pixels));
+        assert_eq!(unsafe { run(0, &cd_values, &chunk) }, None);
+        assert_eq!(unsafe { run(H5Z_FLAG_REVERSE, &cd_values[..3], &chunk) }, None);
+        assert_eq!(SZIP_FILTER_CLASS.id, H5Z_FILTER_SZIP);
+    }
+}
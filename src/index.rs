@@ -0,0 +1,111 @@
+//! Random access by reference sample interval (RSI).
+//!
+//! Every RSI starts with fresh predictor state, so decoding can begin at any RSI once its bit
+//! offset is known. [`RsiIndex::build`] walks a payload once and records those offsets; with the
+//! `serde` feature the index can be stored next to an archived payload and reused later through
+//! [`crate::Decoder::with_index`].
+
+use crate::bitreader::BitReader;
+use crate::block::{decode_block, PredictorState};
+use crate::error::AecError;
+use crate::geometry;
+use crate::params::AecParams;
+
+/// Bit offsets of the RSI starts within one payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RsiIndex {
+    bits_per_sample: u8,
+    block_size: u32,
+    rsi: u32,
+    flags: u32,
+    output_samples: u64,
+    bit_offsets: Vec<u64>,
+}
+
+impl RsiIndex {
+    /// Walk `input` and record where each RSI covering the first `output_samples` samples starts.
+    pub fn build(input: &[u8], params: AecParams, output_samples: usize) -> Result<Self, AecError> {
+        let mut r = BitReader::new(input);
+        let mut state = PredictorState::default();
+        let mut bit_offsets = Vec::new();
+        let mut samples = 0usize;
+        let rsi_count = geometry::rsi_count(params, output_samples);
+
+        // The last RSI itself never needs to be decoded: its start is all we record.
+        while samples < output_samples {
+            if state.block_index_within_rsi == 0 {
+                bit_offsets.push(r.bits_read() as u64);
+                if bit_offsets.len() == rsi_count {
+                    break;
+                }
+            }
+            let block = decode_block(&mut r, &mut state, params).map_err(|e| match e {
+                AecError::UnexpectedEof { bit_pos } => {
+                    AecError::UnexpectedEofDuringDecode { bit_pos, samples_written: samples }
+                }
+                e => e,
+            })?;
+            samples += block.samples.len();
+        }
+
+        Ok(Self {
+            bits_per_sample: params.bits_per_sample,
+            block_size: params.block_size,
+            rsi: params.rsi,
+            flags: params.flags.bits(),
+            output_samples: output_samples as u64,
+            bit_offsets,
+        })
+    }
+
+    /// Number of indexed RSIs.
+    pub fn len(&self) -> usize {
+        self.bit_offsets.len()
+    }
+
+    /// Whether the index covers no RSI at all.
+    pub fn is_empty(&self) -> bool {
+        self.bit_offsets.is_empty()
+    }
+
+    /// Samples the indexed payload decodes to.
+    pub fn output_samples(&self) -> usize {
+        self.output_samples as usize
+    }
+
+    /// Samples per full RSI (`rsi * block_size`).
+    pub fn samples_per_rsi(&self) -> usize {
+        self.rsi as usize * self.block_size as usize
+    }
+
+    /// Bit offset of RSI `n` within the payload.
+    pub fn bit_offset(&self, n: usize) -> Option<usize> {
+        self.bit_offsets.get(n).map(|&b| b as usize)
+    }
+
+    /// First payload byte needed to decode from RSI `n`.
+    pub fn byte_offset(&self, n: usize) -> Option<usize> {
+        self.bit_offset(n).map(|b| b / 8)
+    }
+
+    /// Index of the first sample of RSI `n`.
+    pub fn first_sample(&self, n: usize) -> usize {
+        n * self.samples_per_rsi()
+    }
+
+    /// RSI containing sample `sample`.
+    pub fn rsi_of_sample(&self, sample: usize) -> usize {
+        sample / self.samples_per_rsi()
+    }
+
+    /// Check that the index was built for `params`.
+    pub(crate) fn check_params(&self, params: AecParams) -> Result<(), AecError> {
+        if (self.bits_per_sample, self.block_size, self.rsi, self.flags)
+            != (params.bits_per_sample, params.block_size, params.rsi, params.flags.bits())
+        {
+            return Err(AecError::InvalidInput("RSI index was built with different parameters"));
+        }
+        Ok(())
+    }
+}
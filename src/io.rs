@@ -0,0 +1,64 @@
+//! `std::io` integration (feature `io`).
+
+use std::io::{ErrorKind, Read, Write};
+
+use crate::decoder::{DecodeStatus, Decoder, Flush};
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+use crate::params::AecParams;
+use crate::report::DecodeReport;
+
+/// Payload bytes requested per `Read::read` call by [`copy_decode`].
+const COPY_INPUT_CHUNK: usize = 64 * 1024;
+
+/// Decode a whole payload and stream the packed sample bytes to `writer`.
+///
+/// Output goes through a small staging buffer instead of one `output_samples`-sized
+/// allocation; returns the number of bytes written.
+pub fn decode_to_writer<W: Write + ?Sized>(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    writer: &mut W,
+) -> Result<usize, AecError> {
+    let mut dec = Decoder::new(params, output_samples)?;
+    dec.push_input(input);
+    let (written, _status) = dec.decode_to_writer(writer, Flush::Flush)?;
+    Ok(written)
+}
+
+/// Decode a payload read from `reader` and stream the packed sample bytes to `writer`.
+///
+/// Drives the streaming [`Decoder`] the way `examples/stream_decode_aec_payload.rs` does by
+/// hand: input is read in 64 KiB chunks, end of input switches to [`Flush::Flush`], and reading
+/// stops once all `output_samples` are written (later input is left unread). Neither side needs
+/// to be buffered by the caller, and `writer` is not flushed.
+pub fn copy_decode<R: Read + ?Sized, W: Write + ?Sized>(
+    reader: &mut R,
+    writer: &mut W,
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<DecodeReport, AecError> {
+    let mut dec = Decoder::with_options(params, output_samples, options)?;
+    let mut chunk = vec![0u8; COPY_INPUT_CHUNK];
+    loop {
+        let flush = match reader.read(&mut chunk) {
+            Ok(0) => Flush::Flush,
+            Ok(n) => {
+                dec.push_input(&chunk[..n]);
+                Flush::NoFlush
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(options.tag(e.into())),
+        };
+        // Writers take everything, so only input can run out.
+        match (dec.decode_to_writer(writer, flush)?.1, flush) {
+            (DecodeStatus::Finished, _) => return Ok(dec.report()),
+            (_, Flush::Flush) => {
+                return Err(options.tag(AecError::InvalidInput("decoder requested input after end of input")));
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,4 +1,5 @@
-//! `rust-aec` is a pure Rust decoder for **CCSDS 121.0-B-3 Adaptive Entropy Coding (AEC)**.
+//! `rust-aec` is a pure Rust decoder for **CCSDS 121.0-B-3 Adaptive Entropy Coding (AEC)**, with
+//! a matching [`encode`].
 //!
 //! Primary goal: support **GRIB2 Data Representation Template 5.0 = 42 (CCSDS/AEC)** without
 //! requiring native `libaec`.
@@ -17,16 +18,102 @@
 //! let decoded = decode(&payload, params, num_points);
 //! assert!(decoded.is_ok());
 //! ```
+//!
+//! # Cargo features
+//!
+//! Decoding, encoding and the items re-exported at the crate root (including the GRIB2
+//! `ccsdsFlags` conversions) are always available; there is no separate `core` module. Optional
+//! modules:
+//!
+//! - `io` (default): `std::io` integration ([`io`], `Decoder::decode_to_writer`,
+//!   `Encoder::encode_to_writer`), the [`container`] format and the golden [`corpus`] runner.
+//! - `grib2` (default): GRIB2 template 5.42 helpers ([`grib2`]), Section 6 bitmaps ([`bitmap`])
+//!   and Section 3 scanning modes ([`scanning`]).
+//! - `cli` (default): the command-line tools under `src/bin`.
+//! - `ffi`: a C ABI (`ffi`).
+//! - `serde`: serialization of [`RsiIndex`].
+//! - `wgpu`: decoding into mapped GPU staging buffers (`gpu`).
+//! - `compression-codecs`: `compression_codecs::EncodeV2` and `DecodeV2` adapters for
+//!   `async-compression`-style pipelines (`codec`).
+//! - `bytemuck`: zero-copy views of decoded bytes as `u16`/`u32` samples (`pod`).
+//! - `rayon`: fused parallel decode, GRIB2 scaling and bitmap expansion, and parallel `PAD_RSI`
+//!   encoding (`pipeline`).
+//! - `grib`: template 5.42 decoding of submessages parsed by the `grib` crate (`grib_rs`).
+//! - `hdf5-filter`: an HDF5 szip filter class decoding chunks with [`szip`] (`hdf5_filter`).
+//! - `zarr`: Zarr v3 `aec` and `szip` codecs configured from codec metadata, and with `grib2`,
+//!   decoding of kerchunk-style GRIB2 Section 7 references (`zarr`).
+//!
+//! Embedded and WASM users can depend on the crate with `default-features = false`.
 
+pub mod aec_codec;
+#[cfg(feature = "grib2")]
+pub mod bitmap;
 pub mod bitreader;
+mod bitwriter;
+pub mod block;
+pub mod checksum;
+#[cfg(feature = "compression-codecs")]
+pub mod codec;
+#[cfg(feature = "io")]
+pub mod container;
+mod cost;
+#[cfg(feature = "io")]
+pub mod corpus;
 mod decoder;
+mod encoder;
 pub mod error;
+pub mod fixed;
+pub mod geometry;
+pub mod guess;
+pub mod index;
+pub mod memory;
+pub mod options;
 pub mod params;
+#[cfg(feature = "bytemuck")]
+pub mod pod;
+#[cfg(feature = "rayon")]
+pub mod pipeline;
+pub mod preprocess;
+pub mod report;
+pub mod rice;
+mod sample;
+#[cfg(feature = "grib2")]
+pub mod scanning;
+pub mod session;
+mod sink;
+pub mod szip;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+#[cfg(feature = "grib2")]
+pub mod grib2;
+#[cfg(feature = "grib")]
+pub mod grib_rs;
+#[cfg(feature = "hdf5-filter")]
+pub mod hdf5_filter;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "zarr")]
+pub mod zarr;
 
+pub use crate::aec_codec::AecCodec;
+pub use crate::block::{decode_block, Block, BlockKind, PredictorState};
 pub use crate::error::AecError;
-pub use crate::params::{AecFlags, AecParams};
+pub use crate::guess::{guess_flags, Candidate};
+pub use crate::index::RsiIndex;
+pub use crate::memory::{estimate_memory, DecodeMode};
+pub use crate::options::{DecodeOptions, EncodeLevel, EncodeOptions, MissingValues};
+pub use crate::params::{AecFlags, AecParams, ByteOrder};
+pub use crate::report::{DecodeReport, DecodedField, EncodeStats};
+pub use crate::sample::Sample;
+pub use crate::session::AecSession;
+
+pub use crate::decoder::{DecodeStatus, Decoder, Flush, RsiStats, SampleIter};
+pub use crate::encoder::{EncodeStatus, Encoder, EncoderBuilder};
 
-pub use crate::decoder::{DecodeStatus, Decoder, Flush};
+pub use crate::params::{flags_from_grib2_ccsds_flags, flags_to_grib2_ccsds_flags};
 
 /// Decode an AEC bitstream into packed sample bytes.
 ///
@@ -35,12 +122,93 @@ pub use crate::decoder::{DecodeStatus, Decoder, Flush};
 /// - `output_samples`: number of samples expected in the output.
 ///
 /// Returns a `Vec<u8>` of length `output_samples * bytes_per_sample`, where
-/// `bytes_per_sample = ceil(bits_per_sample / 8)`.
+/// `bytes_per_sample = ceil(bits_per_sample / 8)`. A constant field (`bits_per_sample` 0, as
+/// GRIB2 packs a field whose values all equal the reference value) needs no payload and decodes
+/// to one zero byte per sample.
 ///
 /// Note: When `AecFlags::MSB` is set, samples are written big-endian (MSB-first)
-/// per sample; otherwise little-endian.
+/// per sample; otherwise little-endian, regardless of the host (see [`ByteOrder`]).
 pub fn decode(input: &[u8], params: AecParams, output_samples: usize) -> Result<Vec<u8>, AecError> {
-    decoder::decode(input, params, output_samples)
+    decoder::decode(input, params, output_samples, &DecodeOptions::default())
+}
+
+/// Encode packed samples into an AEC bitstream; the inverse of [`decode`].
+///
+/// `input` holds whole samples laid out as [`decode`] returns them: `bytes_per_sample` bytes
+/// each, in the byte order of `params` (see [`ByteOrder`]). Every value must fit in
+/// `bits_per_sample` bits (signed samples may also be sign-extended). Each block is coded with
+/// the cheapest of the zero-block, Second Extension, Rice split and uncompressed options, and the
+/// stream is padded to a whole byte; with `AecFlags::PAD_RSI`, so is every complete RSI.
+///
+/// With `AecFlags::DATA_PREPROCESS` (GRIB2 `ccsdsFlags` 0x0e and most other producers), samples
+/// are coded as predictor residuals and every RSI starts with a reference sample.
+/// `AecFlags::RESTRICTED` selects the short option id table for up to 4 bits per sample, as in
+/// the decoder.
+/// [`Encoder`] produces the same stream incrementally.
+pub fn encode(input: &[u8], params: AecParams) -> Result<Vec<u8>, AecError> {
+    encoder::encode(input, params, &EncodeOptions::default())
+}
+
+/// Like [`encode`], then decode the stream and compare it with `input` before returning it.
+///
+/// A mismatch fails with [`AecError::RoundTripMismatch`] naming the first differing sample, so
+/// archive producers can rely on the stream before discarding the raw data. Decoding runs in
+/// fixed-size pieces; apart from the stream itself, no output-sized buffer is allocated.
+pub fn encode_verified(input: &[u8], params: AecParams) -> Result<Vec<u8>, AecError> {
+    encoder::encode_verified(input, params)
+}
+
+/// Like [`encode_with_options`], also returning [`EncodeStats`] on the coding options chosen.
+pub fn encode_with_stats(
+    input: &[u8],
+    params: AecParams,
+    options: &EncodeOptions,
+) -> Result<(Vec<u8>, EncodeStats), AecError> {
+    encoder::encode_with_stats(input, params, options)
+}
+
+/// Like [`encode`], with non-default [`EncodeOptions`].
+pub fn encode_with_options(input: &[u8], params: AecParams, options: &EncodeOptions) -> Result<Vec<u8>, AecError> {
+    encoder::encode(input, params, options)
+}
+
+/// Like [`encode`], taking samples as integers instead of packed bytes, so no byte order or
+/// sample width has to be matched by hand.
+///
+/// Values must fit in `bits_per_sample` bits: `0..2^n` for unsigned data, or `-2^(n-1)..2^(n-1)`
+/// with `AecFlags::DATA_SIGNED`. The stream decodes to the same values.
+///
+/// ```
+/// use rust_aec::{decode, encode_samples, AecFlags, AecParams};
+///
+/// let params = AecParams::new(12, 16, 32, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+/// let samples: Vec<u16> = (0..1000).map(|i| 2000 + i % 50).collect();
+/// let stream = encode_samples(&samples, params)?;
+/// let decoded = decode(&stream, params, samples.len())?;
+/// assert_eq!(u16::from_be_bytes([decoded[2], decoded[3]]), samples[1]);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn encode_samples<T: Sample>(samples: &[T], params: AecParams) -> Result<Vec<u8>, AecError> {
+    encoder::encode_samples(samples, params)
+}
+
+/// Like [`encode`], writing into a caller-provided buffer; returns the number of bytes written.
+///
+/// [`AecParams::max_encoded_size`] gives a buffer size that always suffices; a shorter buffer
+/// the stream does not fit fails with [`AecError::InvalidInput`]. Apart from one RSI of
+/// samples, nothing is allocated.
+pub fn encode_into(input: &[u8], params: AecParams, output: &mut [u8]) -> Result<usize, AecError> {
+    encoder::encode_into(input, params, output)
+}
+
+/// Like [`decode`], with non-default [`DecodeOptions`].
+pub fn decode_with_options(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, AecError> {
+    decoder::decode(input, params, output_samples, options)
 }
 
 /// Decode an AEC bitstream into a caller-provided output buffer.
@@ -56,47 +224,531 @@ pub fn decode_into(
     output_samples: usize,
     output: &mut [u8],
 ) -> Result<(), AecError> {
-    decoder::decode_into(input, params, output_samples, output)
+    decoder::decode_into(input, params, output_samples, output, &DecodeOptions::default())
+}
+
+/// Like [`decode_into`], with non-default [`DecodeOptions`].
+///
+/// This never panics: invalid parameters, corrupt or truncated payloads, arithmetic edge cases
+/// and a wrongly sized `output` are all returned as an [`AecError`]. The crate's
+/// panic-detection test harness drives it, [`decode_with_options`] and the streaming
+/// [`Decoder`] with arbitrary inputs; the guarantee does not rely on unwinding, so it holds
+/// with `panic = "abort"`.
+pub fn decode_into_with_options(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    output: &mut [u8],
+    options: &DecodeOptions,
+) -> Result<(), AecError> {
+    decoder::decode_into(input, params, output_samples, output, options)
+}
+
+/// Decode an AEC bitstream and append the packed sample bytes to `out`.
+///
+/// `out` is grown exactly once, so concatenating many tiles into one buffer needs neither
+/// intermediate `Vec`s nor copies. On error `out` is truncated back to its original length.
+pub fn decode_append(input: &[u8], params: AecParams, output_samples: usize, out: &mut Vec<u8>) -> Result<(), AecError> {
+    decoder::decode_append(input, params, output_samples, out, &DecodeOptions::default())
+}
+
+/// Decode only the first `rsi_count` reference sample intervals of a payload.
+///
+/// Returns the packed bytes of that prefix (all `output_samples` if the payload has fewer RSIs)
+/// and the bit offset where decoding stopped, i.e. the start of the next RSI. Catalog and browse
+/// services can render a quick preview of a huge field this way without decompressing all of it.
+pub fn decode_preview(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    rsi_count: usize,
+) -> Result<(Vec<u8>, usize), AecError> {
+    let samples = geometry::first_sample_of_rsi(params, rsi_count).min(output_samples);
+    let (out, report) = decoder::decode_with_report(input, params, samples, &DecodeOptions::default())?;
+    Ok((out, report.bits_consumed))
+}
+
+/// Decode a reduced-resolution quick look that keeps only every `step`-th sample.
+///
+/// The whole payload is still decoded (predictor state must advance through every sample), but
+/// dropped samples are never packed or stored, so thumbnails come out faster and smaller than
+/// decoding everything and subsampling afterwards. Returns `ceil(output_samples / step)` packed
+/// samples: indices `0, step, 2 * step, ...` of the full output.
+pub fn decode_decimated(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    step: usize,
+) -> Result<Vec<u8>, AecError> {
+    decoder::decode_decimated(input, params, output_samples, step, &DecodeOptions::default())
+}
+
+/// Decode into `u16` samples, for up to 16 bits per sample.
+///
+/// Byte order and sample width are handled internally, so no `u16::from_be_bytes` loop over
+/// [`decode`] output is needed. Signed samples come out in two's complement (cast with
+/// `as i16`); wider `bits_per_sample` is [`AecError::InvalidInput`].
+///
+/// ```
+/// use rust_aec::{decode_u16, encode_samples, AecFlags, AecParams};
+///
+/// let params = AecParams::new(12, 32, 128, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+/// let samples: Vec<u16> = (0..500).map(|i| 1000 + i % 37).collect();
+/// let stream = encode_samples(&samples, params)?;
+/// assert_eq!(decode_u16(&stream, params, samples.len())?, samples);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_u16(input: &[u8], params: AecParams, output_samples: usize) -> Result<Vec<u16>, AecError> {
+    decoder::decode_typed(input, params, output_samples, 16, |x| x as u16, &DecodeOptions::default())
+}
+
+/// Like [`decode_u16`], into the caller's `output` (one sample per element, `output.len()`
+/// samples in all) instead of a new `Vec`, with no intermediate byte buffer.
+///
+/// ```
+/// use rust_aec::{decode_into_u16, encode_samples, AecFlags, AecParams};
+///
+/// let params = AecParams::new(16, 32, 64, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+/// let field: Vec<u16> = (0..720).map(|i| 20000 + i % 90).collect();
+/// let stream = encode_samples(&field, params)?;
+/// let mut grid = vec![0u16; field.len()];
+/// decode_into_u16(&stream, params, &mut grid)?;
+/// assert_eq!(grid, field);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_into_u16(input: &[u8], params: AecParams, output: &mut [u16]) -> Result<(), AecError> {
+    decoder::decode_into_typed(input, params, output, 16, |x| x as u16, &DecodeOptions::default())
+}
+
+/// Like [`decode_u16`], into `u32` samples for up to 32 bits per sample.
+pub fn decode_u32(input: &[u8], params: AecParams, output_samples: usize) -> Result<Vec<u32>, AecError> {
+    decoder::decode_typed(input, params, output_samples, 32, |x| x as u32, &DecodeOptions::default())
+}
+
+/// Decode into sign-extended `i32` samples, for signed data (`AecFlags::DATA_SIGNED`) of up to
+/// 32 bits per sample, or unsigned data of up to 31.
+///
+/// [`decode`] returns signed samples as raw `bits_per_sample`-bit two's complement fields; here
+/// a 12-bit `0xfff` comes out as `-1`. Shorthand for [`decode_samples::<i32>`](decode_samples).
+pub fn decode_i32(input: &[u8], params: AecParams, output_samples: usize) -> Result<Vec<i32>, AecError> {
+    decode_samples(input, params, output_samples)
+}
+
+/// Decode into samples of any [`Sample`] type, with byte order handled internally.
+///
+/// The type must hold every value `params` allows: signed data (`AecFlags::DATA_SIGNED`) needs
+/// a signed type of at least `bits_per_sample` bits and comes out sign-extended; unsigned data
+/// needs a type with `bits_per_sample` value bits (so `i16` takes up to 15). Anything else is
+/// [`AecError::InvalidInput`].
+///
+/// ```
+/// use rust_aec::{decode_samples, encode_samples, AecFlags, AecParams};
+///
+/// let params = AecParams::new(10, 16, 64, AecFlags::DATA_SIGNED | AecFlags::DATA_PREPROCESS);
+/// let samples: Vec<i16> = (-300..300).collect();
+/// let stream = encode_samples(&samples, params)?;
+/// assert_eq!(decode_samples::<i16>(&stream, params, samples.len())?, samples);
+/// assert!(decode_samples::<u16>(&stream, params, samples.len()).is_err());
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_samples<T: Sample>(input: &[u8], params: AecParams, output_samples: usize) -> Result<Vec<T>, AecError> {
+    decoder::decode_samples(input, params, output_samples, &DecodeOptions::default())
+}
+
+/// Decode into a [`DecodedField`]: the samples as `u32` values like [`decode_u32`], together
+/// with their width and signedness, the payload bytes used and per-RSI statistics.
+///
+/// ```
+/// use rust_aec::{decode_field, encode_samples, AecFlags, AecParams};
+///
+/// let params = AecParams::new(14, 16, 8, AecFlags::DATA_SIGNED | AecFlags::DATA_PREPROCESS);
+/// let samples: Vec<i16> = (0..500).map(|i| (i % 100) * 40 - 2000).collect();
+/// let stream = encode_samples(&samples, params)?;
+/// let field = decode_field(&stream, params, samples.len())?;
+/// assert!(field.signed);
+/// assert_eq!(field.samples[0] as i32, -2000);
+/// assert_eq!(field.bytes_consumed, stream.len());
+/// assert_eq!(field.stats.len(), 4);
+/// assert_eq!(field.stats.iter().filter_map(|s| s.max).max(), Some(1960));
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_field(input: &[u8], params: AecParams, output_samples: usize) -> Result<DecodedField, AecError> {
+    decoder::decode_field(input, params, output_samples, &DecodeOptions::default())
+}
+
+/// Decode samples and hand them to `sink` in block-sized batches, as `u32` values like
+/// [`decode_u32`], without collecting the whole field.
+///
+/// Suits single-pass consumers such as statistics or regridding. Every batch holds
+/// `block_size` samples except possibly the last; batches decoded before an error are still
+/// delivered.
+///
+/// ```
+/// use rust_aec::{decode_with_sink, encode_samples, AecFlags, AecParams};
+///
+/// let params = AecParams::new(16, 32, 64, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+/// let samples: Vec<u16> = (0..1000).map(|i| 5000 + (i * 7) % 300).collect();
+/// let stream = encode_samples(&samples, params)?;
+/// let (mut min, mut max, mut batches) = (u32::MAX, 0, 0);
+/// decode_with_sink(&stream, params, samples.len(), |batch| {
+///     min = batch.iter().fold(min, |m, &x| m.min(x));
+///     max = batch.iter().fold(max, |m, &x| m.max(x));
+///     batches += 1;
+/// })?;
+/// assert_eq!((min, max, batches), (5000, 5299, 32));
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_with_sink(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    sink: impl FnMut(&[u32]),
+) -> Result<(), AecError> {
+    decoder::decode_with_sink(input, params, output_samples, sink, &DecodeOptions::default())
 }
 
-/// Helper: convert GRIB2 `ccsdsFlags` (template 5.42) to `AecFlags`.
-pub fn flags_from_grib2_ccsds_flags(ccsds_flags: u8) -> AecFlags {
-    let mut flags = AecFlags::empty();
+/// Decode into samples packed at `bits_per_sample` bits each, MSB-first, without padding
+/// between samples: the GRIB2 simple-packing (template 7.0) data layout.
+///
+/// Returns `ceil(output_samples * bits_per_sample / 8)` bytes, the last one zero-padded. Signed
+/// samples keep their two's complement low bits. The flags `MSB` and `DATA_3BYTE` only affect
+/// byte-expanded output and are ignored here. Memory-constrained consumers get the compact form
+/// directly instead of re-packing the byte-expanded output.
+pub fn decode_bit_packed(input: &[u8], params: AecParams, output_samples: usize) -> Result<Vec<u8>, AecError> {
+    decoder::decode_bit_packed(input, params, output_samples, &DecodeOptions::default())
+}
 
-    if (ccsds_flags & (1 << 0)) != 0 {
-        flags |= AecFlags::DATA_SIGNED;
+/// Decode and requantize to `target_bits` (at most `bits_per_sample`) while emitting, e.g.
+/// 12-bit fields to 8-bit browse imagery.
+///
+/// Each sample drops its `bits_per_sample - target_bits` low bits with round-half-up, clamped to
+/// the target range; output is packed for `target_bits` (one byte per sample up to 8 bits), so
+/// no full-resolution intermediate buffer is needed for quick looks.
+pub fn decode_requantized(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    target_bits: u8,
+) -> Result<Vec<u8>, AecError> {
+    decoder::decode_requantized(input, params, output_samples, target_bits, &DecodeOptions::default())
+}
+
+/// Decode a row-major `nx` × `ny` grid (`nx` samples per row, as GRIB2 scans by default) and
+/// store it in column-major order, as Fortran code and NumPy `order="F"` arrays expect.
+///
+/// Sample `(i, j)` (column `i`, row `j`) lands at index `i * ny + j`. Samples are placed while
+/// they are decoded, so no separate transpose pass over the grid is needed.
+pub fn decode_column_major(input: &[u8], params: AecParams, nx: usize, ny: usize) -> Result<Vec<u8>, AecError> {
+    let samples = nx.checked_mul(ny).ok_or(AecError::InvalidInput("output too large"))?;
+    let mut out = vec![0u8; decoder::output_len(params, samples, &DecodeOptions::default())?];
+    decode_into_column_major(input, params, nx, ny, &mut out)?;
+    Ok(out)
+}
+
+/// Like [`decode_column_major`], writing into a caller buffer of exactly `nx * ny` samples.
+pub fn decode_into_column_major(
+    input: &[u8],
+    params: AecParams,
+    nx: usize,
+    ny: usize,
+    output: &mut [u8],
+) -> Result<(), AecError> {
+    decoder::decode_into_column_major(input, params, nx, ny, output, &DecodeOptions::default())
+}
+
+/// Like [`decode_with_options`], for callers whose coding standards forbid panics in library code.
+///
+/// Decoding never panics (see [`decode_into_with_options`]); this entry point also reports a
+/// failed output allocation as an [`AecError`] instead of aborting. Process-level aborts outside
+/// the decoder's control (stack exhaustion, an allocator that aborts) are out of scope.
+pub fn decode_no_panic(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, AecError> {
+    decoder::decode_no_panic(input, params, output_samples, options)
+}
+
+/// Like [`decode_with_options`], also returning a [`DecodeReport`] on the consumed bitstream.
+pub fn decode_with_report(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    options: &DecodeOptions,
+) -> Result<(Vec<u8>, DecodeReport), AecError> {
+    decoder::decode_with_report(input, params, output_samples, options)
+}
+
+/// Like [`decode_into_with_options`], returning a [`DecodeReport`] on the consumed bitstream.
+pub fn decode_into_with_report(
+    input: &[u8],
+    params: AecParams,
+    output_samples: usize,
+    output: &mut [u8],
+    options: &DecodeOptions,
+) -> Result<DecodeReport, AecError> {
+    decoder::decode_into_with_report(input, params, output_samples, output, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonstandard_block_size_is_opt_in() {
+        let params = AecParams::new(8, 12, 4, AecFlags::empty());
+        assert!(matches!(decode(&[], params, 0), Err(AecError::Unsupported(_))));
+
+        let options = DecodeOptions { allow_nonstandard_block_size: true, ..Default::default() };
+        assert!(decode_with_options(&[], params, 0, &options).is_ok());
     }
-    if (ccsds_flags & (1 << 1)) != 0 {
-        flags |= AecFlags::DATA_3BYTE;
+
+    #[test]
+    fn unary_limit_follows_params_and_options() {
+        // Split block (id 1, k = 0) whose first fundamental sequence is 300 zero bits long.
+        let mut payload = vec![0b0010_0000u8];
+        payload.extend(std::iter::repeat_n(0u8, 40));
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+
+        let err = decode(&payload, params, 8).unwrap_err();
+        assert!(matches!(err, AecError::InvalidInput("unary run too long")));
+
+        let options = DecodeOptions { max_unary_run: Some(1000), ..Default::default() };
+        let err = decode_with_options(&payload, params, 8, &options).unwrap_err();
+        assert!(matches!(err, AecError::UnexpectedEofDuringDecode { .. }));
     }
-    if (ccsds_flags & (1 << 2)) != 0 {
-        flags |= AecFlags::MSB;
+
+    #[test]
+    fn input_length_check_catches_swapped_arguments() {
+        let params = AecParams::new(12, 32, 128, AecFlags::DATA_3BYTE | AecFlags::MSB | AecFlags::DATA_PREPROCESS);
+        let payload = vec![0u8; 16];
+        let options = DecodeOptions { check_input_length: true, ..Default::default() };
+
+        // 1_038_240 samples need at least 8112 blocks / 64 RSIs worth of headers and references.
+        let err = decode_with_options(&payload, params, 1_038_240, &options).unwrap_err();
+        assert!(matches!(err, AecError::InvalidInput(msg) if msg.contains("swapped")));
+
+        // Without the check, decoding fails later with an EOF instead.
+        assert!(matches!(
+            decode(&payload, params, 1_038_240),
+            Err(AecError::UnexpectedEofDuringDecode { .. })
+        ));
     }
-    if (ccsds_flags & (1 << 3)) != 0 {
-        flags |= AecFlags::DATA_PREPROCESS;
+
+    #[test]
+    fn decode_append_extends_and_rolls_back() {
+        // Uncompressed blocks (id 7) of 8-bit samples.
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+        let tile = |base: u8| {
+            let mut bits = vec![0b1110_0000u8 | (base >> 3)];
+            bits.extend((0..8u8).map(|i| ((base + i) << 5) | ((base + i + 1) >> 3)));
+            bits
+        };
+        let (a, b) = (tile(0), tile(40));
+
+        let mut out = vec![0xaa];
+        decode_append(&a, params, 8, &mut out).unwrap();
+        decode_append(&b, params, 8, &mut out).unwrap();
+        let mut expected = vec![0xaa];
+        expected.extend(decode(&a, params, 8).unwrap());
+        expected.extend(decode(&b, params, 8).unwrap());
+        assert_eq!(out, expected);
+        assert_eq!(&out[1..9], &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        assert!(decode_append(&a[..3], params, 8, &mut out).is_err());
+        assert_eq!(out, expected);
     }
-    if (ccsds_flags & (1 << 4)) != 0 {
-        flags |= AecFlags::RESTRICTED;
+
+    /// Uncompressed 12-bit block (id 15) of samples 100..108.
+    fn uncompressed_12bit_block() -> Vec<u8> {
+        let mut payload = vec![0xf0u8 | (100 >> 8) as u8];
+        let mut acc = (100u32 & 0xff) as u64;
+        let mut nbits = 8;
+        for v in 101..108u64 {
+            acc = (acc << 12) | v;
+            nbits += 12;
+            while nbits >= 8 {
+                nbits -= 8;
+                payload.push((acc >> nbits) as u8);
+            }
+        }
+        payload.push((acc << (8 - nbits)) as u8);
+        payload
     }
-    if (ccsds_flags & (1 << 5)) != 0 {
-        flags |= AecFlags::PAD_RSI;
+
+    #[test]
+    fn decimated_decode_keeps_every_kth_sample() {
+        // MSB-first output.
+        let params = AecParams::new(12, 8, 4, AecFlags::MSB);
+        let payload = uncompressed_12bit_block();
+
+        let full = decode(&payload, params, 8).unwrap();
+        assert_eq!(full[14..], [0, 107]);
+        for step in [1usize, 3, 8, 20] {
+            let expected: Vec<u8> = full.chunks(2).step_by(step).flatten().copied().collect();
+            assert_eq!(decode_decimated(&payload, params, 8, step).unwrap(), expected, "step {step}");
+        }
+        assert!(decode_decimated(&payload, params, 8, 0).is_err());
     }
 
-    flags
-}
+    #[test]
+    fn typed_decodes_match_the_packed_output() {
+        for flags in [AecFlags::MSB, AecFlags::empty()] {
+            let params = AecParams::new(12, 8, 4, flags);
+            let payload = uncompressed_12bit_block();
+            let packed = decode(&payload, params, 8).unwrap();
+            let expected: Vec<u16> = packed.chunks(2).map(|b| params.byte_order().get(b) as u16).collect();
+            assert_eq!(decode_u16(&payload, params, 8).unwrap(), expected);
+            let mut grid = [0u16; 8];
+            decode_into_u16(&payload, params, &mut grid).unwrap();
+            assert_eq!(grid[..], expected[..]);
+            let wide: Vec<u32> = expected.iter().map(|&v| u32::from(v)).collect();
+            assert_eq!(decode_u32(&payload, params, 8).unwrap(), wide);
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let signed = AecParams::new(20, 16, 4, AecFlags::DATA_SIGNED | AecFlags::DATA_PREPROCESS);
+        let samples: Vec<i32> = (0..100).map(|i| (i - 50) * 9999).collect();
+        let stream = encode_samples(&samples, signed).unwrap();
+        let decoded = decode_u32(&stream, signed, samples.len()).unwrap();
+        assert_eq!(decoded.iter().map(|&v| v as i32).collect::<Vec<_>>(), samples);
+        assert!(decode_u16(&stream, signed, samples.len()).is_err());
+        assert!(decode_into_u16(&stream, signed, &mut [0; 100]).is_err());
+    }
 
     #[test]
-    fn flags_mapping_smoke() {
-        let f = flags_from_grib2_ccsds_flags(0b0011_1011);
-        assert!(f.contains(AecFlags::DATA_SIGNED));
-        assert!(f.contains(AecFlags::DATA_3BYTE));
-        assert!(!f.contains(AecFlags::MSB));
-        assert!(f.contains(AecFlags::DATA_PREPROCESS));
-        assert!(f.contains(AecFlags::RESTRICTED));
-        assert!(f.contains(AecFlags::PAD_RSI));
+    fn signed_decodes_are_sign_extended() {
+        // 12-bit two's complement fields, as `decode` returns them.
+        let params = AecParams::new(12, 8, 1, AecFlags::DATA_SIGNED | AecFlags::MSB);
+        let fields = [0x08, 0x00, 0x0f, 0xff, 0x00, 0x00, 0x07, 0xff].repeat(2);
+        let payload = encode(&fields, params).unwrap();
+        assert_eq!(decode(&payload, params, 8).unwrap(), fields);
+        assert_eq!(decode_i32(&payload, params, 8).unwrap(), [-2048, -1, 0, 2047].repeat(2));
+
+        let unsigned = AecParams::new(32, 16, 8, AecFlags::empty());
+        assert!(decode_i32(&[0; 64], unsigned, 16).is_err());
+    }
+
+    #[test]
+    fn decoded_fields_match_the_typed_decode() {
+        let params = AecParams::new(12, 16, 4, AecFlags::DATA_PREPROCESS);
+        let samples: Vec<u16> = (0..300).map(|i| if i < 64 { 7 } else { (i * 37) % 4096 }).collect();
+        let stream = encode_samples(&samples, params).unwrap();
+        let field = decode_field(&stream, params, samples.len()).unwrap();
+        assert_eq!(field.samples, decode_u32(&stream, params, samples.len()).unwrap());
+        assert_eq!((field.bits_per_sample, field.signed, field.bytes_consumed), (12, false, stream.len()));
+        assert_eq!(field.stats.iter().map(|s| s.samples).sum::<usize>(), samples.len());
+        assert!(field.stats[0].zero_blocks > 0);
+        assert!(decode_field(&stream[..stream.len() / 2], params, samples.len()).is_err());
+    }
+
+    #[test]
+    fn constant_fields_decode_to_zeros_without_input() {
+        for flags in [AecFlags::empty(), AecFlags::DATA_PREPROCESS | AecFlags::DATA_SIGNED | AecFlags::MSB] {
+            let params = AecParams::new(0, 32, 128, flags);
+            assert_eq!(decode(&[], params, 100).unwrap(), [0; 100]);
+            assert_eq!(decode_samples::<i16>(&[0xff], params, 5000).unwrap(), [0; 5000]);
+            assert!(decode_bit_packed(&[], params, 100).unwrap().is_empty());
+
+            let mut dec = Decoder::new(params, 1000).unwrap();
+            assert_eq!(dec.decode_all().unwrap(), [0; 1000]);
+            assert_eq!(dec.total_in(), 0);
+        }
+        assert!(encode(&[0; 8], AecParams::new(0, 16, 8, AecFlags::empty())).is_err());
+    }
+
+    #[test]
+    fn sink_batches_are_block_sized_and_precede_errors() {
+        let params = AecParams::new(12, 16, 4, AecFlags::DATA_PREPROCESS);
+        let samples: Vec<u16> = (0..300).map(|i| (i * 37) % 4096).collect();
+        let stream = encode_samples(&samples, params).unwrap();
+        let mut batches = Vec::new();
+        decode_with_sink(&stream, params, samples.len(), |b| batches.push(b.to_vec())).unwrap();
+        assert!(batches[..batches.len() - 1].iter().all(|b| b.len() == 16));
+        assert_eq!(batches.last().unwrap().len(), 300 % 16);
+        assert_eq!(batches.concat(), decode_u32(&stream, params, samples.len()).unwrap());
+
+        let mut seen = Vec::new();
+        let truncated = &stream[..stream.len() / 2];
+        assert!(decode_with_sink(truncated, params, samples.len(), |b| seen.extend_from_slice(b)).is_err());
+        assert!(!seen.is_empty() && seen.len() < samples.len());
+        assert!(seen.iter().zip(&samples).all(|(&x, &y)| x == u32::from(y)));
+    }
+
+    #[test]
+    fn native_byte_order_output_reads_back_with_ne_bytes() {
+        // Independent of the host: both explicit orders give fixed bytes, and the native order
+        // reinterprets correctly on little- and big-endian targets alike.
+        let payload = uncompressed_12bit_block();
+        let params = AecParams::new(12, 8, 4, AecFlags::empty());
+        let little = decode(&payload, params.with_byte_order(ByteOrder::LittleEndian), 8).unwrap();
+        let big = decode(&payload, params.with_byte_order(ByteOrder::BigEndian), 8).unwrap();
+        assert_eq!((&little[..2], &big[..2]), (&[100, 0][..], &[0, 100][..]));
+
+        let native = decode(&payload, params.with_byte_order(ByteOrder::NATIVE), 8).unwrap();
+        let values: Vec<u16> = native.chunks_exact(2).map(|b| u16::from_ne_bytes([b[0], b[1]])).collect();
+        assert_eq!(values, (100..108).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn bit_packed_decode_round_trips_the_coded_layout() {
+        // The uncompressed block's samples are stored bit-packed after a 4-bit id: dropping the
+        // id gives exactly the simple-packing layout.
+        let payload = uncompressed_12bit_block();
+        let params = AecParams::new(12, 8, 4, AecFlags::empty());
+        let expected: Vec<u8> = payload.windows(2).map(|w| (w[0] << 4) | (w[1] >> 4)).collect();
+        assert_eq!(decode_bit_packed(&payload, params, 8).unwrap(), expected[..12]);
+
+        // An odd sample count leaves a zero-padded half byte.
+        let mut three = expected[..5].to_vec();
+        three[4] &= 0xf0;
+        assert_eq!(decode_bit_packed(&payload, params, 3).unwrap(), three);
+    }
+
+    #[test]
+    fn requantized_decode_rounds_to_the_target_depth() {
+        let payload = uncompressed_12bit_block();
+        let params = AecParams::new(12, 8, 4, AecFlags::empty());
+        // 100..108 / 16, rounded half up: 6.25 .. 6.6875.
+        assert_eq!(decode_requantized(&payload, params, 8, 8).unwrap(), [6, 6, 6, 6, 7, 7, 7, 7]);
+        assert_eq!(decode_requantized(&payload, params, 8, 12).unwrap(), decode(&payload, params, 8).unwrap());
+        // 100..108 / 64 rounds to 2 everywhere.
+        assert_eq!(decode_requantized(&payload, params, 2, 6).unwrap(), [2, 2]);
+        assert!(decode_requantized(&payload, params, 8, 13).is_err());
+        assert!(decode_requantized(&payload, params, 8, 0).is_err());
+    }
+
+    #[test]
+    fn column_major_decode_transposes_the_grid() {
+        // One uncompressed 8-bit block (id 7) of samples 0..8: a 4 x 2 grid, rows 0..4 and 4..8.
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+        let mut payload = vec![0b1110_0000u8];
+        payload.extend((0..8u8).map(|i| (i << 5) | ((i + 1) >> 3)));
+
+        assert_eq!(decode_column_major(&payload, params, 4, 2).unwrap(), [0, 4, 1, 5, 2, 6, 3, 7]);
+        assert_eq!(decode_column_major(&payload, params, 8, 1).unwrap(), decode(&payload, params, 8).unwrap());
+        assert!(decode_into_column_major(&payload, params, 4, 2, &mut [0u8; 7]).is_err());
+    }
+
+    #[test]
+    fn context_tag_reaches_errors_and_reports() {
+        let params = AecParams::new(8, 8, 4, AecFlags::empty());
+        let options = DecodeOptions { context: Some("msg 3 field 7".into()), ..Default::default() };
+
+        let err = decode_with_options(&[0xff], params, 8, &options).unwrap_err();
+        assert_eq!(err.context(), Some("msg 3 field 7"));
+        assert!(matches!(err.untagged(), AecError::UnexpectedEofDuringDecode { .. }));
+        assert!(err.to_string().starts_with("msg 3 field 7: "));
+
+        let bad = AecParams::new(33, 8, 4, AecFlags::empty());
+        assert_eq!(decode_with_options(&[], bad, 0, &options).unwrap_err().context(), Some("msg 3 field 7"));
+
+        let mut dec = Decoder::with_options(params, 8, &options).unwrap();
+        dec.push_input(&[0xff]);
+        assert_eq!(dec.decode_all().unwrap_err().context(), Some("msg 3 field 7"));
+
+        let (_, report) = decode_with_report(&[], params, 0, &options).unwrap();
+        assert_eq!(report.context.as_deref(), Some("msg 3 field 7"));
+        assert!(decode(&[0xff], params, 8).unwrap_err().context().is_none());
     }
 }
@@ -0,0 +1,81 @@
+//! Peak heap usage estimates, for schedulers packing many concurrent decodes per node.
+
+use crate::decoder::{bytes_per_sample, id_len, validate_params};
+use crate::error::AecError;
+use crate::geometry;
+use crate::options::DecodeOptions;
+use crate::params::AecParams;
+
+/// How a payload is going to be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// [`crate::decode`]: the returned output `Vec` plus one block of scratch space.
+    OneShot,
+    /// [`crate::Decoder`] fed `input_chunk` bytes per [`crate::Decoder::push_input`]; output
+    /// buffers are the caller's.
+    Streaming { input_chunk: usize },
+    /// The `rayon` pipeline over `threads` workers, each holding one RSI of input; the output
+    /// grid is the caller's.
+    Parallel { threads: usize },
+}
+
+/// Expected peak heap usage, in bytes, of decoding `output_samples` samples in `mode`.
+///
+/// The estimate covers the decoder's own allocations, assuming the encoder never codes a block
+/// larger than its uncompressed form (true for libaec and this crate's reference streams). Caller
+/// buffers (the input, and output except in [`DecodeMode::OneShot`]) are not included.
+pub fn estimate_memory(params: AecParams, output_samples: usize, mode: DecodeMode) -> Result<usize, AecError> {
+    validate_params(params, &DecodeOptions::default())?;
+    let block_size = params.block_size as usize;
+    let bits = params.bits_per_sample as usize;
+
+    // Worst-case coded block: option id, reference sample and `block_size` raw samples.
+    let block_bytes = (id_len(params)? + bits + block_size * bits).div_ceil(8);
+    let scratch = block_size * size_of::<u32>();
+    let pending = block_size * size_of::<i64>();
+
+    let bytes = match mode {
+        DecodeMode::OneShot => output_samples.saturating_mul(bytes_per_sample(params)?).saturating_add(scratch),
+        DecodeMode::Streaming { input_chunk } => {
+            // Consumed bytes are dropped once they make up half the buffer, and `Vec` growth
+            // may double the capacity on top of that.
+            let buffered = input_chunk.saturating_mul(2).saturating_add(block_bytes);
+            buffered.saturating_mul(2).saturating_add(scratch + pending)
+        }
+        DecodeMode::Parallel { threads } => {
+            let rsi_bytes = block_bytes.saturating_mul(params.rsi as usize);
+            let per_thread = rsi_bytes.saturating_add(scratch + pending);
+            let rsi_count = geometry::rsi_count(params, output_samples);
+            // Chunk table: start position plus (index, start, slice) per RSI.
+            let table = rsi_count.saturating_mul(5 * size_of::<usize>());
+            per_thread.saturating_mul(threads.max(1)).saturating_add(table)
+        }
+    };
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::AecFlags;
+
+    #[test]
+    fn estimates_scale_with_what_each_mode_holds() {
+        let params = AecParams::new(12, 32, 128, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+        let n = 1_000_000;
+
+        let one_shot = estimate_memory(params, n, DecodeMode::OneShot).unwrap();
+        assert_eq!(one_shot, 2 * n + 128);
+
+        let streaming = estimate_memory(params, n, DecodeMode::Streaming { input_chunk: 4096 }).unwrap();
+        assert_eq!(streaming, estimate_memory(params, 10 * n, DecodeMode::Streaming { input_chunk: 4096 }).unwrap());
+        assert!(streaming < one_shot);
+
+        let one = estimate_memory(params, n, DecodeMode::Parallel { threads: 1 }).unwrap();
+        let eight = estimate_memory(params, n, DecodeMode::Parallel { threads: 8 }).unwrap();
+        let table = n.div_ceil(32 * 128) * 5 * size_of::<usize>();
+        assert_eq!(eight - table, 8 * (one - table));
+
+        assert!(estimate_memory(AecParams::new(33, 32, 128, AecFlags::empty()), n, DecodeMode::OneShot).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use crate::error::AecError;
+
+/// Decoder options that are not part of the AEC bitstream parameters.
+///
+/// `DecodeOptions::default()` gives the same behaviour as [`crate::decode`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Accept block sizes outside the CCSDS set {8, 16, 32, 64}.
+    ///
+    /// Some non-WMO producers emit other block sizes; enabling this lets such archives be
+    /// recovered. Any non-zero block size is then accepted.
+    pub allow_nonstandard_block_size: bool,
+
+    /// Longest unary (fundamental sequence) run accepted before the stream is declared corrupt.
+    ///
+    /// `None` derives the limit from the parameters: `max(2^bits_per_sample - 1, 90)`, the
+    /// longest run a valid stream can contain.
+    pub max_unary_run: Option<u32>,
+
+    /// Reject payloads shorter than the smallest valid encoding of `output_samples` before
+    /// decoding starts.
+    ///
+    /// The bound assumes the best case (every segment a single zero-block run), so it never
+    /// rejects a valid stream, but it catches e.g. swapped payload/sample-count arguments
+    /// immediately. One-shot decoding only; the streaming [`crate::Decoder`] ignores it.
+    pub check_input_length: bool,
+
+    /// Maximum number of blocks decoded overall (per one-shot call, or over the lifetime of a
+    /// [`crate::Decoder`]); exceeding it fails with [`crate::AecError::LimitExceeded`].
+    ///
+    /// Blocks covered by a zero-block run count individually. Services decoding untrusted
+    /// uploads can use this to bound CPU time per request.
+    pub max_blocks: Option<u64>,
+
+    /// Maximum number of blocks decoded by a single [`crate::Decoder::decode`] call (or a single
+    /// one-shot call); exceeding it fails with [`crate::AecError::LimitExceeded`].
+    pub max_blocks_per_call: Option<u64>,
+
+    /// Opaque caller context (e.g. `"msg 12 field 3 @ 0x1f40"`) embedded in every error
+    /// ([`crate::AecError::Context`]) and report produced with these options, so batch jobs can
+    /// attribute failures without wrapping each error themselves.
+    pub context: Option<Arc<str>>,
+
+    /// Compute a CRC-32C of the decoded bytes while they are emitted and return it in
+    /// [`crate::DecodeReport::crc32c`], so archives can be verified against stored digests
+    /// without re-reading the output.
+    pub crc32c: bool,
+
+    /// Range the decoded sample values (after inverse preprocessing, sign-extended for signed
+    /// data) are expected to lie in. Samples outside it are counted in
+    /// [`crate::DecodeReport::out_of_range_samples`]; decoding itself is unaffected.
+    ///
+    /// A wrong `bits_per_sample` or flag set usually still decodes, into plausible-looking
+    /// garbage; a physical range check catches that. Convert a physical (scaled) GRIB2 range
+    /// with `grib2::Grib2Scaling::packed_range`.
+    pub expected_range: Option<RangeInclusive<i64>>,
+
+    /// Keep a running hash of decoder-visible state (RSI number, bit position after any
+    /// `PAD_RSI` padding, predictor value) at the end of every fully emitted RSI and return it
+    /// in [`crate::DecodeReport::state_hash`].
+    ///
+    /// The hash is platform-independent and the same for one-shot and streaming decoding, so
+    /// runs on different machines or crate versions can be compared when chasing a rare
+    /// discrepancy.
+    pub state_hash: bool,
+
+    /// Emit a substitute value for missing samples: those equal to one of the
+    /// [`MissingValues`] codes and, if [`Self::expected_range`] is set, those outside it.
+    ///
+    /// Only the output changes; the predictor, [`crate::DecodeReport`] counts and
+    /// [`crate::RsiStats`] see the decoded values.
+    pub missing_values: Option<MissingValues>,
+
+    /// Store `DATA_3BYTE` samples in 4 bytes each, like samples of 17 to 24 bits without the
+    /// flag, so the output can be viewed as `u32` words without repacking. Unsigned samples are
+    /// zero-extended, signed ones sign-extended to 32 bits. No effect without `DATA_3BYTE`.
+    pub widen_3byte: bool,
+}
+
+impl DecodeOptions {
+    pub(crate) fn tag(&self, e: AecError) -> AecError {
+        e.tagged(self.context.as_ref())
+    }
+}
+
+/// Missing-value handling for [`DecodeOptions::missing_values`].
+///
+/// Codes and the substitute are sample values after inverse preprocessing, sign-extended for
+/// signed data; the substitute is written like any sample, so it keeps its low
+/// `bits_per_sample` bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingValues {
+    /// Value emitted in place of every missing sample.
+    pub substitute: i64,
+    /// Primary missing-value code, if the producer marks missing points in the data (GRIB2
+    /// complex packing uses all ones).
+    pub primary: Option<i64>,
+    /// Secondary missing-value code (GRIB2: all ones minus one).
+    pub secondary: Option<i64>,
+}
+
+/// [`MissingValues`] with the range it replaces samples outside of.
+#[derive(Debug, Clone)]
+pub(crate) struct Substitution {
+    missing: MissingValues,
+    range: Option<RangeInclusive<i64>>,
+}
+
+impl Substitution {
+    pub(crate) fn new(options: &DecodeOptions) -> Option<Self> {
+        options.missing_values.map(|missing| Self { missing, range: options.expected_range.clone() })
+    }
+
+    /// The value to emit for the decoded sample `value`.
+    #[inline]
+    pub(crate) fn apply(&self, value: i64) -> i64 {
+        let m = &self.missing;
+        let missing = m.primary == Some(value)
+            || m.secondary == Some(value)
+            || self.range.as_ref().is_some_and(|r| !r.contains(&value));
+        if missing { m.substitute } else { value }
+    }
+}
+
+/// Encoder options that are not part of the AEC bitstream parameters.
+///
+/// `EncodeOptions::default()` gives the same behaviour as [`crate::encode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// How hard the encoder searches for the cheapest coding option of each block.
+    pub level: EncodeLevel,
+}
+
+/// Speed/ratio trade-off of the encoder's block option search, like zlib's levels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EncodeLevel {
+    /// Estimate the split parameter from the block mean and evaluate only that `k`; the Second
+    /// Extension is only tried for blocks averaging below one. Streams typically grow by a
+    /// fraction of a percent, for real-time producers that cannot afford the full search.
+    Fast,
+    /// Size every option the stream allows and take the cheapest.
+    #[default]
+    Best,
+}
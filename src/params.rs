@@ -1,10 +1,19 @@
 use bitflags::bitflags;
 
+use crate::decoder::{id_len, validate_params};
+use crate::encoder::encode_samples;
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+
+/// Samples [`AecParams::suggest`] trial-encodes.
+const SUGGEST_SAMPLES: usize = 1 << 15;
+
 bitflags! {
     /// AEC flags (mirrors `libaec`'s `aec_stream.flags`).
     ///
     /// For GRIB2 template 5.42, a subset of these flags is provided in the
-    /// `ccsdsFlags` field; see [`crate::flags_from_grib2_ccsds_flags`].
+    /// `ccsdsFlags` field; see [`flags_from_grib2_ccsds_flags`] and its inverse
+    /// [`flags_to_grib2_ccsds_flags`].
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct AecFlags: u32 {
         /// Signed samples (two's complement). If not set, samples are unsigned.
@@ -22,7 +31,57 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Byte order of multi-byte output samples, selected by [`AecFlags::MSB`].
+///
+/// Samples are packed with shifts, so the output bytes are the same on every host. Only
+/// reinterpreting them as `u16`/`u32` (`from_ne_bytes`, slice casts, GPU uploads) depends on the
+/// host; decode with [`ByteOrder::NATIVE`] for that instead of assuming little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Least significant byte first (`MSB` clear).
+    LittleEndian,
+    /// Most significant byte first (`MSB` set).
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// Byte order of the compilation target.
+    pub const NATIVE: Self = if cfg!(target_endian = "big") { Self::BigEndian } else { Self::LittleEndian };
+
+    /// The byte order `flags` select.
+    pub fn from_flags(flags: AecFlags) -> Self {
+        if flags.contains(AecFlags::MSB) { Self::BigEndian } else { Self::LittleEndian }
+    }
+
+    /// Store the low `out.len()` bytes of `raw` into `out` in this order.
+    #[inline]
+    pub(crate) fn put(self, raw: u64, out: &mut [u8]) {
+        let n = out.len();
+        match self {
+            Self::BigEndian => {
+                for (i, b) in out.iter_mut().enumerate() {
+                    *b = (raw >> ((n - 1 - i) * 8)) as u8;
+                }
+            }
+            Self::LittleEndian => {
+                for (i, b) in out.iter_mut().enumerate() {
+                    *b = (raw >> (i * 8)) as u8;
+                }
+            }
+        }
+    }
+
+    /// Load `bytes` (at most 8) in this order; the inverse of [`Self::put`].
+    #[inline]
+    pub(crate) fn get(self, bytes: &[u8]) -> u64 {
+        match self {
+            Self::BigEndian => bytes.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b)),
+            Self::LittleEndian => bytes.iter().rev().fold(0, |acc, &b| (acc << 8) | u64::from(b)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AecParams {
     /// Bits per sample.
     ///
@@ -45,4 +104,284 @@ impl AecParams {
     pub fn new(bits_per_sample: u8, block_size: u32, rsi: u32, flags: AecFlags) -> Self {
         Self { bits_per_sample, block_size, rsi, flags }
     }
+
+    /// Create a new parameter set, failing fast if the decoder would reject it.
+    ///
+    /// For the stricter CCSDS 121.0-B-3 checks, follow up with [`AecParams::validate_strict`].
+    ///
+    /// ```
+    /// use rust_aec::{AecFlags, AecParams};
+    ///
+    /// let params = AecParams::try_new(12, 32, 128, AecFlags::DATA_PREPROCESS)?;
+    /// params.validate_strict()?;
+    ///
+    /// assert!(AecParams::try_new(12, 24, 128, AecFlags::empty()).is_err());
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn try_new(bits_per_sample: u8, block_size: u32, rsi: u32, flags: AecFlags) -> Result<Self, AecError> {
+        let params = Self::new(bits_per_sample, block_size, rsi, flags);
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Parameters of a GRIB2 field from its template 5.42 Section 5, starting at the section's
+    /// length octets.
+    ///
+    /// [`crate::grib2::Section5Params::parse`] also returns the number of values and the
+    /// scaling (reference value, binary and decimal scale factors) needed to unpack the field.
+    #[cfg(feature = "grib2")]
+    pub fn from_grib2_drt42(section5: &[u8]) -> Result<Self, AecError> {
+        crate::grib2::Section5Params::parse(section5).map(|s| s.params())
+    }
+
+    /// Byte order of the output samples.
+    pub fn byte_order(&self) -> ByteOrder {
+        ByteOrder::from_flags(self.flags)
+    }
+
+    /// The same parameters with output in `order` (sets or clears [`AecFlags::MSB`]).
+    pub fn with_byte_order(self, order: ByteOrder) -> Self {
+        let mut flags = self.flags;
+        flags.set(AecFlags::MSB, order == ByteOrder::BigEndian);
+        Self { flags, ..self }
+    }
+
+    /// Upper bound on the size of [`crate::encode`] output for `num_samples` samples, for sizing
+    /// the buffer of [`crate::encode_into`].
+    ///
+    /// The encoder never codes a block in more bits than the uncompressed option (option id plus
+    /// `block_size` raw samples), so the bound is that for every block, plus the byte padding
+    /// after each complete RSI with [`AecFlags::PAD_RSI`] and at the end of the stream.
+    ///
+    /// ```
+    /// use rust_aec::{encode_into, AecFlags, AecParams};
+    ///
+    /// let params = AecParams::new(12, 32, 128, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+    /// let samples = vec![0u8; 2 * 1000];
+    /// let mut out = vec![0u8; params.max_encoded_size(1000)];
+    /// let written = encode_into(&samples, params, &mut out)?;
+    /// assert!(written <= out.len());
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn max_encoded_size(&self, num_samples: usize) -> usize {
+        let block_size = (self.block_size as usize).max(1);
+        let rsi = (self.rsi as usize).max(1);
+        let id_len = id_len(*self).unwrap_or(5);
+        let block_bits = id_len.saturating_add(block_size.saturating_mul(usize::from(self.bits_per_sample)));
+        let bytes = |blocks: usize| blocks.saturating_mul(block_bits).div_ceil(8);
+
+        let blocks = num_samples.div_ceil(block_size);
+        if self.flags.contains(AecFlags::PAD_RSI) {
+            (blocks / rsi).saturating_mul(bytes(rsi)).saturating_add(bytes(blocks % rsi))
+        } else {
+            bytes(blocks)
+        }
+    }
+
+    /// Parameters that compress `samples` well, for data without an established CCSDS setup.
+    ///
+    /// `bits_per_sample` is the width of the largest sample. Block size (8 to 64), RSI (16 to
+    /// 128 blocks) and [`AecFlags::DATA_PREPROCESS`] are chosen by encoding the first 32768
+    /// samples with each combination and keeping the smallest result; on ties the GRIB2-typical
+    /// 32-sample blocks and 128-block RSIs win. No other flags are set, so samples are packed
+    /// little-endian.
+    ///
+    /// ```
+    /// use rust_aec::{encode_samples, AecFlags, AecParams};
+    ///
+    /// let smooth: Vec<u16> = (0..10_000).map(|i| (3000.0 + (i as f64 / 40.0).sin() * 800.0) as u16).collect();
+    /// let params = AecParams::suggest(&smooth);
+    /// assert_eq!(params.bits_per_sample, 12);
+    /// assert!(params.flags.contains(AecFlags::DATA_PREPROCESS));
+    /// assert!(encode_samples(&smooth, params)?.len() < smooth.len());
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn suggest(samples: &[u16]) -> Self {
+        let max = samples.iter().copied().max().unwrap_or(0);
+        let bits_per_sample = (16 - max.leading_zeros()).max(1) as u8;
+        let trial = &samples[..samples.len().min(SUGGEST_SAMPLES)];
+
+        let mut best = (usize::MAX, Self::new(bits_per_sample, 32, 128, AecFlags::DATA_PREPROCESS));
+        for flags in [AecFlags::DATA_PREPROCESS, AecFlags::empty()] {
+            for block_size in [32, 16, 64, 8] {
+                for rsi in [128, 64, 32, 16] {
+                    let params = Self::new(bits_per_sample, block_size, rsi, flags);
+                    let size = encode_samples(trial, params).map_or(usize::MAX, |stream| stream.len());
+                    if size < best.0 {
+                        best = (size, params);
+                    }
+                }
+            }
+        }
+        best.1
+    }
+
+    /// Run the same validation as the decoder with default [`DecodeOptions`].
+    pub fn validate(&self) -> Result<(), AecError> {
+        validate_params(*self, &DecodeOptions::default())
+    }
+
+    /// Like [`AecParams::validate`], plus stricter CCSDS 121.0-B-3 / libaec configuration rules
+    /// the decoder itself tolerates:
+    ///
+    /// - `rsi` must not exceed 4096 blocks;
+    /// - `DATA_3BYTE` is only meaningful for 17..=24 bits per sample;
+    /// - `RESTRICTED` is only defined for up to 4 bits per sample.
+    pub fn validate_strict(&self) -> Result<(), AecError> {
+        self.validate()?;
+
+        if self.rsi > 4096 {
+            return Err(AecError::InvalidInput("rsi must be <= 4096"));
+        }
+        if self.flags.contains(AecFlags::DATA_3BYTE) && !(17..=24).contains(&self.bits_per_sample) {
+            return Err(AecError::InvalidInput("DATA_3BYTE requires 17..=24 bits_per_sample"));
+        }
+        if self.flags.contains(AecFlags::RESTRICTED) && self.bits_per_sample > 4 {
+            return Err(AecError::InvalidInput("RESTRICTED requires bits_per_sample <= 4"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert GRIB2 `ccsdsFlags` (template 5.42) to `AecFlags`.
+pub fn flags_from_grib2_ccsds_flags(ccsds_flags: u8) -> AecFlags {
+    let mut flags = AecFlags::empty();
+
+    if (ccsds_flags & (1 << 0)) != 0 {
+        flags |= AecFlags::DATA_SIGNED;
+    }
+    if (ccsds_flags & (1 << 1)) != 0 {
+        flags |= AecFlags::DATA_3BYTE;
+    }
+    if (ccsds_flags & (1 << 2)) != 0 {
+        flags |= AecFlags::MSB;
+    }
+    if (ccsds_flags & (1 << 3)) != 0 {
+        flags |= AecFlags::DATA_PREPROCESS;
+    }
+    if (ccsds_flags & (1 << 4)) != 0 {
+        flags |= AecFlags::RESTRICTED;
+    }
+    if (ccsds_flags & (1 << 5)) != 0 {
+        flags |= AecFlags::PAD_RSI;
+    }
+
+    flags
+}
+
+/// Convert `AecFlags` to GRIB2 `ccsdsFlags` (template 5.42), the inverse of
+/// [`flags_from_grib2_ccsds_flags`].
+pub fn flags_to_grib2_ccsds_flags(flags: AecFlags) -> u8 {
+    let mut ccsds_flags = 0u8;
+
+    if flags.contains(AecFlags::DATA_SIGNED) {
+        ccsds_flags |= 1 << 0;
+    }
+    if flags.contains(AecFlags::DATA_3BYTE) {
+        ccsds_flags |= 1 << 1;
+    }
+    if flags.contains(AecFlags::MSB) {
+        ccsds_flags |= 1 << 2;
+    }
+    if flags.contains(AecFlags::DATA_PREPROCESS) {
+        ccsds_flags |= 1 << 3;
+    }
+    if flags.contains(AecFlags::RESTRICTED) {
+        ccsds_flags |= 1 << 4;
+    }
+    if flags.contains(AecFlags::PAD_RSI) {
+        ccsds_flags |= 1 << 5;
+    }
+
+    ccsds_flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_new_rejects_what_the_decoder_rejects() {
+        assert!(AecParams::try_new(0, 32, 128, AecFlags::empty()).is_ok());
+        assert!(AecParams::try_new(33, 32, 128, AecFlags::empty()).is_err());
+        assert!(AecParams::try_new(12, 32, 0, AecFlags::empty()).is_err());
+        assert!(AecParams::try_new(12, 32, 128, AecFlags::empty()).is_ok());
+    }
+
+    #[test]
+    fn flags_mapping_smoke() {
+        let f = flags_from_grib2_ccsds_flags(0b0011_1011);
+        assert!(f.contains(AecFlags::DATA_SIGNED));
+        assert!(f.contains(AecFlags::DATA_3BYTE));
+        assert!(!f.contains(AecFlags::MSB));
+        assert!(f.contains(AecFlags::DATA_PREPROCESS));
+        assert!(f.contains(AecFlags::RESTRICTED));
+        assert!(f.contains(AecFlags::PAD_RSI));
+    }
+
+    #[test]
+    fn ccsds_flags_round_trip() {
+        for ccsds_flags in 0..64u8 {
+            let flags = flags_from_grib2_ccsds_flags(ccsds_flags);
+            assert_eq!(flags_to_grib2_ccsds_flags(flags), ccsds_flags);
+            assert_eq!(flags_from_grib2_ccsds_flags(flags_to_grib2_ccsds_flags(flags)), flags);
+        }
+        // Bits 6 and 7 are reserved and not carried over.
+        assert_eq!(flags_to_grib2_ccsds_flags(flags_from_grib2_ccsds_flags(0xce)), 0x0e);
+    }
+
+    #[test]
+    fn suggest_prefers_preprocessing_only_where_it_helps() {
+        let ramp: Vec<u16> = (0..5000).map(|i| i * 13).collect();
+        let params = AecParams::suggest(&ramp);
+        assert_eq!(params.bits_per_sample, 16);
+        assert!(params.flags.contains(AecFlags::DATA_PREPROCESS));
+
+        // Small values scattered around zero gain nothing from differencing.
+        let mut x = 0x2545_f491_4f6c_dd1du64;
+        let noise: Vec<u16> = (0..5000)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                (x >> 61) as u16
+            })
+            .collect();
+        let params = AecParams::suggest(&noise);
+        assert_eq!(params.bits_per_sample, 3);
+        assert!(!params.flags.contains(AecFlags::DATA_PREPROCESS));
+
+        assert_eq!(AecParams::suggest(&[]), AecParams::new(1, 32, 128, AecFlags::DATA_PREPROCESS));
+    }
+
+    #[test]
+    fn byte_order_maps_to_the_msb_flag() {
+        let params = AecParams::new(16, 32, 128, AecFlags::DATA_PREPROCESS);
+        assert_eq!(params.byte_order(), ByteOrder::LittleEndian);
+        let big = params.with_byte_order(ByteOrder::BigEndian);
+        assert_eq!(big.flags, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+        assert_eq!(big.with_byte_order(ByteOrder::LittleEndian), params);
+
+        let mut out = [0u8; 3];
+        ByteOrder::BigEndian.put(0x0a0b0c, &mut out);
+        assert_eq!(out, [0x0a, 0x0b, 0x0c]);
+        ByteOrder::LittleEndian.put(0x0a0b0c, &mut out);
+        assert_eq!(out, [0x0c, 0x0b, 0x0a]);
+        let mut native = [0u8; 2];
+        ByteOrder::NATIVE.put(0x1234, &mut native);
+        assert_eq!(u16::from_ne_bytes(native), 0x1234);
+    }
+
+    #[test]
+    fn strict_checks() {
+        let ok = AecParams::new(20, 16, 4096, AecFlags::DATA_3BYTE);
+        assert!(ok.validate_strict().is_ok());
+
+        assert!(AecParams::new(12, 16, 4097, AecFlags::empty()).validate_strict().is_err());
+        assert!(AecParams::new(12, 16, 64, AecFlags::DATA_3BYTE).validate_strict().is_err());
+        assert!(AecParams::new(8, 16, 64, AecFlags::RESTRICTED).validate_strict().is_err());
+        // The relaxed check still accepts these.
+        assert!(AecParams::new(8, 16, 64, AecFlags::RESTRICTED).validate().is_ok());
+    }
 }
@@ -0,0 +1,119 @@
+//! Parallel GRIB2 field decoding and encoding (feature `rayon`).
+//!
+//! [`decode_grid_f32`] decodes every RSI on its own thread, scales each value and places it at
+//! its bitmap position in one pass, so the `f32` grid is written exactly once instead of after
+//! three sequential full-grid passes (decode, scale, bitmap expansion). [`encode_parallel`]
+//! codes the RSIs of a `PAD_RSI` stream on separate threads.
+
+use rayon::prelude::*;
+
+use crate::bitmap::Bitmap;
+use crate::bitwriter::BitWriter;
+use crate::decoder::{bytes_per_sample, validate_params, Decoder};
+use crate::encoder::{read_samples, RsiEncoder};
+use crate::error::AecError;
+use crate::geometry;
+use crate::grib2::Grib2Scaling;
+use crate::index::RsiIndex;
+use crate::options::{DecodeOptions, EncodeOptions};
+use crate::params::{AecFlags, AecParams};
+
+/// Decode a GRIB2 field straight into its scaled `f32` grid.
+///
+/// - `index`: RSI index of `input` (see [`RsiIndex::build`]); it fixes the number of coded
+///   values and lets every RSI be decoded independently.
+/// - `bitmap`: GRIB2 Section 6 bitmap (MSB-first, one bit per grid point), or `None` if every
+///   grid point has a value. Points without a value are set to `missing`.
+/// - `grid`: one `f32` per grid point.
+pub fn decode_grid_f32(
+    input: &[u8],
+    params: AecParams,
+    index: &RsiIndex,
+    scaling: Grib2Scaling,
+    bitmap: Option<&[u8]>,
+    missing: f32,
+    grid: &mut [f32],
+) -> Result<(), AecError> {
+    validate_params(params, &DecodeOptions::default())?;
+    index.check_params(params)?;
+    let values = index.output_samples();
+    let bitmap = bitmap.map(|b| Bitmap::new(b, grid.len())).transpose()?;
+    if bitmap.is_none() && grid.len() != values {
+        return Err(AecError::InvalidInput("grid size does not match the index"));
+    }
+    let present = |i: usize| bitmap.is_none_or(|b| b.is_present(i));
+
+    // Grid position of the first value of every RSI; each RSI owns the points up to the next one.
+    let per_rsi = index.samples_per_rsi();
+    let mut starts = Vec::with_capacity(index.len());
+    let mut seen = 0usize;
+    for i in 0..grid.len() {
+        if present(i) {
+            if seen % per_rsi == 0 {
+                starts.push(i);
+            }
+            seen += 1;
+        }
+    }
+    if seen != values || starts.len() != index.len() {
+        return Err(AecError::InvalidInput("bitmap does not match the number of coded values"));
+    }
+
+    let mut chunks = Vec::with_capacity(starts.len());
+    let (leading, mut rest) = grid.split_at_mut(starts.first().copied().unwrap_or(grid.len()));
+    leading.fill(missing);
+    for (n, &start) in starts.iter().enumerate() {
+        let end = starts.get(n + 1).copied().unwrap_or(start + rest.len());
+        let (chunk, tail) = rest.split_at_mut(end - start);
+        chunks.push((n, start, chunk));
+        rest = tail;
+    }
+
+    let (offset, factor) = scaling.coefficients();
+    chunks.into_par_iter().try_for_each(|(n, start, chunk)| {
+        let first = index.first_sample(n);
+        let mut dec = Decoder::with_index(params, per_rsi.min(values - first), index, n)?;
+        let from = index.byte_offset(n).unwrap_or(input.len()).min(input.len());
+        let to = index.byte_offset(n + 1).map_or(input.len(), |b| (b + 1).min(input.len()));
+        dec.push_input(&input[from..to]);
+
+        let mut pos = 0usize;
+        dec.decode_values(|x| {
+            while !present(start + pos) {
+                chunk[pos] = missing;
+                pos += 1;
+            }
+            chunk[pos] = (offset + x as f64 * factor) as f32;
+            pos += 1;
+        })?;
+        chunk[pos..].fill(missing);
+        Ok(())
+    })
+}
+
+/// Like [`crate::encode`], coding RSIs on all rayon threads; the stream is byte-identical.
+///
+/// With `AecFlags::PAD_RSI` every RSI starts on a byte and is coded independently, so the
+/// per-RSI streams are simply concatenated. Without it RSIs share bytes, and this encodes on
+/// the calling thread like [`crate::encode`].
+pub fn encode_parallel(input: &[u8], params: AecParams) -> Result<Vec<u8>, AecError> {
+    if !params.flags.contains(AecFlags::PAD_RSI) {
+        return crate::encode(input, params);
+    }
+    let encoder = RsiEncoder::new(params, &EncodeOptions::default())?;
+    let rsi_bytes = geometry::samples_per_rsi(params).saturating_mul(bytes_per_sample(params)?);
+    let parts = input
+        .par_chunks(rsi_bytes)
+        .map_init(
+            || (encoder.clone(), Vec::new()),
+            |(encoder, values), chunk| {
+                values.clear();
+                read_samples(params, chunk, values)?;
+                let mut w = BitWriter::new();
+                encoder.encode_rsi(&mut w, values);
+                Ok(w.finish())
+            },
+        )
+        .collect::<Result<Vec<_>, AecError>>()?;
+    Ok(parts.concat())
+}
@@ -0,0 +1,93 @@
+//! Zero-copy views of decoded samples with `bytemuck` (feature `bytemuck`).
+//!
+//! [`crate::decode`] returns bytes, whose allocation carries no alignment for wider samples, so
+//! a `&[u8]` of 16-bit samples cannot always be viewed as `&[u16]`. The functions here either
+//! check the view ([`cast_samples`]) or decode straight into a typed buffer through its bytes
+//! ([`decode_into_pod`]); neither copies nor needs `unsafe` in the caller.
+//!
+//! Views reinterpret bytes in the host's order, so the output must be decoded in
+//! [`crate::ByteOrder::NATIVE`] (see [`crate::AecParams::with_byte_order`]).
+
+use bytemuck::{Pod, PodCastError};
+
+use crate::decoder::{self, bytes_per_sample};
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+use crate::params::{AecParams, ByteOrder};
+use crate::sample::{check_sample_type, Sample};
+
+/// View decoded bytes as samples of `T`, without copying.
+///
+/// Fails with [`AecError::InvalidInput`] if `bytes` is not aligned for `T` or its length is not
+/// a multiple of the sample size.
+pub fn cast_samples<T: Sample + Pod>(bytes: &[u8]) -> Result<&[T], AecError> {
+    bytemuck::try_cast_slice(bytes).map_err(cast_error)
+}
+
+/// Mutable counterpart of [`cast_samples`].
+pub fn cast_samples_mut<T: Sample + Pod>(bytes: &mut [u8]) -> Result<&mut [T], AecError> {
+    bytemuck::try_cast_slice_mut(bytes).map_err(cast_error)
+}
+
+/// Decode `output.len()` samples into `output` through its bytes, in the host's byte order.
+///
+/// `T` must hold every value `params` allows and be exactly as wide as a decoded sample (so
+/// 24-bit samples need `u32` without [`crate::AecFlags::DATA_3BYTE`]). The values are those
+/// [`crate::decode`] produces: signed samples narrower than `T` stay raw two's complement
+/// fields; [`crate::decode_samples`] sign-extends them.
+///
+/// ```
+/// use rust_aec::pod::decode_into_pod;
+/// use rust_aec::{encode_samples, AecFlags, AecParams};
+///
+/// let params = AecParams::new(16, 32, 128, AecFlags::DATA_PREPROCESS);
+/// let field: Vec<u16> = (0..1000).map(|i| 300 + i % 50).collect();
+/// let stream = encode_samples(&field, params)?;
+/// let mut grid = vec![0u16; field.len()];
+/// decode_into_pod(&stream, params, &mut grid)?;
+/// assert_eq!(grid, field);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+pub fn decode_into_pod<T: Sample + Pod>(input: &[u8], params: AecParams, output: &mut [T]) -> Result<(), AecError> {
+    check_sample_type::<T>(params)?;
+    if bytes_per_sample(params)? != size_of::<T>() {
+        return Err(AecError::InvalidInput("sample type width differs from the decoded sample size"));
+    }
+    let params = params.with_byte_order(ByteOrder::NATIVE);
+    decoder::decode_into(input, params, output.len(), bytemuck::cast_slice_mut(output), &DecodeOptions::default())
+}
+
+fn cast_error(e: PodCastError) -> AecError {
+    match e {
+        PodCastError::OutputSliceWouldHaveSlop => {
+            AecError::InvalidInput("byte length is not a multiple of the sample size")
+        }
+        _ => AecError::InvalidInput("byte buffer is not aligned for the sample type"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::AecFlags;
+
+    #[test]
+    fn views_check_alignment_and_length() {
+        let params = AecParams::new(12, 16, 8, AecFlags::DATA_PREPROCESS).with_byte_order(ByteOrder::NATIVE);
+        let samples: Vec<u16> = (0..200).map(|i| (i * 19) % 4096).collect();
+        let stream = crate::encode_samples(&samples, params).unwrap();
+
+        let mut words = vec![0u32; 101];
+        let bytes = bytemuck::cast_slice_mut::<u32, u8>(&mut words);
+        crate::decode_into(&stream, params, samples.len(), &mut bytes[..400]).unwrap();
+        assert_eq!(cast_samples::<u16>(&bytes[..400]).unwrap(), samples);
+        assert!(cast_samples::<u16>(&bytes[1..401]).is_err());
+        assert!(cast_samples::<u16>(&bytes[..399]).is_err());
+
+        let mut grid = vec![0u16; samples.len()];
+        decode_into_pod(&stream, params.with_byte_order(ByteOrder::BigEndian), &mut grid).unwrap();
+        assert_eq!(grid, samples);
+        assert!(decode_into_pod(&stream, params, &mut vec![0u32; samples.len()]).is_err());
+        assert!(decode_into_pod(&stream, params, &mut vec![0u8; samples.len()]).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! Sample preprocessing utilities shared with the decoder.
+//!
+//! These are the exact routines the decoder uses, exposed so validation tools can recompute
+//! expected deltas and reconstructed values bit-for-bit.
+
+use crate::params::{AecFlags, AecParams};
+
+/// Reconstruct the next sample from the previous one and a coded (folded) value.
+///
+/// This is the inverse of the CCSDS unit-delay predictor plus fold mapping, exactly as the
+/// decoder applies it with [`AecFlags::DATA_PREPROCESS`]. `x_prev` is the previous sample
+/// value (sign-extended for signed data) and `d` the coded value read from the stream.
+pub fn inverse_preprocess_step(x_prev: i64, d: u32, params: AecParams) -> i64 {
+    let n = params.bits_per_sample;
+
+    // Match libaec inverse preprocessing exactly (see vendor/libaec.../src/decode.c).
+    // The coded value `d` is mapped to a signed delta using the LSB as sign, but the
+    // application of that delta is bounded; if it would cross the selected boundary,
+    // a reflection mapping is used instead.
+    let delta: i64 = ((d >> 1) as i64) ^ (!(((d & 1) as i64) - 1));
+    let half_d: i64 = ((d >> 1) + (d & 1)) as i64;
+
+    if params.flags.contains(AecFlags::DATA_SIGNED) {
+        // signed_max matches libaec state->xmax for signed data.
+        let signed_max: i64 = (1i64 << (n - 1)) - 1;
+        let data = x_prev;
+
+        if data < 0 {
+            if half_d <= signed_max + data + 1 {
+                data + delta
+            } else {
+                (d as i64) - signed_max - 1
+            }
+        } else {
+            if half_d <= signed_max - data {
+                data + delta
+            } else {
+                signed_max - (d as i64)
+            }
+        }
+    } else {
+        let unsigned_max: u64 = (1u64 << n) - 1;
+        let data_u: u64 = x_prev as u64;
+
+        // med is a single bit (the MSB) for unsigned samples.
+        let med: u64 = unsigned_max / 2 + 1;
+        let mask: u64 = if (data_u & med) != 0 { unsigned_max } else { 0 };
+
+        if (half_d as u64) <= (mask ^ data_u) {
+            x_prev + delta
+        } else {
+            (mask ^ (d as u64)) as i64
+        }
+    }
+}
+
+/// Map a sample to its coded value, given the previous sample: the unit-delay predictor plus
+/// fold mapping the encoder applies with [`AecFlags::DATA_PREPROCESS`].
+///
+/// Deltas within `theta` (the distance from `x_prev` to the nearer end of the sample range) fold
+/// to `2 * delta` or `2 * |delta| - 1`; larger ones to `theta + |delta|`.
+/// [`inverse_preprocess_step`] undoes it. Both samples must lie in the range of
+/// `bits_per_sample` (sign-extended for signed data).
+pub fn preprocess_step(x_prev: i64, x: i64, params: AecParams) -> u32 {
+    let n = params.bits_per_sample;
+    let (x_min, x_max) = if params.flags.contains(AecFlags::DATA_SIGNED) {
+        (-(1i64 << (n - 1)), (1i64 << (n - 1)) - 1)
+    } else {
+        (0, (1i64 << n) - 1)
+    };
+    let delta = x - x_prev;
+    let theta = (x_prev - x_min).min(x_max - x_prev);
+    let d = if delta.abs() > theta {
+        theta + delta.abs()
+    } else if delta >= 0 {
+        2 * delta
+    } else {
+        -2 * delta - 1
+    };
+    d as u32
+}
+
+/// Sign-extend the low `bits` bits of `raw` (two's complement) to `i64`.
+///
+/// `bits` must be in `1..=32`.
+pub fn sign_extend(raw: u32, bits: u8) -> i64 {
+    if bits == 32 {
+        return (raw as i32) as i64;
+    }
+    let shift = 32 - bits as u32;
+    (((raw << shift) as i32) >> shift) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_extend_edges() {
+        assert_eq!(sign_extend(0b0111, 4), 7);
+        assert_eq!(sign_extend(0b1000, 4), -8);
+        assert_eq!(sign_extend(0xffff_ffff, 32), -1);
+        assert_eq!(sign_extend(1, 1), -1);
+    }
+
+    #[test]
+    fn preprocess_step_is_inverted_exactly() {
+        for flags in [AecFlags::DATA_PREPROCESS, AecFlags::DATA_PREPROCESS | AecFlags::DATA_SIGNED] {
+            for n in [1u8, 2, 3, 5] {
+                let params = AecParams::new(n, 8, 1, flags);
+                let range = if flags.contains(AecFlags::DATA_SIGNED) {
+                    -(1i64 << (n - 1))..(1i64 << (n - 1))
+                } else {
+                    0..(1i64 << n)
+                };
+                for x_prev in range.clone() {
+                    for x in range.clone() {
+                        let d = preprocess_step(x_prev, x, params);
+                        assert!(u64::from(d) < 1u64 << n);
+                        assert_eq!(inverse_preprocess_step(x_prev, d, params), x, "{params:?} {x_prev} -> {x}");
+                    }
+                }
+            }
+        }
+        let wide = AecParams::new(32, 8, 1, AecFlags::DATA_PREPROCESS);
+        assert_eq!(preprocess_step(0, u32::MAX as i64, wide), u32::MAX);
+    }
+
+    #[test]
+    fn inverse_step_small_deltas() {
+        let params = AecParams::new(8, 32, 128, AecFlags::DATA_PREPROCESS);
+        // Even coded values are non-negative deltas, odd ones negative.
+        assert_eq!(inverse_preprocess_step(100, 0, params), 100);
+        assert_eq!(inverse_preprocess_step(100, 4, params), 102);
+        assert_eq!(inverse_preprocess_step(100, 3, params), 98);
+        // Near the top of the range the mapping reflects instead of overflowing.
+        assert_eq!(inverse_preprocess_step(254, 10, params), 245);
+    }
+}
@@ -0,0 +1,134 @@
+//! Bitstream facts gathered while decoding and encoding.
+
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use crate::decoder::RsiStats;
+
+/// Where decoding stopped in the payload and how much alignment padding it skipped.
+///
+/// Operational validators use this to confirm producer conformance: a libaec-compatible
+/// producer pads with `PAD_RSI` only at RSI boundaries and leaves no trailing bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeReport {
+    /// Bits read from the payload, including padding.
+    pub bits_consumed: usize,
+    /// Bits skipped by `PAD_RSI` byte alignment at RSI boundaries.
+    pub padding_bits: usize,
+    /// Unread bits left in the last byte touched (0 when decoding ended on a byte boundary).
+    pub final_alignment_bits: u8,
+    /// Whole payload bytes after the last byte touched.
+    pub trailing_bytes: usize,
+    /// [`crate::DecodeOptions::context`] of the decode call.
+    pub context: Option<Arc<str>>,
+    /// CRC-32C of the decoded bytes, if [`crate::DecodeOptions::crc32c`] was set. For the
+    /// streaming [`crate::Decoder`] it covers the bytes handed out so far.
+    pub crc32c: Option<u32>,
+    /// Samples outside [`crate::DecodeOptions::expected_range`] (0 when no range was given).
+    pub out_of_range_samples: usize,
+    /// Index of the first such sample.
+    pub first_out_of_range: Option<usize>,
+    /// Decoder state hash, if [`crate::DecodeOptions::state_hash`] was set.
+    pub state_hash: Option<u64>,
+}
+
+impl DecodeReport {
+    pub(crate) fn new(bits_consumed: usize, padding_bits: usize, input_bytes: usize) -> Self {
+        let bytes_touched = bits_consumed.div_ceil(8);
+        Self {
+            bits_consumed,
+            padding_bits,
+            final_alignment_bits: ((8 - bits_consumed % 8) % 8) as u8,
+            trailing_bytes: input_bytes.saturating_sub(bytes_touched),
+            context: None,
+            crc32c: None,
+            out_of_range_samples: 0,
+            first_out_of_range: None,
+            state_hash: None,
+        }
+    }
+}
+
+/// A decoded field with what is needed to interpret it, from [`crate::decode_field`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodedField {
+    /// The samples, one per value; signed samples in 32-bit two's complement (cast with
+    /// `as i32`).
+    pub samples: Vec<u32>,
+    /// Significant bits of every sample.
+    pub bits_per_sample: u8,
+    /// Whether the samples are signed (`AecFlags::DATA_SIGNED`).
+    pub signed: bool,
+    /// Payload bytes the field occupies, including a partly used last byte.
+    pub bytes_consumed: usize,
+    /// Statistics of every reference sample interval, in order.
+    pub stats: Vec<RsiStats>,
+}
+
+/// Coding options the encoder chose, for tuning `block_size` and `rsi` to a kind of data.
+///
+/// Returned by [`crate::encode_with_stats`] and [`crate::Encoder::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeStats {
+    /// Samples encoded.
+    pub samples: usize,
+    /// Zero-block runs.
+    pub zero_runs: u64,
+    /// Blocks covered by zero-block runs.
+    pub zero_blocks: u64,
+    /// Second Extension blocks.
+    pub second_extension_blocks: u64,
+    /// Rice split blocks.
+    pub split_blocks: u64,
+    /// Sum of the split parameter `k` over all split blocks.
+    pub split_k_sum: u64,
+    /// Uncompressed blocks.
+    pub uncompressed_blocks: u64,
+    /// Packed sample bytes encoded.
+    pub input_bytes: usize,
+    /// Encoded bytes (for [`crate::Encoder`], those handed out so far).
+    pub output_bytes: usize,
+}
+
+impl EncodeStats {
+    /// Average split parameter over the split blocks, `None` without any.
+    pub fn average_k(&self) -> Option<f64> {
+        (self.split_blocks > 0).then(|| self.split_k_sum as f64 / self.split_blocks as f64)
+    }
+
+    /// Encoded bytes per packed input byte (0.25 for 4:1 compression); 0 for empty input.
+    pub fn ratio(&self) -> f64 {
+        if self.input_bytes == 0 { 0.0 } else { self.output_bytes as f64 / self.input_bytes as f64 }
+    }
+}
+
+/// Counts decoded samples outside [`crate::DecodeOptions::expected_range`].
+#[derive(Debug, Clone)]
+pub(crate) struct RangeCheck {
+    range: RangeInclusive<i64>,
+    count: usize,
+    first: Option<usize>,
+}
+
+impl RangeCheck {
+    pub(crate) fn new(range: Option<&RangeInclusive<i64>>) -> Option<Self> {
+        range.map(|range| Self { range: range.clone(), count: 0, first: None })
+    }
+
+    /// Check sample number `index`.
+    #[inline]
+    pub(crate) fn observe(&mut self, index: usize, value: i64) {
+        if !self.range.contains(&value) {
+            self.count += 1;
+            self.first.get_or_insert(index);
+        }
+    }
+
+    /// Record the findings in `report`.
+    pub(crate) fn report(check: Option<&Self>, report: DecodeReport) -> DecodeReport {
+        match check {
+            Some(c) => DecodeReport { out_of_range_samples: c.count, first_out_of_range: c.first, ..report },
+            None => report,
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Golomb–Rice coding of unsigned integer sequences, outside the CCSDS block framing.
+//!
+//! A value `v` is coded with parameter `k` as the fundamental sequence of `v >> k` (that many
+//! zero bits, then a one) followed by its low `k` bits, MSB first. The CCSDS split option lays
+//! a block out as all fundamental sequences first, then all remainders
+//! ([`RiceLayout::Split`]); most other formats keep each value's two parts together
+//! ([`RiceLayout::Interleaved`]). Both take the same number of bits, [`encoded_bits`]. The
+//! AEC encoder and [`crate::decode_block`] code their split blocks with these routines.
+//!
+//! ```
+//! use rust_aec::rice::{best_k, decode, encode, RiceLayout};
+//!
+//! let values = [3, 9, 0, 14, 5, 7, 2, 11];
+//! let k = best_k(&values, 8);
+//! let bytes = encode(&values, k, RiceLayout::Interleaved)?;
+//! assert_eq!(decode(&bytes, k, values.len(), RiceLayout::Interleaved)?, values);
+//! # Ok::<(), rust_aec::AecError>(())
+//! ```
+
+use crate::bitreader::BitReader;
+use crate::bitwriter::BitWriter;
+use crate::error::AecError;
+
+/// Largest supported Rice parameter.
+pub const MAX_K: u32 = 31;
+
+/// Order of the unary and binary parts of a coded sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RiceLayout {
+    /// Each value's fundamental sequence directly followed by its `k` low bits.
+    Interleaved,
+    /// All fundamental sequences, then all `k`-bit remainders (the CCSDS split option).
+    Split,
+}
+
+/// Bits of `values` coded with parameter `k`, in either layout.
+pub fn encoded_bits(values: &[u32], k: u32) -> u64 {
+    values.iter().map(|&v| u64::from(v >> k) + 1).sum::<u64>() + values.len() as u64 * u64::from(k)
+}
+
+/// The parameter in `0..=max_k` coding `values` in the fewest bits (the smallest on ties).
+pub fn best_k(values: &[u32], max_k: u32) -> u32 {
+    (0..=max_k.min(MAX_K)).min_by_key(|&k| encoded_bits(values, k)).unwrap_or(0)
+}
+
+/// Code `values` with parameter `k`, padded with zero bits to a whole byte.
+pub fn encode(values: &[u32], k: u32, layout: RiceLayout) -> Result<Vec<u8>, AecError> {
+    check_k(k)?;
+    let mut w = BitWriter::new();
+    write_values(&mut w, values, k, layout);
+    Ok(w.finish())
+}
+
+/// Decode `count` values coded with parameter `k`; trailing bits are ignored.
+pub fn decode(input: &[u8], k: u32, count: usize, layout: RiceLayout) -> Result<Vec<u32>, AecError> {
+    check_k(k)?;
+    let mut values = vec![0u32; count];
+    read_values(&mut BitReader::new(input), k, layout, u32::MAX >> k, &mut values)?;
+    Ok(values)
+}
+
+/// Read a fundamental sequence: the number of zero bits before the next one bit.
+///
+/// Runs longer than `limit` zero bits fail with [`AecError::InvalidInput`], so corrupt input
+/// is caught before the end of the data.
+pub fn read_unary(r: &mut BitReader<'_>, limit: u32) -> Result<u32, AecError> {
+    r.read_unary(limit)
+}
+
+/// Read `values.len()` values coded with parameter `k` from `r`, accepting quotients of at most
+/// `max_quotient`.
+pub fn read_values(
+    r: &mut BitReader<'_>,
+    k: u32,
+    layout: RiceLayout,
+    max_quotient: u32,
+    values: &mut [u32],
+) -> Result<(), AecError> {
+    let quotient = |r: &mut BitReader<'_>| {
+        let q = read_unary(r, max_quotient)?;
+        q.checked_shl(k).ok_or(AecError::InvalidInput("rice shift overflow"))
+    };
+    match layout {
+        RiceLayout::Interleaved => {
+            for v in values.iter_mut() {
+                *v = quotient(r)? | r.read_bits_u32(k as usize)?;
+            }
+        }
+        RiceLayout::Split => {
+            for v in values.iter_mut() {
+                *v = quotient(r)?;
+            }
+            if k > 0 {
+                for v in values.iter_mut() {
+                    *v |= r.read_bits_u32(k as usize)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write `values` coded with parameter `k` to `w`.
+pub(crate) fn write_values(w: &mut BitWriter, values: &[u32], k: u32, layout: RiceLayout) {
+    match layout {
+        RiceLayout::Interleaved => {
+            for &v in values {
+                w.put_unary(u64::from(v >> k));
+                w.put(v, k);
+            }
+        }
+        RiceLayout::Split => {
+            for &v in values {
+                w.put_unary(u64::from(v >> k));
+            }
+            for &v in values {
+                w.put(v, k);
+            }
+        }
+    }
+}
+
+fn check_k(k: u32) -> Result<(), AecError> {
+    if k > MAX_K {
+        return Err(AecError::InvalidInput("rice parameter k must be at most 31"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_layouts_round_trip() {
+        // k = 2: 5 -> 01 01, 2 -> 1 10, 9 -> 001 01.
+        assert_eq!(encode(&[5, 2, 9], 2, RiceLayout::Interleaved).unwrap(), [0b0101_1100, 0b0101_0000]);
+        assert_eq!(encode(&[5, 2, 9], 2, RiceLayout::Split).unwrap(), [0b0110_0101, 0b1001_0000]);
+
+        let values: Vec<u32> = (0..500).map(|i| (i * 7919) % 1000).collect();
+        for layout in [RiceLayout::Interleaved, RiceLayout::Split] {
+            for k in [0, 3, 9, 31] {
+                let bytes = encode(&values, k, layout).unwrap();
+                assert_eq!(bytes.len() as u64, encoded_bits(&values, k).div_ceil(8));
+                assert_eq!(decode(&bytes, k, values.len(), layout).unwrap(), values, "{layout:?} k={k}");
+                assert!(decode(&bytes[..bytes.len() / 2], k, values.len(), layout).is_err());
+            }
+        }
+        assert_eq!(best_k(&values, 16), 8);
+        assert!(encode(&values, 32, RiceLayout::Split).is_err());
+    }
+
+    #[test]
+    fn quotients_past_the_value_range_are_rejected() {
+        // 2 zero bits then a one with k = 31 would need a 33-bit value.
+        let bytes = [0b0010_0000, 0, 0, 0, 0];
+        assert!(decode(&bytes, 31, 1, RiceLayout::Interleaved).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Integer sample types for the typed encode and decode entry points.
+
+use crate::error::AecError;
+use crate::params::{AecFlags, AecParams};
+
+/// Integer sample types [`crate::encode_samples`] accepts and [`crate::decode_samples`]
+/// returns: `u8`, `u16`, `u32`, `i8`, `i16` and `i32`.
+pub trait Sample: Copy {
+    /// Width of the type in bits.
+    const BITS: u8;
+    /// Whether the type holds negative values.
+    const SIGNED: bool;
+
+    /// The sample's numeric value.
+    fn to_i64(self) -> i64;
+
+    /// `value` truncated to the type.
+    fn from_i64(value: i64) -> Self;
+}
+
+macro_rules! impl_sample {
+    ($($t:ty),*) => {$(
+        impl Sample for $t {
+            const BITS: u8 = <$t>::BITS as u8;
+            const SIGNED: bool = <$t>::MIN != 0;
+
+            fn to_i64(self) -> i64 {
+                i64::from(self)
+            }
+
+            fn from_i64(value: i64) -> Self {
+                value as $t
+            }
+        }
+    )*};
+}
+
+impl_sample!(u8, u16, u32, i8, i16, i32);
+
+/// Check that `T` holds every sample value of `params`: signed data needs a signed type of at
+/// least `bits_per_sample` bits, unsigned data a type with that many value bits.
+pub(crate) fn check_sample_type<T: Sample>(params: AecParams) -> Result<(), AecError> {
+    let fits = if params.flags.contains(AecFlags::DATA_SIGNED) {
+        T::SIGNED && params.bits_per_sample <= T::BITS
+    } else {
+        params.bits_per_sample <= T::BITS - u8::from(T::SIGNED)
+    };
+    if fits { Ok(()) } else { Err(AecError::InvalidInput("sample type cannot hold bits_per_sample values")) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_types_must_hold_every_value() {
+        let params = |bits, flags| AecParams::new(bits, 32, 128, flags);
+        assert!(check_sample_type::<u16>(params(16, AecFlags::empty())).is_ok());
+        assert!(check_sample_type::<i16>(params(16, AecFlags::empty())).is_err());
+        assert!(check_sample_type::<i16>(params(15, AecFlags::empty())).is_ok());
+        assert!(check_sample_type::<i16>(params(16, AecFlags::DATA_SIGNED)).is_ok());
+        assert!(check_sample_type::<u32>(params(12, AecFlags::DATA_SIGNED)).is_err());
+        assert!(check_sample_type::<u8>(params(9, AecFlags::empty())).is_err());
+        assert_eq!((i8::BITS, i8::SIGNED, u32::BITS, u32::SIGNED), (8, true, 32, false));
+    }
+}
@@ -0,0 +1,131 @@
+//! GRIB2 scanning modes: the order of the grid points in a field (feature `grib2`).
+//!
+//! Decoded values come in the order Section 3 declares (code table 3.4): rows or columns
+//! first, scanned in either direction, possibly alternating. [`ScanningMode::to_canonical`]
+//! reorders them into one layout, north to south and west to east, row by row — scanning
+//! mode 0 — so grids from different producers can be indexed alike.
+
+use crate::error::AecError;
+
+/// A GRIB2 scanning mode (code table 3.4), as the flag octet of Section 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScanningMode(pub u8);
+
+impl ScanningMode {
+    /// Rows run east to west (flag bit 1).
+    pub fn negative_i(self) -> bool {
+        self.0 & 0x80 != 0
+    }
+
+    /// Columns run south to north (flag bit 2).
+    pub fn positive_j(self) -> bool {
+        self.0 & 0x40 != 0
+    }
+
+    /// Points are consecutive along columns rather than rows (flag bit 3).
+    pub fn column_major(self) -> bool {
+        self.0 & 0x20 != 0
+    }
+
+    /// Every other row (or column) runs the opposite way (flag bit 4).
+    pub fn boustrophedonic(self) -> bool {
+        self.0 & 0x10 != 0
+    }
+
+    /// Reorder the `ni * nj` `values` of a grid `ni` points wide and `nj` points high, scanned in
+    /// this mode, into rows from north to south of points from west to east.
+    ///
+    /// ```
+    /// use rust_aec::scanning::ScanningMode;
+    ///
+    /// // A 3x2 grid scanned from the south-west corner, rows alternating.
+    /// let scanned = [4, 5, 6, 3, 2, 1];
+    /// assert_eq!(ScanningMode(0x50).to_canonical(&scanned, 3, 2)?, [1, 2, 3, 4, 5, 6]);
+    /// # Ok::<(), rust_aec::AecError>(())
+    /// ```
+    pub fn to_canonical<T: Copy>(self, values: &[T], ni: usize, nj: usize) -> Result<Vec<T>, AecError> {
+        if ni.checked_mul(nj) != Some(values.len()) {
+            return Err(AecError::InvalidInput("grid shape does not match the number of values"));
+        }
+        let mut grid = values.to_vec();
+        // Lines are the runs of consecutive points: rows, or columns if column major.
+        let line_len = if self.column_major() { nj } else { ni };
+        for (k, &value) in values.iter().enumerate() {
+            let (line, mut pos) = (k / line_len, k % line_len);
+            if self.boustrophedonic() && line % 2 == 1 {
+                pos = line_len - 1 - pos;
+            }
+            let (i, j) = if self.column_major() { (line, pos) } else { (pos, line) };
+            let col = if self.negative_i() { ni - 1 - i } else { i };
+            let row = if self.positive_j() { nj - 1 - j } else { j };
+            grid[row * ni + col] = value;
+        }
+        Ok(grid)
+    }
+}
+
+/// Grid shape `(ni, nj)` and scanning mode from a GRIB2 Section 3, starting at its length
+/// octets.
+///
+/// Supports the grid definition templates with a regular `ni * nj` grid: 3.0 (latitude/longitude),
+/// 3.10 (Mercator), 3.20 (polar stereographic), 3.30 (Lambert conformal) and 3.40 (Gaussian).
+/// Reduced grids (`ni` or `nj` missing) are [`AecError::Unsupported`].
+pub fn grid_from_section3(section3: &[u8]) -> Result<(usize, usize, ScanningMode), AecError> {
+    let s = section3;
+    if s.len() < 14 || s[4] != 3 {
+        return Err(AecError::InvalidInput("not a GRIB2 Section 3"));
+    }
+    let scanning = match u16::from_be_bytes([s[12], s[13]]) {
+        0 | 40 => 71,
+        10 => 59,
+        20 | 30 => 64,
+        _ => return Err(AecError::Unsupported("GRIB2 grid definition template")),
+    };
+    let s = s.get(..=scanning).ok_or(AecError::InvalidInput("GRIB2 Section 3 is truncated"))?;
+    let ni = u32::from_be_bytes([s[30], s[31], s[32], s[33]]);
+    let nj = u32::from_be_bytes([s[34], s[35], s[36], s[37]]);
+    if ni == u32::MAX || nj == u32::MAX {
+        return Err(AecError::Unsupported("GRIB2 reduced grid"));
+    }
+    Ok((ni as usize, nj as usize, ScanningMode(s[scanning])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_scanning_mode_reorders_to_rows_from_the_north_west() {
+        // Canonical 3x2 grid: 0 1 2 / 3 4 5, north row first.
+        let cases: [(u8, [u8; 6]); 8] = [
+            (0x00, [0, 1, 2, 3, 4, 5]),
+            (0x80, [2, 1, 0, 5, 4, 3]),
+            (0x40, [3, 4, 5, 0, 1, 2]),
+            (0xc0, [5, 4, 3, 2, 1, 0]),
+            (0x10, [0, 1, 2, 5, 4, 3]),
+            (0x20, [0, 3, 1, 4, 2, 5]),
+            (0x60, [3, 0, 4, 1, 5, 2]),
+            (0xb0, [2, 5, 4, 1, 0, 3]),
+        ];
+        for (mode, scanned) in cases {
+            assert_eq!(ScanningMode(mode).to_canonical(&scanned, 3, 2).unwrap(), [0, 1, 2, 3, 4, 5], "{mode:#x}");
+        }
+        assert!(ScanningMode(0).to_canonical(&[0; 5], 3, 2).is_err());
+    }
+
+    #[test]
+    fn section3_gives_the_shape_and_scanning_mode() {
+        let mut s3 = vec![0u8; 72];
+        s3[..4].copy_from_slice(&72u32.to_be_bytes());
+        s3[4] = 3;
+        s3[30..34].copy_from_slice(&360u32.to_be_bytes());
+        s3[34..38].copy_from_slice(&181u32.to_be_bytes());
+        s3[71] = 0x40;
+        assert_eq!(grid_from_section3(&s3).unwrap(), (360, 181, ScanningMode(0x40)));
+        assert!(grid_from_section3(&s3[..71]).is_err());
+        s3[30..34].fill(0xff);
+        assert!(matches!(grid_from_section3(&s3), Err(AecError::Unsupported(_))));
+        s3[13] = 90;
+        assert!(matches!(grid_from_section3(&s3), Err(AecError::Unsupported(_))));
+    }
+}
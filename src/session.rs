@@ -0,0 +1,87 @@
+//! Reusable decode session for many payloads sharing one parameter set.
+
+use crate::decoder::{decode_into_ctx, DecodeContext};
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+use crate::params::AecParams;
+use crate::aec_codec::AecCodec;
+
+/// Decode session caching parameter-derived state and scratch space across calls.
+///
+/// Workloads that decode thousands of fields with identical [`AecParams`] (e.g. every message
+/// of a model run) can create one session and call [`AecSession::decode_into`] repeatedly,
+/// avoiding per-call validation, setup and scratch allocations.
+///
+/// ```
+/// use rust_aec::{flags_from_grib2_ccsds_flags, AecParams, AecSession};
+///
+/// let params = AecParams::new(12, 32, 128, flags_from_grib2_ccsds_flags(0x0e));
+/// let mut session = AecSession::new(params)?;
+///
+/// let mut out = Vec::new();
+/// for (payload, num_points) in [(Vec::<u8>::new(), 0usize)] {
+///     out.resize(session.output_len(num_points)?, 0);
+///     session.decode_into(&payload, num_points, &mut out)?;
+/// }
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AecSession {
+    ctx: DecodeContext,
+    scratch: Vec<u32>,
+}
+
+impl AecSession {
+    /// Create a session, validating `params` once.
+    pub fn new(params: AecParams) -> Result<Self, AecError> {
+        Self::with_options(params, &DecodeOptions::default())
+    }
+
+    /// Create a session with non-default [`DecodeOptions`].
+    pub fn with_options(params: AecParams, options: &DecodeOptions) -> Result<Self, AecError> {
+        let ctx = DecodeContext::new(params, options)?;
+        let scratch = Vec::with_capacity(params.block_size as usize);
+        Ok(Self { ctx, scratch })
+    }
+
+    /// Parameters this session decodes with.
+    pub fn params(&self) -> AecParams {
+        self.ctx.params
+    }
+
+    /// Output bytes per sample.
+    pub fn bytes_per_sample(&self) -> usize {
+        self.ctx.bytes_per_sample
+    }
+
+    /// Required output buffer length for `output_samples` samples.
+    pub fn output_len(&self, output_samples: usize) -> Result<usize, AecError> {
+        output_samples
+            .checked_mul(self.ctx.bytes_per_sample)
+            .ok_or(AecError::InvalidInput("output too large"))
+    }
+
+    /// Decode `input` into `output`; see [`crate::decode_into`] for the buffer contract.
+    pub fn decode_into(&mut self, input: &[u8], output_samples: usize, output: &mut [u8]) -> Result<(), AecError> {
+        decode_into_ctx(&self.ctx, input, output_samples, output, &mut self.scratch)?;
+        Ok(())
+    }
+
+    /// Decode `input` into a newly allocated buffer.
+    pub fn decode(&mut self, input: &[u8], output_samples: usize) -> Result<Vec<u8>, AecError> {
+        let mut out = vec![0u8; self.output_len(output_samples)?];
+        self.decode_into(input, output_samples, &mut out)?;
+        Ok(out)
+    }
+}
+
+impl AecCodec for AecSession {
+    fn params(&self) -> AecParams {
+        self.ctx.params
+    }
+
+    /// Decode with the session's cached state, like [`AecSession::decode`].
+    fn decode_payload(&mut self, payload: &[u8], output_samples: usize) -> Result<Vec<u8>, AecError> {
+        self.decode(payload, output_samples)
+    }
+}
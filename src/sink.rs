@@ -0,0 +1,654 @@
+//! Output sinks for reconstructed samples.
+//!
+//! The decoders produce reconstructed sample values (`i64`, sign-extended for signed data);
+//! a sink turns them into the caller's output representation.
+
+#[cfg(feature = "io")]
+use std::io::Write;
+
+#[cfg(feature = "io")]
+use crate::checksum::Crc32c;
+use crate::decoder::bytes_per_sample;
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+use crate::params::{AecFlags, AecParams, ByteOrder};
+
+/// Destination for reconstructed samples.
+pub(crate) trait OutputSink {
+    /// Number of further samples the sink can accept right now.
+    fn remaining(&self) -> usize;
+
+    /// Accept one sample; only called while `remaining() > 0`.
+    fn put(&mut self, value: i64) -> Result<(), AecError>;
+
+    /// Accept a prefix of `values`, up to `remaining()` samples; returns how many were taken.
+    fn put_slice(&mut self, values: &[i64]) -> Result<usize, AecError> {
+        let n = values.len().min(self.remaining());
+        for &value in &values[..n] {
+            self.put(value)?;
+        }
+        Ok(n)
+    }
+}
+
+/// Packed byte layout of one output sample.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SampleFormat {
+    pub(crate) bytes_per_sample: usize,
+    mask: u64,
+    signed: bool,
+    order: ByteOrder,
+}
+
+impl SampleFormat {
+    pub(crate) fn new(params: AecParams, bytes_per_sample: usize) -> Self {
+        let n = params.bits_per_sample as u32;
+        Self {
+            bytes_per_sample,
+            mask: if n >= 64 { u64::MAX } else { (1u64 << n) - 1 },
+            signed: params.flags.contains(AecFlags::DATA_SIGNED),
+            order: params.byte_order(),
+        }
+    }
+
+    /// The layout of decoded output under `options`.
+    pub(crate) fn for_output(params: AecParams, options: &DecodeOptions) -> Result<Self, AecError> {
+        if options.widen_3byte && params.flags.contains(AecFlags::DATA_3BYTE) {
+            // A sign-extended value masked to the whole word is its 32-bit two's complement.
+            let mask = if params.flags.contains(AecFlags::DATA_SIGNED) { u64::from(u32::MAX) } else { 0xff_ffff };
+            return Ok(Self { mask, ..Self::new(params, 4) });
+        }
+        Ok(Self::new(params, bytes_per_sample(params)?))
+    }
+
+    /// Write `value` into `out`, which must be exactly `bytes_per_sample` bytes long.
+    #[inline]
+    pub(crate) fn write(&self, value: i64, out: &mut [u8]) {
+        let raw_u = if self.signed { (value as u64) & self.mask } else { (value.max(0) as u64) & self.mask };
+        self.order.put(raw_u, out);
+    }
+
+    /// Write every value of `values` into `out`, which must be exactly
+    /// `values.len() * bytes_per_sample` bytes long.
+    pub(crate) fn write_slice(&self, values: &[i64], out: &mut [u8]) {
+        debug_assert_eq!(out.len(), values.len() * self.bytes_per_sample);
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        let (values, out) = wasm_simd::write_prefix(self, values, out);
+        #[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+        let (values, out) = sse2::write_prefix(self, values, out);
+        self.write_each(values, out);
+    }
+
+    /// [`SampleFormat::write_slice`] without vector packing.
+    fn write_each(&self, values: &[i64], out: &mut [u8]) {
+        for (&value, dst) in values.iter().zip(out.chunks_exact_mut(self.bytes_per_sample)) {
+            self.write(value, dst);
+        }
+    }
+}
+
+/// simd128 packing of up to 16-bit samples, four per step. Clamping and masking an `i32` lane
+/// match [`SampleFormat::write`] only for values within `i32`; groups holding other values
+/// (substituted missing values) are written one sample at a time.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm_simd {
+    use core::arch::wasm32::*;
+
+    use super::{ByteOrder, SampleFormat};
+
+    /// Pack the longest prefix of whole four-sample groups; returns the unpacked rest.
+    pub(super) fn write_prefix<'v, 'o>(
+        format: &SampleFormat,
+        values: &'v [i64],
+        out: &'o mut [u8],
+    ) -> (&'v [i64], &'o mut [u8]) {
+        let bps = format.bytes_per_sample;
+        if !(bps == 1 || bps == 2) || format.mask > 0xffff {
+            return (values, out);
+        }
+        let groups = values.len() / 4;
+        let mask = i32x4_splat(format.mask as i32);
+        let zero = i32x4_splat(0);
+        for g in 0..groups {
+            let (group, dst) = (&values[g * 4..(g + 1) * 4], &mut out[g * 4 * bps..(g + 1) * 4 * bps]);
+            if group.iter().any(|&v| i32::try_from(v).is_err()) {
+                format.write_each(group, dst);
+                continue;
+            }
+            // SAFETY: the loads read `group` and the stores write `dst`, both four samples long;
+            // wasm loads and stores have no alignment requirement.
+            unsafe {
+                let src = group.as_ptr();
+                let lo = v128_load(src.cast::<v128>());
+                let hi = v128_load(src.add(2).cast::<v128>());
+                // Low 32 bits of each `i64` lane.
+                let mut v = i32x4_shuffle::<0, 2, 4, 6>(lo, hi);
+                if !format.signed {
+                    v = i32x4_max(v, zero);
+                }
+                v = v128_and(v, mask);
+                let dst = dst.as_mut_ptr();
+                if bps == 1 {
+                    let packed = i8x16_shuffle::<0, 4, 8, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0>(v, v);
+                    v128_store32_lane::<0>(packed, dst.cast::<u32>());
+                } else if format.order == ByteOrder::BigEndian {
+                    let packed = i8x16_shuffle::<1, 0, 5, 4, 9, 8, 13, 12, 0, 0, 0, 0, 0, 0, 0, 0>(v, v);
+                    v128_store64_lane::<0>(packed, dst.cast::<u64>());
+                } else {
+                    let packed = i8x16_shuffle::<0, 1, 4, 5, 8, 9, 12, 13, 0, 0, 0, 0, 0, 0, 0, 0>(v, v);
+                    v128_store64_lane::<0>(packed, dst.cast::<u64>());
+                }
+            }
+        }
+        let (_, rest) = out.split_at_mut(groups * 4 * bps);
+        (&values[groups * 4..], rest)
+    }
+}
+
+/// SSE2 packing of up to 16-bit samples, eight per step, with one 16-byte store for 16-bit
+/// output (byte-swapped in the register for [`ByteOrder::BigEndian`]). SSE2 is part of the
+/// x86_64 baseline, so no runtime detection is needed there.
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "sse2"))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    use super::{ByteOrder, SampleFormat};
+
+    /// Pack the longest prefix of whole eight-sample groups; returns the unpacked rest.
+    pub(super) fn write_prefix<'v, 'o>(
+        format: &SampleFormat,
+        values: &'v [i64],
+        out: &'o mut [u8],
+    ) -> (&'v [i64], &'o mut [u8]) {
+        let bps = format.bytes_per_sample;
+        if !(bps == 1 || bps == 2) || format.mask > 0xffff {
+            return (values, out);
+        }
+        let groups = values.len() / 8;
+        // SAFETY: SSE2 is enabled (cfg above); every load reads from `group` and every store
+        // writes to `dst`, both eight samples long, and the unaligned load/store intrinsics have
+        // no alignment requirement.
+        unsafe {
+            let mask = _mm_set1_epi32(format.mask as i32);
+            let zero = _mm_setzero_si128();
+            // Four samples as clamped, masked 32-bit lanes, like `SampleFormat::write` for values
+            // within `i32`.
+            let lanes = |src: *const i64| {
+                let lo = _mm_loadu_si128(src.cast::<__m128i>());
+                let hi = _mm_loadu_si128(src.add(2).cast::<__m128i>());
+                // Low 32 bits of each `i64` lane.
+                let mut v = _mm_unpacklo_epi64(_mm_shuffle_epi32::<0b10_00>(lo), _mm_shuffle_epi32::<0b10_00>(hi));
+                if !format.signed {
+                    v = _mm_and_si128(v, _mm_cmpgt_epi32(v, zero));
+                }
+                v = _mm_and_si128(v, mask);
+                // Sign-extend the low 16 bits so the saturating pack keeps them as they are.
+                _mm_srai_epi32::<16>(_mm_slli_epi32::<16>(v))
+            };
+            for g in 0..groups {
+                let (group, dst) = (&values[g * 8..(g + 1) * 8], &mut out[g * 8 * bps..(g + 1) * 8 * bps]);
+                // Substituted missing values can lie outside `i32`, where the low 32 bits clamp
+                // differently from the whole value.
+                if group.iter().any(|&v| i32::try_from(v).is_err()) {
+                    format.write_each(group, dst);
+                    continue;
+                }
+                let src = group.as_ptr();
+                let mut packed = _mm_packs_epi32(lanes(src), lanes(src.add(4)));
+                let dst = dst.as_mut_ptr();
+                if bps == 1 {
+                    _mm_storel_epi64(dst.cast::<__m128i>(), _mm_packus_epi16(packed, packed));
+                    continue;
+                }
+                if format.order == ByteOrder::BigEndian {
+                    packed = _mm_or_si128(_mm_slli_epi16::<8>(packed), _mm_srli_epi16::<8>(packed));
+                }
+                _mm_storeu_si128(dst.cast::<__m128i>(), packed);
+            }
+        }
+        let (_, rest) = out.split_at_mut(groups * 8 * bps);
+        (&values[groups * 8..], rest)
+    }
+}
+
+/// Writes packed sample bytes into a caller slice.
+pub(crate) struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+    format: SampleFormat,
+}
+
+impl<'a> SliceSink<'a> {
+    pub(crate) fn new(buf: &'a mut [u8], format: SampleFormat) -> Self {
+        Self { buf, pos: 0, format }
+    }
+
+    /// Bytes written so far.
+    pub(crate) fn len(&self) -> usize {
+        self.pos
+    }
+}
+
+impl OutputSink for SliceSink<'_> {
+    fn remaining(&self) -> usize {
+        (self.buf.len() - self.pos) / self.format.bytes_per_sample
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        let end = self.pos + self.format.bytes_per_sample;
+        let dst = self.buf.get_mut(self.pos..end).ok_or(AecError::InvalidInput("output buffer too small"))?;
+        self.format.write(value, dst);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn put_slice(&mut self, values: &[i64]) -> Result<usize, AecError> {
+        let n = values.len().min(self.remaining());
+        let end = self.pos + n * self.format.bytes_per_sample;
+        self.format.write_slice(&values[..n], &mut self.buf[self.pos..end]);
+        self.pos = end;
+        Ok(n)
+    }
+}
+
+/// Writes samples into a typed slice; `convert` keeps the low bits of each value.
+pub(crate) struct TypedSliceSink<'a, T, F> {
+    buf: &'a mut [T],
+    pos: usize,
+    convert: F,
+}
+
+impl<'a, T, F: Fn(i64) -> T> TypedSliceSink<'a, T, F> {
+    pub(crate) fn new(buf: &'a mut [T], convert: F) -> Self {
+        Self { buf, pos: 0, convert }
+    }
+}
+
+impl<T, F: Fn(i64) -> T> OutputSink for TypedSliceSink<'_, T, F> {
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        let dst = self.buf.get_mut(self.pos).ok_or(AecError::InvalidInput("output buffer too small"))?;
+        *dst = (self.convert)(value);
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn put_slice(&mut self, values: &[i64]) -> Result<usize, AecError> {
+        let n = values.len().min(self.remaining());
+        for (dst, &v) in self.buf[self.pos..self.pos + n].iter_mut().zip(values) {
+            *dst = (self.convert)(v);
+        }
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Appends packed sample bytes to a `Vec`.
+pub(crate) struct VecSink<'a> {
+    vec: &'a mut Vec<u8>,
+    format: SampleFormat,
+}
+
+impl<'a> VecSink<'a> {
+    pub(crate) fn new(vec: &'a mut Vec<u8>, format: SampleFormat) -> Self {
+        Self { vec, format }
+    }
+}
+
+impl OutputSink for VecSink<'_> {
+    fn remaining(&self) -> usize {
+        usize::MAX
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        let start = self.vec.len();
+        self.vec.resize(start + self.format.bytes_per_sample, 0);
+        self.format.write(value, &mut self.vec[start..]);
+        Ok(())
+    }
+
+    fn put_slice(&mut self, values: &[i64]) -> Result<usize, AecError> {
+        let start = self.vec.len();
+        self.vec.resize(start + values.len() * self.format.bytes_per_sample, 0);
+        self.format.write_slice(values, &mut self.vec[start..]);
+        Ok(values.len())
+    }
+}
+
+/// Appends samples to a `Vec` packed at `bits_per_sample` bits each, MSB-first, as GRIB2 simple
+/// packing stores them. Call [`BitPackSink::finish`] to write the zero-padded last byte.
+pub(crate) struct BitPackSink<'a> {
+    vec: &'a mut Vec<u8>,
+    bits: u32,
+    mask: u64,
+    acc: u64,
+    nbits: u32,
+}
+
+impl<'a> BitPackSink<'a> {
+    pub(crate) fn new(vec: &'a mut Vec<u8>, params: AecParams) -> Self {
+        let bits = params.bits_per_sample as u32;
+        Self { vec, bits, mask: (1u64 << bits) - 1, acc: 0, nbits: 0 }
+    }
+
+    pub(crate) fn finish(self) {
+        if self.nbits > 0 {
+            self.vec.push((self.acc << (8 - self.nbits)) as u8);
+        }
+    }
+}
+
+impl OutputSink for BitPackSink<'_> {
+    fn remaining(&self) -> usize {
+        usize::MAX
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        // At most 7 + 32 bits are pending, so `acc` never overflows.
+        self.acc = (self.acc << self.bits) | (value as u64 & self.mask);
+        self.nbits += self.bits;
+        while self.nbits >= 8 {
+            self.nbits -= 8;
+            self.vec.push((self.acc >> self.nbits) as u8);
+        }
+        self.acc &= (1u64 << self.nbits) - 1;
+        Ok(())
+    }
+}
+
+/// Rounds samples to a lower bit depth (dropping `shift` low bits) before passing them on.
+pub(crate) struct RequantizeSink<S> {
+    inner: S,
+    shift: u32,
+    min: i64,
+    max: i64,
+}
+
+impl<S> RequantizeSink<S> {
+    /// Requantize `params.bits_per_sample`-bit samples to `target_bits` (at most as many).
+    pub(crate) fn new(inner: S, params: AecParams, target_bits: u8) -> Self {
+        let shift = (params.bits_per_sample - target_bits) as u32;
+        let (min, max) = if params.flags.contains(AecFlags::DATA_SIGNED) {
+            (-(1i64 << (target_bits - 1)), (1i64 << (target_bits - 1)) - 1)
+        } else {
+            (0, (1i64 << target_bits) - 1)
+        };
+        Self { inner, shift, min, max }
+    }
+}
+
+impl<S: OutputSink> OutputSink for RequantizeSink<S> {
+    fn remaining(&self) -> usize {
+        self.inner.remaining()
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        // Round half up; the top code would round past the target range, so clamp.
+        let rounded = if self.shift == 0 { value } else { (value + (1 << (self.shift - 1))) >> self.shift };
+        self.inner.put(rounded.clamp(self.min, self.max))
+    }
+}
+
+/// Writes the samples of a row-major `nx` × `ny` grid into a caller slice in column-major order.
+pub(crate) struct TransposeSink<'a> {
+    buf: &'a mut [u8],
+    format: SampleFormat,
+    nx: usize,
+    ny: usize,
+    row: usize,
+    col: usize,
+}
+
+impl<'a> TransposeSink<'a> {
+    /// `buf` must hold exactly `nx * ny` samples.
+    pub(crate) fn new(buf: &'a mut [u8], format: SampleFormat, nx: usize, ny: usize) -> Self {
+        Self { buf, format, nx, ny, row: 0, col: 0 }
+    }
+}
+
+impl OutputSink for TransposeSink<'_> {
+    fn remaining(&self) -> usize {
+        (self.ny - self.row) * self.nx - self.col
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        let bps = self.format.bytes_per_sample;
+        let start = (self.col * self.ny + self.row) * bps;
+        let dst = self.buf.get_mut(start..start + bps).ok_or(AecError::InvalidInput("output buffer too small"))?;
+        self.format.write(value, dst);
+        self.col += 1;
+        if self.col == self.nx {
+            self.col = 0;
+            self.row += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Passes every `step`-th sample on to `inner`, dropping the rest.
+pub(crate) struct StrideSink<S> {
+    inner: S,
+    step: usize,
+    phase: usize,
+}
+
+impl<S> StrideSink<S> {
+    pub(crate) fn new(inner: S, step: usize) -> Self {
+        Self { inner, step, phase: 0 }
+    }
+}
+
+impl<S: OutputSink> OutputSink for StrideSink<S> {
+    fn remaining(&self) -> usize {
+        // Samples dropped before the next kept one, then `step` per sample `inner` still takes.
+        let skipped = (self.step - self.phase) % self.step;
+        self.inner.remaining().saturating_mul(self.step).saturating_add(skipped)
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        if self.phase == 0 {
+            self.inner.put(value)?;
+        }
+        self.phase = (self.phase + 1) % self.step;
+        Ok(())
+    }
+}
+
+/// Streams packed sample bytes to an `io::Write`, staging them in a small buffer.
+///
+/// Call [`WriterSink::finish`] to flush the staged tail.
+#[cfg(feature = "io")]
+pub(crate) struct WriterSink<'a, W: Write + ?Sized> {
+    writer: &'a mut W,
+    staged: Vec<u8>,
+    written: usize,
+    format: SampleFormat,
+    crc32c: Option<&'a mut Crc32c>,
+}
+
+#[cfg(feature = "io")]
+const WRITER_STAGING: usize = 16 * 1024;
+
+#[cfg(feature = "io")]
+impl<'a, W: Write + ?Sized> WriterSink<'a, W> {
+    pub(crate) fn new(writer: &'a mut W, format: SampleFormat, crc32c: Option<&'a mut Crc32c>) -> Self {
+        Self { writer, staged: Vec::with_capacity(WRITER_STAGING), written: 0, format, crc32c }
+    }
+
+    fn flush_staged(&mut self) -> Result<(), AecError> {
+        self.writer.write_all(&self.staged)?;
+        if let Some(crc) = self.crc32c.as_mut() {
+            crc.update(&self.staged);
+        }
+        self.written += self.staged.len();
+        self.staged.clear();
+        Ok(())
+    }
+
+    /// Flush staged bytes and return the total number of bytes written.
+    pub(crate) fn finish(mut self) -> Result<usize, AecError> {
+        self.flush_staged()?;
+        Ok(self.written)
+    }
+}
+
+#[cfg(feature = "io")]
+impl<W: Write + ?Sized> OutputSink for WriterSink<'_, W> {
+    fn remaining(&self) -> usize {
+        usize::MAX
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        let start = self.staged.len();
+        self.staged.resize(start + self.format.bytes_per_sample, 0);
+        self.format.write(value, &mut self.staged[start..]);
+        if self.staged.len() >= WRITER_STAGING {
+            self.flush_staged()?;
+        }
+        Ok(())
+    }
+}
+
+/// Hands every sample to a closure.
+pub(crate) struct FnSink<F>(pub(crate) F);
+
+impl<F: FnMut(i64)> OutputSink for FnSink<F> {
+    fn remaining(&self) -> usize {
+        usize::MAX
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        (self.0)(value);
+        Ok(())
+    }
+}
+
+/// Appends up to `room` samples to a `Vec`.
+pub(crate) struct BatchSink<'a> {
+    pub(crate) out: &'a mut Vec<i64>,
+    pub(crate) room: usize,
+}
+
+impl OutputSink for BatchSink<'_> {
+    fn remaining(&self) -> usize {
+        self.room
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        self.out.push(value);
+        self.room -= 1;
+        Ok(())
+    }
+
+    fn put_slice(&mut self, values: &[i64]) -> Result<usize, AecError> {
+        let n = values.len().min(self.room);
+        self.out.extend_from_slice(&values[..n]);
+        self.room -= n;
+        Ok(n)
+    }
+}
+
+/// Holds at most one sample; used to split a sample across two caller buffers.
+#[derive(Default)]
+pub(crate) struct OneSampleSink {
+    pub(crate) value: Option<i64>,
+}
+
+impl OutputSink for OneSampleSink {
+    fn remaining(&self) -> usize {
+        usize::from(self.value.is_none())
+    }
+
+    fn put(&mut self, value: i64) -> Result<(), AecError> {
+        self.value = Some(value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requantizing_clamps_rounding_at_the_range_ends() {
+        let mut seen = Vec::new();
+        let unsigned = AecParams::new(12, 16, 8, AecFlags::empty());
+        let mut sink = RequantizeSink::new(FnSink(|v| seen.push(v)), unsigned, 8);
+        for v in [0, 7, 8, 4087, 4088, 4095] {
+            sink.put(v).unwrap();
+        }
+        let signed = AecParams::new(12, 16, 8, AecFlags::DATA_SIGNED);
+        let mut sink = RequantizeSink::new(FnSink(|v| seen.push(v)), signed, 8);
+        for v in [-2048, -9, -8, 2039, 2047] {
+            sink.put(v).unwrap();
+        }
+        assert_eq!(seen, [0, 0, 1, 255, 255, 255, -128, -1, 0, 127, 127]);
+    }
+
+    #[test]
+    fn write_slice_matches_per_sample_writes() {
+        let values: Vec<i64> = (-9..30).map(|i| i * 1237 - 4000).collect();
+        for bits in [3u8, 8, 12, 16, 24, 32] {
+            for flags in [AecFlags::empty(), AecFlags::DATA_SIGNED, AecFlags::MSB, AecFlags::DATA_SIGNED | AecFlags::MSB] {
+                let bps = usize::from(bits).div_ceil(8).next_power_of_two();
+                let format = SampleFormat::new(AecParams::new(bits, 16, 8, flags), bps);
+                let mut bulk = vec![0u8; values.len() * bps];
+                format.write_slice(&values, &mut bulk);
+                let mut single = vec![0u8; values.len() * bps];
+                for (&v, dst) in values.iter().zip(single.chunks_exact_mut(bps)) {
+                    format.write(v, dst);
+                }
+                assert_eq!(bulk, single, "bits {bits} flags {flags:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn vector_packing_keeps_the_top_bit_of_16_bit_samples() {
+        // Values past `i16::MAX`, past the mask and negative, in whole vector groups and a tail.
+        let pattern = [65535, 32768, 40000, 70000, -1, 0, 255, 32767];
+        let values: Vec<i64> = (0..37).map(|i| pattern[i % 8] + i as i64).collect();
+        for flags in [AecFlags::empty(), AecFlags::MSB, AecFlags::DATA_SIGNED | AecFlags::MSB] {
+            let format = SampleFormat::new(AecParams::new(16, 16, 8, flags), 2);
+            let mut bulk = vec![0u8; values.len() * 2];
+            format.write_slice(&values, &mut bulk);
+            let expected: Vec<u8> = values
+                .iter()
+                .flat_map(|&v| {
+                    let raw = if flags.contains(AecFlags::DATA_SIGNED) { v as u16 } else { v.max(0) as u16 };
+                    if flags.contains(AecFlags::MSB) { raw.to_be_bytes() } else { raw.to_le_bytes() }
+                })
+                .collect();
+            assert_eq!(bulk, expected, "flags {flags:?}");
+        }
+    }
+
+    #[test]
+    fn values_outside_i32_pack_like_single_writes() {
+        // Missing-value substitutes are arbitrary `i64`s; their low 32 bits clamp differently.
+        let pattern = [0x8000_1234, -0xffff_0000 + 5, 7, i64::MAX, i64::MIN, 1 << 40, 300, -2];
+        let values: Vec<i64> = (0..27).map(|i| if i < 16 { i as i64 } else { pattern[i % 8] }).collect();
+        for (bits, bps) in [(8, 1), (16, 2), (12, 2)] {
+            for flags in [AecFlags::empty(), AecFlags::MSB, AecFlags::DATA_SIGNED] {
+                let format = SampleFormat::new(AecParams::new(bits, 16, 8, flags), bps);
+                let mut bulk = vec![0u8; values.len() * bps];
+                format.write_slice(&values, &mut bulk);
+                let mut single = vec![0u8; values.len() * bps];
+                for (&v, dst) in values.iter().zip(single.chunks_exact_mut(bps)) {
+                    format.write(v, dst);
+                }
+                assert_eq!(bulk, single, "{bits} bits, flags {flags:?}");
+            }
+        }
+        let format = SampleFormat::new(AecParams::new(16, 16, 8, AecFlags::empty()), 2);
+        let mut out = [0u8; 16];
+        format.write_slice(&[0x8000_1234; 8], &mut out);
+        assert_eq!(out[..2], [0x34, 0x12]);
+    }
+}
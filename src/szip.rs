@@ -0,0 +1,259 @@
+//! SZIP compatibility: decoding the szip streams of the HDF5 / NetCDF-4 szip filter.
+//!
+//! libaec also stands in for the SZIP library behind HDF5's szip filter (filter id 4, used by
+//! NetCDF-4 too). Its parameters differ from [`AecParams`]: an SZIP options mask, bits per
+//! pixel, pixels per block and pixels per scanline. [`SzipParams`] maps them the way libaec's
+//! `SZ_BufftoBuffDecompress` does:
+//!
+//! - every scanline is its own RSI of `ceil(pixels_per_scanline / pixels_per_block)` blocks,
+//!   padded to whole blocks when the scanline is not a multiple of the block size (the
+//!   padding is dropped after decoding);
+//! - [`SZ_NN_OPTION_MASK`] selects [`AecFlags::DATA_PREPROCESS`] and [`SZ_MSB_OPTION_MASK`]
+//!   [`AecFlags::MSB`]; the other options do not change the stream;
+//! - 32- and 64-bit pixels are coded as 8-bit samples, byte plane after byte plane.
+//!
+//! [`decode_hdf5_chunk`] decodes a chunk as HDF5 stores it, after the 4-byte size it prepends.
+//!
+//! ```
+//! use rust_aec::szip::{decode_hdf5_chunk, SzipParams};
+//!
+//! // HDF5 filter client data: options mask, pixels per block, bits per pixel, pixels per scanline.
+//! let params = SzipParams::from_hdf5_cd_values(&[141, 32, 16, 256])?;
+//! assert_eq!(params.aec_params()?.rsi, 8);
+//!
+//! // An empty chunk.
+//! assert!(decode_hdf5_chunk(&[0, 0, 0, 0], &params)?.is_empty());
+//! # Ok::<(), rust_aec::AecError>(())
+//! ```
+
+use crate::decoder::{bytes_per_sample, decode};
+use crate::encode;
+use crate::error::AecError;
+use crate::options::DecodeOptions;
+use crate::params::{AecFlags, AecParams};
+
+/// Allow the k = 13 split option (has no effect on decoding).
+pub const SZ_ALLOW_K13_OPTION_MASK: u32 = 1;
+/// Chip mode (has no effect on decoding).
+pub const SZ_CHIP_OPTION_MASK: u32 = 2;
+/// Entropy coding without a predictor (the default unless [`SZ_NN_OPTION_MASK`] is set).
+pub const SZ_EC_OPTION_MASK: u32 = 4;
+/// Pixels are little-endian (the default unless [`SZ_MSB_OPTION_MASK`] is set).
+pub const SZ_LSB_OPTION_MASK: u32 = 8;
+/// Pixels are big-endian: [`AecFlags::MSB`].
+pub const SZ_MSB_OPTION_MASK: u32 = 16;
+/// Nearest-neighbour preprocessing: [`AecFlags::DATA_PREPROCESS`].
+pub const SZ_NN_OPTION_MASK: u32 = 32;
+/// Raw stream without an SZIP header; HDF5 always sets it, and it does not change decoding.
+pub const SZ_RAW_OPTION_MASK: u32 = 128;
+
+/// Longest scanline SZIP accepts.
+pub const SZ_MAX_PIXELS_PER_SCANLINE: u32 = 4096;
+
+/// SZIP parameters (`SZ_com_t`), as the HDF5 szip filter records them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SzipParams {
+    /// SZIP options (`SZ_*_OPTION_MASK`).
+    pub options_mask: u32,
+    /// Bits per pixel: 1 to 24, 32 or 64.
+    pub bits_per_pixel: u32,
+    /// Pixels per block: even, at most 32.
+    pub pixels_per_block: u32,
+    /// Pixels per scanline, at most [`SZ_MAX_PIXELS_PER_SCANLINE`].
+    pub pixels_per_scanline: u32,
+}
+
+impl SzipParams {
+    /// Parameters from the client data of an HDF5 szip filter (as `h5dump -p` prints it):
+    /// options mask, pixels per block, bits per pixel and pixels per scanline.
+    pub fn from_hdf5_cd_values(cd_values: &[u32]) -> Result<Self, AecError> {
+        let [options_mask, pixels_per_block, bits_per_pixel, pixels_per_scanline] = *cd_values else {
+            return Err(AecError::InvalidInput("HDF5 szip filter takes 4 client data values"));
+        };
+        let params = Self { options_mask, bits_per_pixel, pixels_per_block, pixels_per_scanline };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Check the parameters against the limits SZIP places on them.
+    pub fn validate(&self) -> Result<(), AecError> {
+        if !matches!(self.bits_per_pixel, 1..=24 | 32 | 64) {
+            return Err(AecError::InvalidInput("szip bits_per_pixel must be 1..=24, 32 or 64"));
+        }
+        if self.pixels_per_block == 0 || self.pixels_per_block > 32 || self.pixels_per_block % 2 != 0 {
+            return Err(AecError::InvalidInput("szip pixels_per_block must be even and at most 32"));
+        }
+        if self.pixels_per_scanline == 0 || self.pixels_per_scanline > SZ_MAX_PIXELS_PER_SCANLINE {
+            return Err(AecError::InvalidInput("szip pixels_per_scanline must be 1..=4096"));
+        }
+        Ok(())
+    }
+
+    /// The parameters of the AEC stream: one scanline per RSI, and 8-bit samples for 32- and
+    /// 64-bit pixels.
+    pub fn aec_params(&self) -> Result<AecParams, AecError> {
+        self.validate()?;
+        let mut flags = AecFlags::empty();
+        flags.set(AecFlags::DATA_PREPROCESS, self.options_mask & SZ_NN_OPTION_MASK != 0);
+        flags.set(AecFlags::MSB, self.options_mask & SZ_MSB_OPTION_MASK != 0);
+        let bits_per_sample = if self.byte_planes() { 8 } else { self.bits_per_pixel as u8 };
+        let rsi = self.pixels_per_scanline.div_ceil(self.pixels_per_block);
+        Ok(AecParams::new(bits_per_sample, self.pixels_per_block, rsi, flags))
+    }
+
+    /// Bytes per pixel in the decoded data.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self.bits_per_pixel {
+            0..=8 => 1,
+            9..=16 => 2,
+            17..=32 => 4,
+            _ => 8,
+        }
+    }
+
+    /// 32- and 64-bit pixels are split into byte planes and coded as bytes.
+    fn byte_planes(&self) -> bool {
+        matches!(self.bits_per_pixel, 32 | 64)
+    }
+}
+
+/// Decode an SZIP stream into `output_len` bytes of pixels, as `SZ_BufftoBuffDecompress` does.
+///
+/// Pixels are `params.bytes_per_pixel()` bytes each (17- to 24-bit pixels take 4), in the byte
+/// order of [`SZ_MSB_OPTION_MASK`]; 32- and 64-bit pixels come back in the byte order they
+/// were compressed in.
+pub fn decode_szip(input: &[u8], params: &SzipParams, output_len: usize) -> Result<Vec<u8>, AecError> {
+    let aec = params.aec_params()?;
+    let sample_size = bytes_per_sample(aec)?;
+    if output_len % params.bytes_per_pixel() != 0 {
+        return Err(AecError::InvalidInput("szip output length is not a whole number of pixels"));
+    }
+    let samples = output_len / sample_size;
+    // Scanlines are coded padded to whole blocks; the padding is dropped again.
+    let line = params.pixels_per_scanline as usize;
+    let padded_line = (aec.rsi * aec.block_size) as usize;
+    let scanlines = samples.div_ceil(line);
+    let coded = if padded_line == line { samples } else { scanlines * padded_line };
+
+    let options = DecodeOptions { allow_nonstandard_block_size: true, ..Default::default() };
+    let mut decoded = decode(input, aec, coded, &options)?;
+    if padded_line != line {
+        let (line_bytes, padded_bytes) = (line * sample_size, padded_line * sample_size);
+        for i in 1..scanlines {
+            decoded.copy_within(i * padded_bytes..i * padded_bytes + line_bytes, i * line_bytes);
+        }
+        decoded.truncate(output_len);
+    }
+    if !params.byte_planes() {
+        return Ok(decoded);
+    }
+    let width = params.bytes_per_pixel();
+    let pixels = output_len / width;
+    let mut out = vec![0u8; output_len];
+    for (plane, bytes) in decoded.chunks_exact(pixels.max(1)).enumerate() {
+        for (pixel, &b) in bytes.iter().enumerate() {
+            out[pixel * width + plane] = b;
+        }
+    }
+    Ok(out)
+}
+
+/// Encode `input` pixels into an SZIP stream, the inverse of [`decode_szip`]: scanlines are
+/// padded to whole blocks by repeating their last pixel, as libaec's `SZ_BufftoBuffCompress`
+/// does.
+///
+/// The encoder codes the CCSDS block sizes 8, 16 and 32 only; other `pixels_per_block` are
+/// [`AecError::Unsupported`].
+pub fn encode_szip(input: &[u8], params: &SzipParams) -> Result<Vec<u8>, AecError> {
+    let aec = params.aec_params()?;
+    let sample_size = bytes_per_sample(aec)?;
+    let width = params.bytes_per_pixel();
+    if input.len() % width != 0 {
+        return Err(AecError::InvalidInput("szip input is not a whole number of pixels"));
+    }
+    let planes: Vec<u8>;
+    let data = if params.byte_planes() {
+        planes = (0..width).flat_map(|plane| input.iter().skip(plane).step_by(width).copied()).collect();
+        &planes[..]
+    } else {
+        input
+    };
+    let line = params.pixels_per_scanline as usize * sample_size;
+    let padded_line = (aec.rsi * aec.block_size) as usize * sample_size;
+    if line == padded_line {
+        return encode(data, aec);
+    }
+    let mut padded = Vec::with_capacity(data.len().div_ceil(line) * padded_line);
+    for scanline in data.chunks(line) {
+        padded.extend_from_slice(scanline);
+        let last = &scanline[scanline.len() - sample_size..];
+        for _ in 0..(padded_line - scanline.len()) / sample_size {
+            padded.extend_from_slice(last);
+        }
+    }
+    encode(&padded, aec)
+}
+
+/// Decode an HDF5 szip-filtered chunk: a little-endian `u32` holding the decoded size, then the
+/// SZIP stream ([`decode_szip`]).
+pub fn decode_hdf5_chunk(chunk: &[u8], params: &SzipParams) -> Result<Vec<u8>, AecError> {
+    let (size, stream) = chunk
+        .split_first_chunk::<4>()
+        .ok_or(AecError::InvalidInput("HDF5 szip chunk is shorter than its size header"))?;
+    decode_szip(stream, params, u32::from_le_bytes(*size) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(options_mask: u32, bits_per_pixel: u32, pixels_per_block: u32, pixels_per_scanline: u32) -> SzipParams {
+        SzipParams { options_mask, bits_per_pixel, pixels_per_block, pixels_per_scanline }
+    }
+
+    #[test]
+    fn szip_options_map_to_aec_params() {
+        let p = params(SZ_RAW_OPTION_MASK | SZ_NN_OPTION_MASK | SZ_MSB_OPTION_MASK | SZ_EC_OPTION_MASK, 12, 16, 100);
+        assert_eq!(p.aec_params().unwrap(), AecParams::new(12, 16, 7, AecFlags::DATA_PREPROCESS | AecFlags::MSB));
+        let wide = params(SZ_LSB_OPTION_MASK, 64, 8, 8);
+        assert_eq!(wide.aec_params().unwrap(), AecParams::new(8, 8, 1, AecFlags::empty()));
+        assert_eq!(params(0, 20, 8, 8).bytes_per_pixel(), 4);
+        for bad in [params(0, 25, 8, 8), params(0, 8, 7, 8), params(0, 8, 34, 8), params(0, 8, 8, 4097)] {
+            assert!(bad.validate().is_err(), "{bad:?}");
+        }
+        assert!(SzipParams::from_hdf5_cd_values(&[141, 32, 16]).is_err());
+    }
+
+    #[test]
+    fn padded_scanlines_are_dropped() {
+        // 3 scanlines of 20 pixels, each coded as 3 blocks of 8 with the last pixel repeated.
+        let p = params(SZ_NN_OPTION_MASK | SZ_MSB_OPTION_MASK, 12, 8, 20);
+        let pixels: Vec<u16> = (0..60).map(|i| 1000 + (i * 37 % 500)).collect();
+        let mut padded = Vec::new();
+        for line in pixels.chunks(20) {
+            for i in 0..24 {
+                padded.extend_from_slice(&line[i.min(19)].to_be_bytes());
+            }
+        }
+        let stream = encode(&padded, p.aec_params().unwrap()).unwrap();
+        let expected: Vec<u8> = pixels.iter().flat_map(|v| v.to_be_bytes()).collect();
+        assert_eq!(decode_szip(&stream, &p, 120).unwrap(), expected);
+        assert_eq!(encode_szip(&expected, &p).unwrap(), stream);
+
+        let chunk = [&120u32.to_le_bytes()[..], &stream].concat();
+        assert_eq!(decode_hdf5_chunk(&chunk, &p).unwrap(), expected);
+        assert!(decode_szip(&stream, &p, 119).is_err());
+        assert!(decode_hdf5_chunk(&chunk[..3], &p).is_err());
+    }
+
+    #[test]
+    fn wide_pixels_are_coded_as_byte_planes() {
+        let p = params(SZ_NN_OPTION_MASK, 32, 16, 64);
+        let pixels: Vec<f32> = (0..64).map(|i| 273.15 + i as f32 * 0.25).collect();
+        let bytes: Vec<u8> = pixels.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let planes: Vec<u8> = (0..4).flat_map(|plane| bytes.iter().skip(plane).step_by(4).copied()).collect();
+        let stream = encode(&planes, p.aec_params().unwrap()).unwrap();
+        assert_eq!(decode_szip(&stream, &p, bytes.len()).unwrap(), bytes);
+        assert_eq!(encode_szip(&bytes, &p).unwrap(), stream);
+    }
+}
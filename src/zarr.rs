@@ -0,0 +1,285 @@
+//! Zarr v3 `aec` and `szip` bytes-to-bytes codecs (feature `zarr`).
+//!
+//! [`ZarrCodec`] is built from the codec metadata of an array (`{"name": ..., "configuration":
+//! {...}}`, as in `zarr.json`) and encodes or decodes whole chunks, so Rust Zarr stores can read
+//! CCSDS-compressed chunks without libaec:
+//!
+//! - `aec`: a raw AEC stream; `bits_per_sample`, `block_size`, `rsi` and `flags` (the
+//!   [`AecFlags`] bits) as in [`AecParams`]. The names imagecodecs uses (`bitspersample`,
+//!   `blocksize`) are accepted too.
+//! - `szip`: an SZIP stream ([`crate::szip`]) with `options_mask`, `pixels_per_block`,
+//!   `bits_per_pixel` and `pixels_per_scanline`.
+//!
+//! Neither stream records its decoded size, so [`ZarrCodec::decode`] takes it from the chunk
+//! representation, as a Zarr implementation knows it.
+//!
+//! With `grib2`, [`decode_grib_chunk`] decodes kerchunk-style references into GRIB2 files: a byte
+//! range holding a template 5.42 Section 7, with the Section 5 keys in the array attributes.
+//!
+//! ```
+//! use rust_aec::zarr::ZarrCodec;
+//!
+//! let metadata = serde_json::json!({
+//!     "name": "aec",
+//!     "configuration": {"bits_per_sample": 16, "block_size": 32, "rsi": 128, "flags": 8}
+//! });
+//! let codec = ZarrCodec::from_metadata(&metadata)?;
+//! let chunk: Vec<u8> = (0..512u16).flat_map(|v| (1000 + v % 7).to_le_bytes()).collect();
+//! let encoded = codec.encode(&chunk)?;
+//! assert_eq!(codec.decode(&encoded, chunk.len())?, chunk);
+//! assert_eq!(ZarrCodec::from_metadata(&codec.to_metadata())?, codec);
+//! # Ok::<(), rust_aec::AecError>(())
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::AecError;
+#[cfg(feature = "grib2")]
+use crate::grib2::{Grib2Scaling, Section5Params};
+use crate::params::{AecFlags, AecParams};
+use crate::szip::{decode_szip, encode_szip, SzipParams};
+
+/// A Zarr v3 AEC or SZIP codec with its configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZarrCodec {
+    /// The `aec` codec.
+    Aec(AecParams),
+    /// The `szip` codec.
+    Szip(SzipParams),
+}
+
+#[derive(Serialize, Deserialize)]
+struct AecConfiguration {
+    #[serde(alias = "bitspersample")]
+    bits_per_sample: u8,
+    #[serde(alias = "blocksize", default = "default_block_size")]
+    block_size: u32,
+    #[serde(default = "default_rsi")]
+    rsi: u32,
+    #[serde(default)]
+    flags: u32,
+}
+
+fn default_block_size() -> u32 {
+    32
+}
+
+fn default_rsi() -> u32 {
+    128
+}
+
+#[derive(Serialize, Deserialize)]
+struct SzipConfiguration {
+    options_mask: u32,
+    pixels_per_block: u32,
+    bits_per_pixel: u32,
+    pixels_per_scanline: u32,
+}
+
+impl ZarrCodec {
+    /// The codec described by its metadata object, `{"name": "aec" | "szip", "configuration":
+    /// {...}}`; `block_size`, `rsi` and `flags` of `aec` default to 32, 128 and 0.
+    pub fn from_metadata(metadata: &Value) -> Result<Self, AecError> {
+        let configuration = metadata.get("configuration").cloned().unwrap_or(Value::Null);
+        let codec = match metadata.get("name").and_then(Value::as_str) {
+            Some("aec") => {
+                let c: AecConfiguration = serde_json::from_value(configuration)
+                    .map_err(|_| AecError::InvalidInput("invalid Zarr aec codec configuration"))?;
+                let flags = AecFlags::from_bits(c.flags).ok_or(AecError::InvalidInput("unknown AEC flags"))?;
+                Self::Aec(AecParams::try_new(c.bits_per_sample, c.block_size, c.rsi, flags)?)
+            }
+            Some("szip") => {
+                let c: SzipConfiguration = serde_json::from_value(configuration)
+                    .map_err(|_| AecError::InvalidInput("invalid Zarr szip codec configuration"))?;
+                let params = SzipParams {
+                    options_mask: c.options_mask,
+                    bits_per_pixel: c.bits_per_pixel,
+                    pixels_per_block: c.pixels_per_block,
+                    pixels_per_scanline: c.pixels_per_scanline,
+                };
+                params.validate()?;
+                Self::Szip(params)
+            }
+            Some(_) => return Err(AecError::Unsupported("Zarr codec other than aec or szip")),
+            None => return Err(AecError::InvalidInput("Zarr codec metadata has no name")),
+        };
+        Ok(codec)
+    }
+
+    /// The codec's metadata object, as [`ZarrCodec::from_metadata`] reads it.
+    pub fn to_metadata(&self) -> Value {
+        let configuration = match *self {
+            Self::Aec(p) => serde_json::to_value(AecConfiguration {
+                bits_per_sample: p.bits_per_sample,
+                block_size: p.block_size,
+                rsi: p.rsi,
+                flags: p.flags.bits(),
+            }),
+            Self::Szip(p) => serde_json::to_value(SzipConfiguration {
+                options_mask: p.options_mask,
+                pixels_per_block: p.pixels_per_block,
+                bits_per_pixel: p.bits_per_pixel,
+                pixels_per_scanline: p.pixels_per_scanline,
+            }),
+        };
+        serde_json::json!({ "name": self.name(), "configuration": configuration.unwrap_or(Value::Null) })
+    }
+
+    /// The codec name, `aec` or `szip`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Aec(_) => "aec",
+            Self::Szip(_) => "szip",
+        }
+    }
+
+    /// Encode a chunk of samples (packed as [`crate::decode`] returns them, or pixels for
+    /// `szip`).
+    pub fn encode(&self, chunk: &[u8]) -> Result<Vec<u8>, AecError> {
+        match self {
+            Self::Aec(p) => crate::encode(chunk, *p),
+            Self::Szip(p) => encode_szip(chunk, p),
+        }
+    }
+
+    /// Decode an encoded chunk into its `decoded_len` bytes.
+    pub fn decode(&self, encoded: &[u8], decoded_len: usize) -> Result<Vec<u8>, AecError> {
+        match self {
+            Self::Aec(p) => {
+                let sample_size = crate::decoder::bytes_per_sample(*p)?;
+                if decoded_len % sample_size != 0 {
+                    return Err(AecError::InvalidInput("chunk size is not a whole number of samples"));
+                }
+                crate::decode(encoded, *p, decoded_len / sample_size)
+            }
+            Self::Szip(p) => decode_szip(encoded, p, decoded_len),
+        }
+    }
+}
+
+/// Template 5.42 keys, named as eccodes (and so kerchunk and cfgrib) names them.
+#[cfg(feature = "grib2")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Template42Attrs {
+    #[serde(alias = "numberOfCodedValues")]
+    number_of_values: usize,
+    reference_value: f32,
+    binary_scale_factor: i16,
+    decimal_scale_factor: i16,
+    bits_per_value: u8,
+    ccsds_flags: u8,
+    ccsds_block_size: u8,
+    ccsds_rsi: u16,
+}
+
+/// The template 5.42 Section 5 described by the eccodes keys in `attrs` (`numberOfValues`,
+/// `referenceValue`, `binaryScaleFactor`, `decimalScaleFactor`, `bitsPerValue`, `ccsdsFlags`,
+/// `ccsdsBlockSize` and `ccsdsRsi`); other attributes are ignored.
+#[cfg(feature = "grib2")]
+pub fn section5_from_attrs(attrs: &Value) -> Result<Section5Params, AecError> {
+    let a: Template42Attrs = Template42Attrs::deserialize(attrs)
+        .map_err(|_| AecError::InvalidInput("attributes lack the GRIB2 template 5.42 keys"))?;
+    Ok(Section5Params {
+        num_values: a.number_of_values,
+        scaling: Grib2Scaling {
+            reference_value: a.reference_value,
+            binary_scale_factor: a.binary_scale_factor,
+            decimal_scale_factor: a.decimal_scale_factor,
+        },
+        bits_per_value: a.bits_per_value,
+        ccsds_flags: a.ccsds_flags,
+        block_size: a.ccsds_block_size,
+        rsi: a.ccsds_rsi,
+    })
+}
+
+/// Decode the chunk a kerchunk-style reference points at: `range` is a GRIB2 Section 7 (with or
+/// without its five header octets), `attrs` the array attributes holding its template 5.42 keys
+/// ([`section5_from_attrs`]). Returns the scaled values of the coded points.
+///
+/// ```
+/// use rust_aec::grib2::{pack_field, section7_bytes, PackOptions};
+/// use rust_aec::zarr::decode_grib_chunk;
+///
+/// let values: Vec<f32> = (0..100).map(|i| 280.0 + i as f32 * 0.1).collect();
+/// let (s5, payload) = pack_field(&values, &PackOptions { decimal_scale_factor: 1, ..Default::default() })?;
+/// let attrs = serde_json::json!({
+///     "numberOfValues": s5.num_values, "referenceValue": s5.scaling.reference_value,
+///     "binaryScaleFactor": s5.scaling.binary_scale_factor, "decimalScaleFactor": 1,
+///     "bitsPerValue": s5.bits_per_value, "ccsdsFlags": s5.ccsds_flags,
+///     "ccsdsBlockSize": s5.block_size, "ccsdsRsi": s5.rsi, "shortName": "2t"
+/// });
+/// let chunk = decode_grib_chunk(&section7_bytes(&payload)?, &attrs)?;
+/// assert!((chunk[42] - 284.2).abs() < 1e-3);
+/// # Ok::<(), rust_aec::AecError>(())
+/// ```
+#[cfg(feature = "grib2")]
+pub fn decode_grib_chunk(range: &[u8], attrs: &Value) -> Result<Vec<f32>, AecError> {
+    let s5 = section5_from_attrs(attrs)?;
+    let payload = match range {
+        [a, b, c, d, 7, payload @ ..] if u32::from_be_bytes([*a, *b, *c, *d]) as usize == range.len() => payload,
+        payload => payload,
+    };
+    crate::grib2::decode_grib2_f32(payload, s5.params(), s5.scaling, s5.num_values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn codecs_round_trip_chunks_from_metadata() {
+        let szip = json!({
+            "name": "szip",
+            "configuration": {
+                "options_mask": 141, "pixels_per_block": 8, "bits_per_pixel": 32, "pixels_per_scanline": 20
+            }
+        });
+        let codec = ZarrCodec::from_metadata(&szip).unwrap();
+        assert_eq!(codec.to_metadata(), szip);
+        let chunk: Vec<u8> = (0..50).flat_map(|i| (i as f32 * 0.5).to_le_bytes()).collect();
+        assert_eq!(codec.decode(&codec.encode(&chunk).unwrap(), chunk.len()).unwrap(), chunk);
+
+        let imagecodecs = json!({"name": "aec", "configuration": {"bitspersample": 12, "blocksize": 16}});
+        assert_eq!(
+            ZarrCodec::from_metadata(&imagecodecs).unwrap(),
+            ZarrCodec::Aec(AecParams::new(12, 16, 128, AecFlags::empty()))
+        );
+        for bad in [
+            json!({"name": "zstd"}),
+            json!({"configuration": {}}),
+            json!({"name": "aec", "configuration": {"bits_per_sample": 12, "flags": 1024}}),
+            json!({"name": "szip", "configuration": {"options_mask": 0}}),
+        ] {
+            assert!(ZarrCodec::from_metadata(&bad).is_err(), "{bad}");
+        }
+        let aec = ZarrCodec::Aec(AecParams::new(12, 16, 128, AecFlags::empty()));
+        assert!(aec.decode(&[], 3).is_err());
+    }
+
+    #[cfg(feature = "grib2")]
+    #[test]
+    fn grib_chunks_decode_with_or_without_the_section_header() {
+        use crate::grib2::{pack_sections, PackOptions};
+
+        let values: Vec<f32> = (0..300).map(|i| 1013.0 - (i % 40) as f32 * 0.5).collect();
+        let (s5, s7) = pack_sections(&values, &PackOptions { decimal_scale_factor: 1, ..Default::default() }).unwrap();
+        let s5 = Section5Params::parse(&s5).unwrap();
+        let mut attrs = json!({
+            "numberOfCodedValues": 300, "referenceValue": s5.scaling.reference_value,
+            "binaryScaleFactor": s5.scaling.binary_scale_factor, "decimalScaleFactor": 1,
+            "bitsPerValue": s5.bits_per_value, "ccsdsFlags": s5.ccsds_flags,
+            "ccsdsBlockSize": s5.block_size, "ccsdsRsi": s5.rsi
+        });
+        assert_eq!(section5_from_attrs(&attrs).unwrap(), s5);
+        let whole = decode_grib_chunk(&s7, &attrs).unwrap();
+        assert_eq!(decode_grib_chunk(&s7[5..], &attrs).unwrap(), whole);
+        assert!(whole.iter().zip(&values).all(|(a, b)| (a - b).abs() < 0.06));
+
+        attrs.as_object_mut().unwrap().remove("ccsdsRsi");
+        assert!(decode_grib_chunk(&s7, &attrs).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Helpers shared by integration tests: hand-crafted AEC bitstreams and a seeded generator.
+#![allow(dead_code)]
+
+/// MSB-first bit writer used to assemble small test payloads.
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    nbits: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, value: u32, nbits: usize) {
+        for i in (0..nbits).rev() {
+            if self.nbits % 8 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 != 0 {
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |= 0x80 >> (self.nbits % 8);
+            }
+            self.nbits += 1;
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Encode `samples` as a sequence of uncompressed blocks (no preprocessing).
+///
+/// `id_len` must match the decoder's option id length for `bits_per_sample`.
+pub fn uncompressed_stream(samples: &[u32], bits_per_sample: usize, block_size: usize, id_len: usize) -> Vec<u8> {
+    let mut w = BitWriter::new();
+    for block in samples.chunks(block_size) {
+        w.put((1u32 << id_len) - 1, id_len);
+        for &s in block {
+            w.put(s, bits_per_sample);
+        }
+    }
+    w.finish()
+}
+
+/// Small deterministic xorshift generator, so failures are reproducible.
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    pub fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize].clone()
+    }
+}
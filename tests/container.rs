@@ -0,0 +1,43 @@
+mod common;
+
+use std::io::Cursor;
+
+use rust_aec::container::{ContainerReader, ContainerWriter};
+use rust_aec::{decode, AecFlags, AecParams};
+
+#[test]
+fn records_round_trip_in_any_order() -> anyhow::Result<()> {
+    let fields: Vec<(AecParams, Vec<u32>)> = vec![
+        (AecParams::new(8, 8, 2, AecFlags::empty()), (0..40).collect()),
+        (AecParams::new(12, 16, 4, AecFlags::MSB), (0..64).map(|i| i * 50).collect()),
+        (AecParams::new(8, 8, 1, AecFlags::empty()), Vec::new()),
+    ];
+    let payloads: Vec<Vec<u8>> = fields
+        .iter()
+        .map(|(p, s)| {
+            let id_len = if p.bits_per_sample > 8 { 4 } else { 3 };
+            common::uncompressed_stream(s, p.bits_per_sample as usize, p.block_size as usize, id_len)
+        })
+        .collect();
+
+    let mut writer = ContainerWriter::new(Cursor::new(Vec::new()))?;
+    for ((params, samples), payload) in fields.iter().zip(&payloads) {
+        writer.add(*params, samples.len() as u64, payload)?;
+    }
+    let bytes = writer.finish()?.into_inner();
+
+    let mut reader = ContainerReader::new(Cursor::new(&bytes))?;
+    assert_eq!(reader.len(), 3);
+    for n in [2, 0, 1] {
+        let (params, samples) = &fields[n];
+        let record = reader.record(n)?;
+        assert_eq!((record.params, record.output_samples, &record.payload), (*params, samples.len() as u64, &payloads[n]));
+        assert_eq!(reader.decode(n)?, decode(&payloads[n], *params, samples.len())?);
+    }
+    assert!(reader.record(3).is_err());
+
+    // Truncation and foreign files are rejected, not misread.
+    assert!(ContainerReader::new(Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+    assert!(ContainerReader::new(Cursor::new(b"GRIB\x02\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0")).is_err());
+    Ok(())
+}
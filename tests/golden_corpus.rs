@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use rust_aec::corpus::{load_case, load_dir, run_case, run_dir};
+use rust_aec::{AecFlags, AecParams};
+
+fn corpus_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+#[test]
+fn golden_corpus_decodes_identically_on_both_paths() -> anyhow::Result<()> {
+    let (cases, failures) = run_dir(&corpus_root())?;
+    assert!(cases >= 3, "corpus has only {cases} cases");
+    assert!(failures.is_empty(), "{failures:#?}");
+    Ok(())
+}
+
+#[test]
+fn manifests_are_parsed_and_mismatches_reported() -> anyhow::Result<()> {
+    let cases = load_dir(&corpus_root())?;
+    let case = cases.iter().find(|c| c.name == "second-extension-split-uncompressed").expect("case present");
+    assert_eq!(case.params, AecParams::new(16, 8, 2, AecFlags::DATA_PREPROCESS | AecFlags::MSB));
+    assert_eq!(case.output_samples, 21);
+
+    // Without `output_samples`, the count follows from expected.bin.
+    let zero_run = load_case(&corpus_root().join("zero-run-preprocess-16bit-msb"))?;
+    assert_eq!(zero_run.output_samples, 64);
+
+    let mut broken = case.clone();
+    broken.expected[3] ^= 1;
+    let failures = run_case(&broken);
+    assert_eq!(failures.iter().map(|f| f.path).collect::<Vec<_>>(), ["one-shot", "streaming"]);
+    assert!(failures[0].message.contains("byte 3"), "{}", failures[0].message);
+    Ok(())
+}
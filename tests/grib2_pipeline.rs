@@ -0,0 +1,54 @@
+mod common;
+
+use rust_aec::grib2::{decode_grib2_grid_f32, Grib2Scaling};
+use rust_aec::pipeline::{decode_grid_f32, encode_parallel};
+use rust_aec::{decode, encode, AecFlags, AecParams, RsiIndex};
+
+#[test]
+fn fused_pipeline_matches_sequential_passes() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..150).map(|i| (i * 37) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 2, AecFlags::empty());
+    let index = RsiIndex::build(&payload, params, samples.len())?;
+    let scaling = Grib2Scaling { reference_value: -40.0, binary_scale_factor: 1, decimal_scale_factor: 1 };
+
+    // Every third grid point is missing.
+    let grid_len = 225usize;
+    let mut bitmap = vec![0u8; grid_len.div_ceil(8)];
+    for i in (0..grid_len).filter(|i| i % 3 != 0) {
+        bitmap[i / 8] |= 0x80 >> (i % 8);
+    }
+
+    let decoded = decode(&payload, params, samples.len())?;
+    let mut values = decoded.iter().map(|&x| scaling.apply(x as i64));
+    let expected: Vec<f32> =
+        (0..grid_len).map(|i| if i % 3 != 0 { values.next().unwrap() } else { f32::MAX }).collect();
+
+    let mut grid = vec![0f32; grid_len];
+    decode_grid_f32(&payload, params, &index, scaling, Some(&bitmap), f32::MAX, &mut grid)?;
+    assert_eq!(grid, expected);
+    assert_eq!(decode_grib2_grid_f32(&payload, params, scaling, Some(&bitmap), grid_len, f32::MAX)?, expected);
+
+    let mut dense = vec![0f32; samples.len()];
+    decode_grid_f32(&payload, params, &index, scaling, None, f32::MAX, &mut dense)?;
+    assert_eq!(dense, decoded.iter().map(|&x| scaling.apply(x as i64)).collect::<Vec<_>>());
+
+    // Wrong bitmap population and a truncated payload are errors, not panics.
+    bitmap[0] |= 0x80;
+    assert!(decode_grid_f32(&payload, params, &index, scaling, Some(&bitmap), f32::MAX, &mut grid).is_err());
+    assert!(decode_grid_f32(&payload[..60], params, &index, scaling, None, f32::MAX, &mut dense).is_err());
+    Ok(())
+}
+
+#[test]
+fn parallel_encoding_matches_one_shot() -> anyhow::Result<()> {
+    let samples: Vec<u8> = (0..100_000u32).flat_map(|i| ((i / 3 + i % 11 * 40) as u16).to_be_bytes()).collect();
+    for flags in [AecFlags::PAD_RSI, AecFlags::empty()] {
+        let params = AecParams::new(16, 32, 8, flags | AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+        let stream = encode_parallel(&samples, params)?;
+        assert_eq!(stream, encode(&samples, params)?);
+        assert_eq!(decode(&stream, params, samples.len() / 2)?, samples);
+    }
+    assert!(encode_parallel(&samples[..3], AecParams::new(16, 32, 8, AecFlags::PAD_RSI)).is_err());
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use rust_aec::grib2::{decode_grib2_field, pack_field, PackOptions};
+use rust_aec::grib_rs::{decode_submessage, is_ccsds};
+
+fn section(number: u8, content: &[u8]) -> Vec<u8> {
+    [&((content.len() + 5) as u32).to_be_bytes()[..], &[number], content].concat()
+}
+
+/// A 4x4 regular lat/lon message with one template 5.42 field and the given Section 6 content.
+fn message(section5: &[u8], section6: &[u8], payload: &[u8]) -> Vec<u8> {
+    let s1 = section(1, &[0, 34, 0, 0, 28, 0, 1, 7, 234, 1, 1, 0, 0, 0, 0, 0]);
+    let mut grid = vec![0u8; 67];
+    grid[1..5].copy_from_slice(&16u32.to_be_bytes());
+    grid[25..29].copy_from_slice(&4u32.to_be_bytes());
+    grid[29..33].copy_from_slice(&4u32.to_be_bytes());
+    let s4 = section(4, &[0; 29]);
+    let body = [s1, section(3, &grid), s4, section5.to_vec(), section(6, section6), section(7, payload)].concat();
+    let total = (16 + body.len() + 4) as u64;
+    [&b"GRIB\0\0\0\x02"[..], &total.to_be_bytes(), &body, b"7777"].concat()
+}
+
+#[test]
+fn ccsds_submessages_decode_like_whole_messages() {
+    let options = PackOptions { decimal_scale_factor: 1, ..Default::default() };
+    let values: Vec<f32> = (0..16).map(|i| 1000.0 - i as f32 * 2.5).collect();
+    // Every point, then the 12 points of a bitmap.
+    for (s6, coded) in [(&[255][..], 16), (&[0, 0b1110_1110, 0b0111_0111], 12)] {
+        let (s5, payload) = pack_field(&values[..coded], &options).unwrap();
+        let data = message(&s5.to_bytes().unwrap(), s6, &payload);
+        let grib2 = grib::from_bytes(&data[..]).unwrap();
+        let (_, submessage) = grib2.iter().next().unwrap();
+        assert!(is_ccsds(&submessage));
+
+        let grid = decode_submessage(&data, &submessage).unwrap();
+        let expected = decode_grib2_field(&data).unwrap();
+        assert_eq!((grid.len(), grid.iter().filter(|v| !v.is_nan()).count()), (16, coded));
+        assert!(grid.iter().zip(&expected).all(|(a, b)| a == b || (a.is_nan() && b.is_nan())));
+        assert!(decode_submessage(&data[..data.len() - 10], &submessage).is_err());
+    }
+}
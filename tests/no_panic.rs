@@ -0,0 +1,78 @@
+//! Panic-detection harness for the decoding entry points: arbitrary payloads, parameters and
+//! options must only ever produce `Ok` or `Err`.
+
+mod common;
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use rust_aec::{
+    decode_into_with_options, decode_no_panic, decode_with_options, AecFlags, AecParams, DecodeStatus, Decoder,
+    DecodeOptions, Flush, MissingValues,
+};
+
+fn run_case(payload: &[u8], params: AecParams, output_samples: usize, options: &DecodeOptions) {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let _ = decode_no_panic(payload, params, output_samples, options);
+        // The other entry points allocate (or take) the whole output, so keep it small.
+        let samples = output_samples.min(4096);
+        let _ = decode_with_options(payload, params, samples, options);
+        for bytes_per_sample in 1..=4 {
+            let mut out = vec![0u8; samples * bytes_per_sample];
+            let _ = decode_into_with_options(payload, params, samples, &mut out, options);
+        }
+        stream(payload, params, samples, options);
+    }));
+    assert!(
+        result.is_ok(),
+        "panic for params={params:?} output_samples={output_samples} options={options:?} payload={payload:02x?}"
+    );
+}
+
+/// Run the streaming decoder over `payload` through a small output buffer.
+fn stream(payload: &[u8], params: AecParams, output_samples: usize, options: &DecodeOptions) {
+    let Ok(mut decoder) = Decoder::with_options(params, output_samples, options) else {
+        return;
+    };
+    decoder.push_input(payload);
+    let mut out = [0u8; 7];
+    for _ in 0..output_samples * 4 + 16 {
+        match decoder.decode(&mut out, Flush::Flush) {
+            Ok((_, DecodeStatus::NeedOutput)) => {}
+            _ => return,
+        }
+    }
+}
+
+#[test]
+fn arbitrary_inputs_never_panic() {
+    let mut rng = common::Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..20_000 {
+        let bits_per_sample = rng.pick(&[0u8, 1, 2, 3, 4, 7, 8, 12, 16, 17, 24, 25, 31, 32, 33, 255]);
+        let block_size = rng.pick(&[0u32, 1, 7, 8, 16, 32, 64, 65, u32::MAX]);
+        let rsi = rng.pick(&[0u32, 1, 2, 3, 64, 128, 4096, u32::MAX]);
+        let flags = AecFlags::from_bits_truncate(rng.next() as u32);
+        let params = AecParams::new(bits_per_sample, block_size, rsi, flags);
+
+        let missing = MissingValues { substitute: -1, primary: Some(0), secondary: Some(3) };
+        let options = DecodeOptions {
+            allow_nonstandard_block_size: rng.below(2) == 0,
+            max_unary_run: rng.pick(&[None, Some(0), Some(1), Some(u32::MAX)]),
+            check_input_length: rng.below(2) == 0,
+            max_blocks: rng.pick(&[None, Some(0), Some(3)]),
+            max_blocks_per_call: rng.pick(&[None, Some(1)]),
+            context: None,
+            crc32c: rng.below(2) == 0,
+            expected_range: rng.pick(&[None, Some(0..=0), Some(-5..=1000)]),
+            state_hash: rng.below(2) == 0,
+            missing_values: rng.pick(&[None, Some(missing)]),
+            widen_3byte: rng.below(2) == 0,
+        };
+
+        let len = rng.below(64) as usize;
+        let fill = rng.pick(&[0x00u8, 0xff, 0x55]);
+        let payload: Vec<u8> = (0..len).map(|_| if rng.below(4) == 0 { fill } else { rng.next() as u8 }).collect();
+        let output_samples = rng.pick(&[0usize, 1, 2, 63, 64, 65, 1000, usize::MAX / 2, usize::MAX]);
+
+        run_case(&payload, params, output_samples, &options);
+    }
+}
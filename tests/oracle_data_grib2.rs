@@ -29,7 +29,7 @@ fn oracle_matches_data_grib2_payload() -> anyhow::Result<()> {
     let num_points = 1_038_240usize;
     assert_eq!(oracle.len(), num_points * 2, "expected 2 bytes/sample for 12-bit samples");
 
-    // From ccsds_dump / aec_oracle_dump on data.grib2.
+    // From `aec-grib extract --grib data.grib2 --message 0`.
     let bits_per_sample = 12u8;
     let block_size = 32u32;
     let rsi = 128u32;
@@ -0,0 +1,59 @@
+mod common;
+
+use rust_aec::{decode, decode_preview, AecFlags, AecParams, Decoder, RsiIndex};
+
+#[test]
+fn decoding_from_an_indexed_rsi_matches_full_decode() -> anyhow::Result<()> {
+    // 67-bit blocks, two per RSI: RSI starts fall mid-byte.
+    let samples: Vec<u32> = (0..52).map(|i| (i * 11) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 2, AecFlags::empty());
+    let full = decode(&payload, params, samples.len())?;
+
+    let index = RsiIndex::build(&payload, params, samples.len())?;
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.bit_offset(1), Some(134));
+
+    for n in 0..index.len() {
+        let first = index.first_sample(n);
+        let mut dec = Decoder::with_index(params, samples.len() - first, &index, n)?;
+        dec.push_input(&payload[index.byte_offset(n).unwrap()..]);
+        assert_eq!(dec.decode_all()?, full[first..], "rsi {n}");
+    }
+
+    let other = AecParams::new(8, 8, 4, AecFlags::empty());
+    assert!(Decoder::with_index(other, 8, &index, 0).is_err());
+    assert!(Decoder::with_index(params, 8, &index, 4).is_err());
+    Ok(())
+}
+
+#[test]
+fn preview_stops_at_an_rsi_boundary() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..52).map(|i| (i * 7) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 2, AecFlags::empty());
+    let full = decode(&payload, params, samples.len())?;
+    let index = RsiIndex::build(&payload, params, samples.len())?;
+
+    for n in 1..index.len() {
+        let (prefix, bit_offset) = decode_preview(&payload, params, samples.len(), n)?;
+        assert_eq!(prefix, full[..n * 16]);
+        assert_eq!(Some(bit_offset), index.bit_offset(n));
+    }
+    // More RSIs than the payload holds yields the whole field.
+    let (all, _) = decode_preview(&payload, params, samples.len(), 10)?;
+    assert_eq!(all, full);
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn index_round_trips_through_serde() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..40).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let index = RsiIndex::build(&payload, AecParams::new(8, 8, 2, AecFlags::empty()), samples.len())?;
+
+    let json = serde_json::to_string(&index)?;
+    assert_eq!(serde_json::from_str::<RsiIndex>(&json)?, index);
+    Ok(())
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use rust_aec::fixed::decode_into_fixed;
+use rust_aec::{decode, AecFlags, AecParams, AecSession};
+
+#[test]
+fn session_matches_one_shot_across_payloads() -> anyhow::Result<()> {
+    let params = AecParams::new(12, 16, 4, AecFlags::MSB);
+    let mut session = AecSession::new(params)?;
+    assert_eq!(session.bytes_per_sample(), 2);
+
+    let mut out = Vec::new();
+    for len in [0usize, 16, 37, 160] {
+        let samples: Vec<u32> = (0..len as u32).map(|i| (i * 97) % 4096).collect();
+        let payload = common::uncompressed_stream(&samples, 12, 16, 4);
+
+        out.resize(session.output_len(len)?, 0);
+        session.decode_into(&payload, len, &mut out)?;
+        assert_eq!(out, decode(&payload, params, len)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn fixed_decode_matches_dynamic() -> anyhow::Result<()> {
+    let params = AecParams::new(12, 16, 4, AecFlags::MSB);
+    let samples: Vec<u32> = (0..70).map(|i| (i * 37) % 4096).collect();
+    let payload = common::uncompressed_stream(&samples, 12, 16, 4);
+    let expected = decode(&payload, params, samples.len())?;
+
+    let mut out = vec![0u8; expected.len()];
+    decode_into_fixed::<16, 2>(&payload, params, samples.len(), &mut out)?;
+    assert_eq!(out, expected);
+    decode_into_fixed::<0, 2>(&payload, params, samples.len(), &mut out)?;
+    assert_eq!(out, expected);
+
+    assert!(decode_into_fixed::<32, 2>(&payload, params, samples.len(), &mut out).is_err());
+    assert!(decode_into_fixed::<16, 4>(&payload, params, samples.len(), &mut out).is_err());
+    Ok(())
+}
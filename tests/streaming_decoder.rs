@@ -1,6 +1,14 @@
+mod common;
+
 use std::path::PathBuf;
 
-use rust_aec::{decode, flags_from_grib2_ccsds_flags, AecParams, DecodeStatus, Decoder, Flush};
+use rust_aec::bitreader::BitReader;
+use rust_aec::io::copy_decode;
+use rust_aec::{
+    decode, decode_block, decode_with_options, decode_with_report, encode, flags_from_grib2_ccsds_flags, AecError,
+    AecFlags, AecParams, DecodeOptions, DecodeReport, DecodeStatus, Decoder, Flush, MissingValues, PredictorState,
+    RsiStats,
+};
 
 fn repo_root() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).canonicalize().unwrap()
@@ -52,7 +60,7 @@ fn streaming_matches_one_shot_on_oracle_payload() -> anyhow::Result<()> {
 
     let payload = std::fs::read(payload_path)?;
 
-    // From ccsds_dump / aec_oracle_dump on data.grib2.
+    // From `aec-grib extract --grib data.grib2 --message 0`.
     let bits_per_sample = 12u8;
     let block_size = 32u32;
     let rsi = 128u32;
@@ -77,3 +85,501 @@ fn streaming_matches_one_shot_on_oracle_payload() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn output_samples_can_grow_before_completion() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..32).map(|i| (i * 7) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 4, AecFlags::empty());
+    let expected = decode(&payload, params, samples.len())?;
+
+    let mut dec = Decoder::new(params, 12)?;
+    dec.push_input(&payload);
+
+    // A tiny output buffer leaves most of the first block pending.
+    let mut out = vec![0u8; 3];
+    let (n, status) = dec.decode(&mut out, Flush::NoFlush)?;
+    assert_eq!((n, status), (3, DecodeStatus::NeedOutput));
+
+    dec.set_output_samples(samples.len())?;
+    let mut got = out[..n].to_vec();
+    got.extend(decode_streaming_rest(&mut dec)?);
+    assert_eq!(got, expected);
+
+    assert!(dec.set_output_samples(64).is_err());
+    Ok(())
+}
+
+#[test]
+fn output_samples_cannot_shrink_below_decoded() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..32).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 4, AecFlags::empty());
+
+    let mut dec = Decoder::new(params, 32)?;
+    dec.push_input(&payload);
+    let mut out = vec![0u8; 1];
+    dec.decode(&mut out, Flush::NoFlush)?;
+
+    assert!(dec.set_output_samples(4).is_err());
+    dec.set_output_samples(8)?;
+    assert_eq!(dec.output_samples(), 8);
+    Ok(())
+}
+
+#[test]
+fn decode_all_matches_one_shot() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..40).map(|i| (i * 13) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 4, AecFlags::empty());
+
+    let mut dec = Decoder::new(params, samples.len())?;
+    dec.push_input(&payload);
+    assert_eq!(dec.decode_all()?, decode(&payload, params, samples.len())?);
+
+    let mut short = Decoder::new(params, samples.len())?;
+    short.push_input(&payload[..payload.len() / 2]);
+    assert!(short.decode_all().is_err());
+    Ok(())
+}
+
+#[test]
+fn samples_iterate_lazily_and_end_with_an_error_on_truncation() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..3000).map(|i| (i * 13) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 4, AecFlags::empty());
+
+    let mut dec = Decoder::new(params, samples.len())?;
+    dec.push_input(&payload);
+    assert_eq!(dec.samples().collect::<Result<Vec<_>, _>>()?, samples);
+    assert!(dec.samples().next().is_none());
+
+    let mut short = Decoder::new(params, samples.len())?;
+    short.push_input(&payload[..payload.len() / 2]);
+    let items: Vec<_> = short.samples().collect();
+    let (last, decoded) = items.split_last().unwrap();
+    assert!(last.is_err() && decoded.len() > samples.len() / 3, "{}", decoded.len());
+    assert!(decoded.iter().zip(&samples).all(|(x, &y)| *x.as_ref().unwrap() == y));
+    Ok(())
+}
+
+#[test]
+fn decode_available_never_errors_on_missing_input() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..24).map(|i| (i * 5) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 4, AecFlags::empty());
+
+    let mut dec = Decoder::new(params, samples.len())?;
+    let mut got = Vec::new();
+    let mut buf = [0u8; 6];
+    assert_eq!(dec.decode_available(&mut buf)?, 0);
+
+    for chunk in payload.chunks(3) {
+        dec.push_input(chunk);
+        loop {
+            let n = dec.decode_available(&mut buf)?;
+            got.extend_from_slice(&buf[..n]);
+            if n < buf.len() {
+                break;
+            }
+        }
+    }
+    assert!(dec.is_finished());
+    assert_eq!(got, decode(&payload, params, samples.len())?);
+    Ok(())
+}
+
+#[test]
+fn block_budget_is_enforced() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..64).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 4, AecFlags::empty());
+
+    let exact = DecodeOptions { max_blocks: Some(8), ..Default::default() };
+    assert_eq!(decode_with_options(&payload, params, 64, &exact)?, decode(&payload, params, 64)?);
+
+    let tight = DecodeOptions { max_blocks: Some(7), ..Default::default() };
+    let err = decode_with_options(&payload, params, 64, &tight).unwrap_err();
+    assert!(matches!(err, AecError::LimitExceeded { limit: 7, .. }));
+
+    let mut dec = Decoder::with_options(params, 64, &tight)?;
+    dec.push_input(&payload);
+    assert!(matches!(dec.decode_all(), Err(AecError::LimitExceeded { .. })));
+
+    // A per-call budget only bounds each call; small output buffers keep calls short.
+    let per_call = DecodeOptions { max_blocks_per_call: Some(1), ..Default::default() };
+    let mut dec = Decoder::with_options(params, 64, &per_call)?;
+    dec.push_input(&payload);
+    let mut out = Vec::new();
+    let mut buf = [0u8; 8];
+    while !dec.is_finished() {
+        let (n, _) = dec.decode(&mut buf, Flush::Flush)?;
+        out.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(out, decode(&payload, params, 64)?);
+
+    let mut dec = Decoder::with_options(params, 64, &per_call)?;
+    dec.push_input(&payload);
+    let mut big = [0u8; 64];
+    assert!(matches!(dec.decode(&mut big, Flush::Flush), Err(AecError::LimitExceeded { .. })));
+    Ok(())
+}
+
+#[test]
+fn reference_sample_split_and_straddling_samples() -> anyhow::Result<()> {
+    let params = AecParams::new(16, 8, 2, AecFlags::DATA_PREPROCESS | AecFlags::MSB);
+    let mut w = common::BitWriter::new();
+    // Second Extension block after the reference sample: the first symbol fills one slot.
+    w.put(0, 4);
+    w.put(1, 1);
+    w.put(1000, 16);
+    for m in [1u32, 2, 0, 4] {
+        w.put(1, m as usize + 1);
+    }
+    // Rice split block, k = 2.
+    w.put(3, 4);
+    for q in [0u32, 1, 0, 2, 0, 0, 1, 0] {
+        w.put(1, q as usize + 1);
+    }
+    for rem in [1u32, 2, 3, 0, 1, 2, 3, 0] {
+        w.put(rem, 2);
+    }
+    // Next RSI: uncompressed block, only partly needed.
+    w.put(15, 4);
+    for v in [7u32, 9, 11, 13, 15, 17, 19, 21] {
+        w.put(v, 16);
+    }
+    let payload = w.finish();
+
+    let mut r = BitReader::new(&payload);
+    let mut state = PredictorState::default();
+    let mut values = Vec::new();
+    for _ in 0..3 {
+        values.extend(decode_block(&mut r, &mut state, params)?.samples);
+    }
+    let expected: Vec<u8> = values[..21].iter().flat_map(|&x| (x as u16).to_be_bytes()).collect();
+    assert_eq!(decode(&payload, params, 21)?, expected);
+
+    for (in_chunk, out_chunk) in [(1usize, 1usize), (2, 3), (5, 5), (64, 64)] {
+        let got = decode_streaming(&payload, params, 21, in_chunk, out_chunk)?;
+        assert_eq!(got, expected, "in_chunk={in_chunk} out_chunk={out_chunk}");
+    }
+
+    // Checksums cover exactly the bytes handed out, however they were chunked.
+    let checked = DecodeOptions { crc32c: true, ..Default::default() };
+    let (_, report) = decode_with_report(&payload, params, 21, &checked)?;
+    assert_eq!(report.crc32c, Some(rust_aec::checksum::crc32c(&expected)));
+    let mut dec = Decoder::with_options(params, 21, &checked)?;
+    dec.push_input(&payload);
+    let mut buf = [0u8; 3];
+    let mut got = dec.decode(&mut buf[..1], Flush::Flush)?.0;
+    got += dec.decode(&mut buf, Flush::Flush)?.0;
+    dec.decode_all()?;
+    assert_eq!((got, dec.report().crc32c), (4, report.crc32c));
+
+    let mut dec = Decoder::with_options(params, 21, &checked)?;
+    let mut sink = Vec::new();
+    dec.push_input(&payload[..7]);
+    let (n, status) = dec.decode_to_writer(&mut sink, Flush::NoFlush)?;
+    assert_eq!((n, status), (sink.len(), DecodeStatus::NeedInput));
+    dec.push_input(&payload[7..]);
+    let (_, status) = dec.decode_to_writer(&mut sink, Flush::Flush)?;
+    assert_eq!(status, DecodeStatus::Finished);
+    assert_eq!(sink, expected);
+    assert_eq!(dec.total_out(), expected.len());
+    assert_eq!(dec.report().crc32c, report.crc32c);
+
+    let mut sink = Vec::new();
+    assert_eq!(rust_aec::io::decode_to_writer(&payload, params, 21, &mut sink)?, expected.len());
+    assert_eq!(sink, expected);
+    Ok(())
+}
+
+#[test]
+fn reference_sample_completing_the_output_ends_a_zero_block() -> anyhow::Result<()> {
+    // Zero block whose reference sample is the last wanted sample; the run length after it is
+    // garbage (an endless unary run) and must not be read.
+    let params = AecParams::new(8, 8, 1, AecFlags::DATA_PREPROCESS);
+    let mut w = common::BitWriter::new();
+    w.put(0, 3);
+    w.put(0, 1);
+    w.put(0xab, 8);
+    for _ in 0..32 {
+        w.put(0, 8);
+    }
+    let payload = w.finish();
+
+    assert_eq!(decode(&payload, params, 1)?, [0xab]);
+    for (in_chunk, out_chunk) in [(1usize, 1usize), (64, 64)] {
+        assert_eq!(decode_streaming(&payload, params, 1, in_chunk, out_chunk)?, [0xab], "in_chunk={in_chunk}");
+    }
+    Ok(())
+}
+
+#[test]
+fn report_counts_pad_rsi_padding() -> anyhow::Result<()> {
+    // One block per RSI: 3 + 8 * 8 = 67 bits, padded by 5 bits to the next byte.
+    let params = AecParams::new(8, 8, 1, AecFlags::PAD_RSI);
+    let mut w = common::BitWriter::new();
+    for block in 0..2u32 {
+        w.put(7, 3);
+        for i in 0..8 {
+            w.put(block * 8 + i, 8);
+        }
+        w.put(0, 5);
+    }
+    let mut payload = w.finish();
+    payload.push(0xff);
+
+    let expected = DecodeReport { bits_consumed: 144, padding_bits: 10, final_alignment_bits: 0, trailing_bytes: 1, context: None, crc32c: None, out_of_range_samples: 0, first_out_of_range: None, state_hash: None };
+    let (out, report) = decode_with_report(&payload, params, 16, &DecodeOptions::default())?;
+    assert_eq!(out, (0..16).collect::<Vec<u8>>());
+    assert_eq!(report, expected);
+
+    let mut dec = Decoder::new(params, 16)?;
+    dec.push_input(&payload);
+    dec.decode_all()?;
+    assert_eq!(dec.report(), expected);
+
+    // Without padding, stopping inside the first block leaves the last byte partially read.
+    let unpadded = AecParams::new(8, 8, 1, AecFlags::empty());
+    let (_, report) = decode_with_report(&payload, unpadded, 2, &DecodeOptions::default())?;
+    assert_eq!((report.bits_consumed, report.padding_bits, report.final_alignment_bits), (19, 0, 5));
+    Ok(())
+}
+
+#[test]
+fn expected_range_counts_out_of_range_samples() -> anyhow::Result<()> {
+    let samples: Vec<u32> = (0..40).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 2, AecFlags::empty());
+    let options = DecodeOptions { expected_range: Some(5..=29), ..Default::default() };
+
+    let (_, report) = decode_with_report(&payload, params, 40, &options)?;
+    assert_eq!((report.out_of_range_samples, report.first_out_of_range), (15, Some(0)));
+    let mut dec = Decoder::with_options(params, 40, &options)?;
+    dec.push_input(&payload);
+    dec.decode_all()?;
+    assert_eq!(dec.report(), report);
+
+    // A zero-block run repeating an out-of-range reference sample: every repeat counts.
+    let params = AecParams::new(8, 8, 4, AecFlags::DATA_PREPROCESS);
+    let mut w = common::BitWriter::new();
+    w.put(0, 4);
+    w.put(200, 8);
+    w.put(1, 2);
+    let payload = w.finish();
+    let options = DecodeOptions { expected_range: Some(0..=100), ..Default::default() };
+    let (out, report) = decode_with_report(&payload, params, 16, &options)?;
+    assert_eq!(out, [200; 16]);
+    assert_eq!((report.out_of_range_samples, report.first_out_of_range), (16, Some(0)));
+    let mut dec = Decoder::with_options(params, 16, &options)?;
+    dec.push_input(&payload);
+    assert_eq!(dec.decode_all()?, out);
+    assert_eq!(dec.report(), report);
+    Ok(())
+}
+
+#[test]
+fn widened_3byte_samples_fill_whole_words() -> anyhow::Result<()> {
+    for flags in [AecFlags::empty(), AecFlags::DATA_SIGNED] {
+        let params = AecParams::new(20, 16, 4, flags | AecFlags::DATA_3BYTE | AecFlags::DATA_PREPROCESS);
+        let samples: Vec<i32> = (-150..150).map(|i: i32| if flags.is_empty() { i.abs() } else { i } * 3001).collect();
+        let packed: Vec<u8> = samples.iter().flat_map(|&v| v.to_le_bytes()[..3].to_vec()).collect();
+        let payload = encode(&packed, params)?;
+        let options = DecodeOptions { widen_3byte: true, ..Default::default() };
+
+        let words = decode_with_options(&payload, params, samples.len(), &options)?;
+        let decoded: Vec<i32> = words.chunks_exact(4).map(|w| i32::from_le_bytes(w.try_into().unwrap())).collect();
+        assert_eq!(decoded, samples, "{flags:?}");
+        let mut dec = Decoder::with_options(params, samples.len(), &options)?;
+        dec.push_input(&payload);
+        assert_eq!(decode_streaming_rest(&mut dec)?, words);
+        // Without the option the samples stay packed.
+        assert_eq!(decode(&payload, params, samples.len())?.len(), samples.len() * 3);
+    }
+    Ok(())
+}
+
+#[test]
+fn missing_values_are_substituted_in_the_output_only() -> anyhow::Result<()> {
+    // Preprocessed, so a substitute leaking into the predictor would shift later samples.
+    let samples: Vec<u8> = (0..200u32).map(|i| ((i * 3) % 50) as u8).collect();
+    let params = AecParams::new(8, 16, 4, AecFlags::DATA_PREPROCESS);
+    let payload = encode(&samples, params)?;
+    let missing = MissingValues { substitute: 255, primary: Some(12), secondary: Some(13) };
+    let options = DecodeOptions { expected_range: Some(0..=40), missing_values: Some(missing), ..Default::default() };
+    let expected: Vec<u8> = samples.iter().map(|&v| if v == 12 || v == 13 || v > 40 { 255 } else { v }).collect();
+
+    let (out, report) = decode_with_report(&payload, params, samples.len(), &options)?;
+    assert_eq!(out, expected);
+    assert_eq!(report.out_of_range_samples, samples.iter().filter(|&&v| v > 40).count());
+    let mut dec = Decoder::with_options(params, samples.len(), &options)?;
+    dec.push_input(&payload);
+    assert_eq!(decode_streaming_rest(&mut dec)?, expected);
+    assert_eq!(dec.report(), report);
+
+    // Repeats of a zero-block run are substituted too.
+    let params = AecParams::new(8, 8, 4, AecFlags::DATA_PREPROCESS);
+    let mut w = common::BitWriter::new();
+    w.put(0, 4);
+    w.put(200, 8);
+    w.put(1, 2);
+    let payload = w.finish();
+    let missing = MissingValues { primary: Some(200), ..missing };
+    let options = DecodeOptions { missing_values: Some(missing), ..Default::default() };
+    assert_eq!(decode_with_options(&payload, params, 16, &options)?, [255; 16]);
+    let mut dec = Decoder::with_options(params, 16, &options)?;
+    dec.push_input(&payload);
+    assert_eq!(decode_streaming_rest(&mut dec)?, [255; 16]);
+    Ok(())
+}
+
+#[test]
+fn state_hash_matches_between_one_shot_and_streaming() -> anyhow::Result<()> {
+    let params = AecParams::new(8, 8, 2, AecFlags::DATA_PREPROCESS | AecFlags::PAD_RSI);
+    let mut w = common::BitWriter::new();
+    // RSI 0: one zero-block run over both blocks, reference 50.
+    w.put(0, 4);
+    w.put(50, 8);
+    w.put(0b01, 2);
+    w.put(0, 2);
+    // RSI 1 and the partly needed RSI 2: uncompressed blocks.
+    for rsi in 0..2u32 {
+        for block in 0..2u32 {
+            w.put(7, 3);
+            for i in 0..8 {
+                w.put(rsi * 40 + block * 8 + i, 8);
+            }
+        }
+        w.put(0, 2);
+    }
+    let payload = w.finish();
+    let options = DecodeOptions { state_hash: true, ..Default::default() };
+
+    let (out, report) = decode_with_report(&payload, params, 40, &options)?;
+    let mut dec = Decoder::with_options(params, 40, &options)?;
+    let mut streamed = Vec::new();
+    let mut buf = [0u8; 3];
+    for byte in &payload {
+        dec.push_input(std::slice::from_ref(byte));
+        loop {
+            let (n, status) = dec.decode(&mut buf, Flush::NoFlush)?;
+            streamed.extend_from_slice(&buf[..n]);
+            if status != DecodeStatus::NeedOutput {
+                break;
+            }
+        }
+    }
+    streamed.extend(decode_streaming_rest(&mut dec)?);
+    assert_eq!(streamed, out);
+    assert_eq!(dec.report().state_hash, report.state_hash);
+
+    // Pinned so that a change in decoder state (or in the hash) across versions is noticed.
+    assert_eq!(report.state_hash, Some(0x8939_c9d7_98fc_0998));
+
+    // Output layout is not decoder state; a different reference sample is.
+    let msb = params.with_byte_order(rust_aec::ByteOrder::BigEndian);
+    assert_eq!(decode_with_report(&payload, msb, 40, &options)?.1.state_hash, report.state_hash);
+    let mut other = payload.clone();
+    other[0] ^= 0x01;
+    assert_ne!(decode_with_report(&other, params, 40, &options)?.1.state_hash, report.state_hash);
+    Ok(())
+}
+
+#[test]
+fn rsi_callback_reports_each_interval() -> anyhow::Result<()> {
+    let params = AecParams::new(8, 8, 2, AecFlags::empty());
+    let mut w = common::BitWriter::new();
+    // RSI 0: one zero block, then an uncompressed block.
+    w.put(0, 3);
+    w.put(0, 1);
+    w.put(1, 1);
+    w.put(7, 3);
+    for v in 1..=8u32 {
+        w.put(v, 8);
+    }
+    // RSI 1: uncompressed, only the first four samples are needed.
+    w.put(7, 3);
+    for v in 100..108u32 {
+        w.put(v, 8);
+    }
+    let payload = w.finish();
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut dec = Decoder::new(params, 20)?;
+    let sink = seen.clone();
+    dec.set_rsi_callback(move |stats| sink.lock().unwrap().push(stats.clone()));
+
+    let mut out = Vec::new();
+    let mut buf = [0u8; 3];
+    for byte in &payload {
+        dec.push_input(std::slice::from_ref(byte));
+        loop {
+            let (n, status) = dec.decode(&mut buf, Flush::NoFlush)?;
+            out.extend_from_slice(&buf[..n]);
+            if status != DecodeStatus::NeedOutput {
+                break;
+            }
+        }
+    }
+    out.extend(decode_streaming_rest(&mut dec)?);
+    assert_eq!(out, decode(&payload, params, 20)?);
+
+    let expected = vec![
+        RsiStats { index: 0, bits: 72, zero_blocks: 1, uncompressed_blocks: 1, samples: 16, min: Some(0), max: Some(8), ..Default::default() },
+        RsiStats { index: 1, bits: 35, uncompressed_blocks: 1, samples: 4, min: Some(100), max: Some(103), ..Default::default() },
+    ];
+    assert_eq!(*seen.lock().unwrap(), expected);
+    Ok(())
+}
+
+#[test]
+fn copy_decode_streams_from_reader_to_writer() -> anyhow::Result<()> {
+    /// Hands out three bytes per call, interrupting every other call.
+    struct Trickle<'a>(&'a [u8], bool);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.1 = !self.1;
+            if self.1 {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            let n = buf.len().min(3).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let samples: Vec<u32> = (0..40).map(|i| (i * 13) % 256).collect();
+    let payload = common::uncompressed_stream(&samples, 8, 8, 3);
+    let params = AecParams::new(8, 8, 2, AecFlags::empty());
+    let expected = decode(&payload, params, samples.len())?;
+
+    let options = DecodeOptions { crc32c: true, ..Default::default() };
+    let mut out = Vec::new();
+    let report = copy_decode(&mut Trickle(&payload, false), &mut out, params, samples.len(), &options)?;
+    assert_eq!(out, expected);
+    assert_eq!(report.crc32c, Some(rust_aec::checksum::crc32c(&expected)));
+
+    // Input ending early is an error, not a hang.
+    let mut out = Vec::new();
+    let err = copy_decode(&mut &payload[..10], &mut out, params, samples.len(), &options).unwrap_err();
+    assert!(matches!(err, AecError::UnexpectedEofDuringDecode { .. }), "{err:?}");
+    Ok(())
+}
+
+fn decode_streaming_rest(dec: &mut Decoder) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut buf = [0u8; 5];
+    loop {
+        let (n, status) = dec.decode(&mut buf, Flush::Flush)?;
+        out.extend_from_slice(&buf[..n]);
+        match status {
+            DecodeStatus::NeedOutput => continue,
+            DecodeStatus::NeedInput => anyhow::bail!("decoder requested more input during Flush"),
+            DecodeStatus::Finished => return Ok(out),
+        }
+    }
+}